@@ -10,6 +10,10 @@ pub mod kw {
     syn::custom_keyword!(skyline);
     syn::custom_keyword!(nnrtld);
     syn::custom_keyword!(name);
+    syn::custom_keyword!(group);
+    syn::custom_keyword!(priority);
+    syn::custom_keyword!(when);
+    syn::custom_keyword!(after);
 }
 
 pub struct KeyValue<Key: Parse, Value: Parse> {
@@ -107,7 +111,21 @@ impl Parse for HookStyle {
 pub struct HookAttributes {
     pub module: Option<KeyValue<kw::module, ModuleArg>>,
     pub style: KeyValue<HookStyle, syn::Expr>,
-    pub force_jit: Option<kw::force_jit>
+    pub force_jit: Option<kw::force_jit>,
+    pub group: Option<KeyValue<kw::group, syn::LitStr>>,
+    pub priority: Option<KeyValue<kw::priority, syn::Expr>>,
+    pub when: Option<KeyValue<kw::when, syn::Expr>>,
+    pub after: Option<kw::after>,
+}
+
+/// Parses an optional trailing argument, emitting an error if it has already been provided
+fn parse_unique<T: Parse>(input: syn::parse::ParseStream, slot: &mut Option<T>, name: &str) -> syn::Result<()> {
+    let span = input.span();
+    if slot.replace(input.parse()?).is_some() {
+        Err(syn::Error::new(span, format!("duplicate `{}` argument", name)))
+    } else {
+        Ok(())
+    }
 }
 
 impl Parse for HookAttributes {
@@ -122,13 +140,38 @@ impl Parse for HookAttributes {
 
         let style = input.parse()?;
 
-        let force_jit = if input.parse::<syn::Token![,]>().is_ok() {
-            Some(input.parse::<kw::force_jit>()?)
-        } else {
-            None
-        };
+        let mut force_jit = None;
+        let mut group = None;
+        let mut priority = None;
+        let mut when = None;
+        let mut after = None;
+
+        // The remaining arguments are optional and can be provided in any order
+        while input.parse::<syn::Token![,]>().is_ok() {
+            if input.is_empty() {
+                break;
+            }
+
+            if input.peek(kw::force_jit) {
+                parse_unique(input, &mut force_jit, "force_jit")?;
+            } else if input.peek(kw::group) {
+                parse_unique(input, &mut group, "group")?;
+            } else if input.peek(kw::priority) {
+                parse_unique(input, &mut priority, "priority")?;
+            } else if input.peek(kw::when) {
+                parse_unique(input, &mut when, "when")?;
+            } else if input.peek(kw::after) {
+                parse_unique(input, &mut after, "after")?;
+            } else {
+                return Err(syn::Error::new(input.span(), "unknown hook argument"));
+            }
+        }
+
+        if !input.is_empty() {
+            return Err(syn::Error::new(input.span(), "expected `,`"));
+        }
 
-        Ok(Self { module, style, force_jit })
+        Ok(Self { module, style, force_jit, group, priority, when, after })
     }
 }
 
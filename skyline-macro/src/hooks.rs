@@ -9,7 +9,9 @@ pub enum HookKind {
     Callback,
     Inline,
     LegacyInline,
-    Hook
+    Hook,
+    PostHook,
+    InlineAction
 }
 
 impl HookKind {
@@ -19,7 +21,9 @@ impl HookKind {
             Self::Callback => syn::parse_quote!(#skyline::hooks::HookType::Callback),
             Self::Inline => syn::parse_quote!(#skyline::hooks::HookType::Inline),
             Self::LegacyInline => syn::parse_quote!(#skyline::hooks::HookType::LegacyInline),
-            Self::Hook => syn::parse_quote!(#skyline::hooks::HookType::Hook)
+            Self::Hook => syn::parse_quote!(#skyline::hooks::HookType::Hook),
+            Self::PostHook => syn::parse_quote!(#skyline::hooks::HookType::PostHook),
+            Self::InlineAction => syn::parse_quote!(#skyline::hooks::HookType::InlineAction)
         }
     }
 
@@ -29,7 +33,9 @@ impl HookKind {
             Self::Callback => "callback",
             Self::Inline => "inline",
             Self::LegacyInline => "legacy_inline",
-            Self::Hook => "hook"
+            Self::Hook => "hook",
+            Self::PostHook => "post_hook",
+            Self::InlineAction => "inline_action"
         }
     }
 }
@@ -174,6 +180,17 @@ fn should_be_jit_hook(attrs: &HookAttributes) -> bool {
     }
 }
 
+/// Checks if a JIT hook has to redirect to an assembly stub generated by us, instead of having the
+/// backend generate the code around the user function
+///
+/// The backend knows nothing about `when` predicates, post hooks or hooks which resume somewhere else,
+/// and its inline frame stops short of the system registers in `InlineCtx`, so those have to be
+/// handled in the stub. The stub is installed as a plain function hook, which the backend can place
+/// on any instruction.
+fn needs_manual_stub(kind: HookKind, attrs: &HookAttributes) -> bool {
+    matches!(kind, HookKind::Inline | HookKind::InlineAction | HookKind::PostHook) || attrs.when.is_some()
+}
+
 /// Emits a compiler error if user has provided a `replace` hooking style (which is an absolute expression)
 /// with a `module` argument
 fn error_module_on_replace(attrs: &HookAttributes) {
@@ -258,12 +275,114 @@ fn push_original_utils(user_fn: &mut syn::ItemFn, base_ident: &syn::Ident, tramp
     Ok(())
 }
 
+/// Generates the priority expression that is passed to the hooking backend, falling back
+/// to the default priority if the user has not provided one
+fn priority_expr(attrs: &HookAttributes, skyline: &syn::Ident) -> TokenStream {
+    match &attrs.priority {
+        Some(priority) => {
+            let value = &priority.value;
+            quote::quote!((#value) as i32)
+        },
+        None => quote::quote!(#skyline::hooks::DEFAULT_HOOK_PRIORITY)
+    }
+}
+
+/// Gets the name of the generated predicate function for a hook
+fn predicate_ident(base_ident: &syn::Ident, kind: HookKind) -> syn::Ident {
+    quote::format_ident!("__skex_codegen_{}_{}_predicate", base_ident, kind.as_str())
+}
+
+/// Generates the `extern "C"` wrapper around the user's `when` predicate, so that it can be called
+/// from the hook assembly regardless of how the user declared it
+fn generate_predicate_fn(attrs: &HookAttributes, predicate_ident: Option<&syn::Ident>) -> TokenStream {
+    let (Some(when), Some(predicate_ident)) = (&attrs.when, predicate_ident) else {
+        return TokenStream::new();
+    };
+
+    let predicate = &when.value;
+
+    quote::quote! {
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        extern "C" fn #predicate_ident() -> bool {
+            (#predicate)()
+        }
+    }
+}
+
+/// Generates the group registration for a hook module if the user has provided a `group` argument
+///
+/// The registration is a static placed into a dedicated linker section, which the `Group` API
+/// in skyline walks at runtime to find every member of a group.
+fn generate_group_member(attrs: &HookAttributes, base_ident: &syn::Ident) -> syn::Result<TokenStream> {
+    let Some(group) = &attrs.group else {
+        return Ok(TokenStream::new());
+    };
+
+    let skyline = crate::get_skyline_crate_name()?;
+    let group = &group.value;
+    let name = syn::LitStr::new(&base_ident.to_string(), base_ident.span());
+
+    Ok(quote::quote! {
+        #[used]
+        #[link_section = "skex_hook_groups"]
+        static __SKEX_GROUP_MEMBER: #skyline::hooks::GroupMember = #skyline::hooks::GroupMember {
+            group: #group,
+            name: #name,
+            install: try_install,
+            uninstall,
+            enable: try_enable,
+            disable: try_disable,
+            is_installed,
+            is_enabled,
+        };
+    })
+}
+
+/// Generates the flag which tracks whether a hook is installed, along with the getters for the
+/// hook's state, which lets a group only roll back the members it changed
+///
+/// `is_enabled` is the expression which reads whether the hook is enabled, since that state is
+/// stored differently by symbol and JIT hooks
+fn generate_state_fns(is_enabled: TokenStream) -> TokenStream {
+    quote::quote! {
+        static __SKEX_IS_INSTALLED: ::std::sync::atomic::AtomicBool = ::std::sync::atomic::AtomicBool::new(false);
+
+        pub fn is_installed() -> bool {
+            __SKEX_IS_INSTALLED.load(::std::sync::atomic::Ordering::Acquire)
+        }
+
+        pub fn is_enabled() -> bool {
+            #is_enabled
+        }
+    }
+}
+
 mod jit_hooks;
 mod symbol_hooks;
 
-fn make_hook_internal(attrs: HookAttributes, mut user_fn: syn::ItemFn, kind: HookKind) -> proc_macro::TokenStream {
+fn make_hook_internal(attrs: HookAttributes, mut user_fn: syn::ItemFn, mut kind: HookKind) -> proc_macro::TokenStream {
     let is_symbol_hook = !should_be_jit_hook(&attrs);
 
+    // An inline hook which returns something is returning an `InlineAction`, and needs
+    // a hook which can change where execution resumes
+    if matches!(kind, HookKind::Inline) && !matches!(user_fn.sig.output, syn::ReturnType::Default) {
+        kind = HookKind::InlineAction;
+    }
+
+    // `#[callback(after)]` is another way of writing `#[post_hook]`
+    if let Some(after) = &attrs.after {
+        if matches!(kind, HookKind::Callback) {
+            kind = HookKind::PostHook;
+        } else {
+            after
+                .span()
+                .unwrap()
+                .error("invalid argument of 'after' on hook which is not a callback -- did you mean `#[callback(.., after)]`?")
+                .emit();
+        }
+    }
+
     error_module_on_replace(&attrs);
 
     // Change our signature ABI to be extern "C", so that we guarantee to be using the proper register layout
@@ -3,19 +3,46 @@ use proc_macro2::{TokenStream, Span};
 use crate::attrs::{ModuleArg, HookAttributes, HookStyle, KnownModule, kw};
 
 use super::HookKind;
+use super::symbol_hooks::{self, ManualHookContext};
 
 struct HookContext {
     base_ident: syn::Ident,
     trampoline_ident: syn::Ident,
+    kind: HookKind,
+
+    /// The assembly stub which the backend redirects to instead of the user function,
+    /// see [`super::needs_manual_stub`]
+    manual: Option<ManualHookContext>,
 }
 
 impl HookContext {
-    pub fn new(base_ident: syn::Ident, kind: HookKind) -> Self {
+    pub fn new(base_ident: syn::Ident, kind: HookKind, attrs: &HookAttributes) -> Self {
         Self {
             trampoline_ident: quote::format_ident!("__skex_codegen_{}_{}_trampoline", base_ident, kind.as_str()),
+            kind,
+            manual: super::needs_manual_stub(kind, attrs).then(|| ManualHookContext::new(base_ident.clone(), kind, attrs)),
             base_ident
         }
     }
+
+    /// Gets the function which the backend redirects to
+    fn replace(&self) -> TokenStream {
+        let ident = match &self.manual {
+            Some(manual) => &manual.manual_ident,
+            None => &self.base_ident,
+        };
+
+        quote::quote!(#ident as *const ())
+    }
+
+    /// Gets the kind of hook which the backend installs. The stub already does everything the
+    /// kind of hook needs, so the backend only has to redirect to it like it does for a function hook.
+    fn hook_type(&self, skyline: &syn::Ident) -> syn::Path {
+        match &self.manual {
+            Some(_) => HookKind::Hook.to_path(skyline),
+            None => self.kind.to_path(skyline),
+        }
+    }
 }
 
 impl KnownModule {
@@ -29,7 +56,7 @@ impl KnownModule {
     }
 }
 
-fn evaluate_hooking_expression(attrs: &HookAttributes, ctx: &HookContext, kind: HookKind) -> syn::Result<TokenStream> {
+fn evaluate_hooking_expression(attrs: &HookAttributes, ctx: &HookContext) -> syn::Result<TokenStream> {
     // We are evaluating the expression, regardless of whether or not it is an absolute
     // or a relative expression, so get it first.
     let offset_expr = &attrs.style.value;
@@ -70,9 +97,10 @@ fn evaluate_hooking_expression(attrs: &HookAttributes, ctx: &HookContext, kind:
             );
 
             // Get all of the idents and paths we will need to generate our code
-            let base_ident = &ctx.base_ident;
+            let replace = ctx.replace();
             let trampoline_ident = &ctx.trampoline_ident;
-            let kind = kind.to_path(&skyline);
+            let kind = ctx.hook_type(&skyline);
+            let priority = super::priority_expr(attrs, &skyline);
 
             // First we try using the static module by name, and then if that doesn't work
             // we will fallback on the dynamic module hooking.
@@ -82,12 +110,14 @@ fn evaluate_hooking_expression(attrs: &HookAttributes, ctx: &HookContext, kind:
                 } else {
                     #skyline::hooks::ffi::skex_hooks_install_on_dynamic_load(
                         (#offset_expr) as usize,
-                        #base_ident as *const (),
+                        #replace,
                         &mut #trampoline_ident as *mut u64 as *mut *mut (),
                         #name.as_ptr(),
-                        #kind
+                        #kind,
+                        #priority
                     );
-                    return;
+                    __SKEX_IS_INSTALLED.store(true, ::std::sync::atomic::Ordering::Release);
+                    return Ok(());
                 }
             })
         }
@@ -141,10 +171,10 @@ fn evaluate_hooking_expression_for_set_enable(attrs: &HookAttributes) -> syn::Re
                     &module.text()[(#offset_expr) as usize] as *const u8
                 } else {
                     let __non_null_name = #name.split_at(#name.len() - 1).0;
-                    if let Some(module) = #skyline::rtld::find_module_by_name(#name.split_at(#name.len() - 1).0) {
+                    if let Some(module) = #skyline::rtld::find_module_by_name(__non_null_name) {
                         module.module_base.add((#offset_expr) as usize) as *const u8
                     } else {
-                        panic!("Dynamic module \"{}\" is not currently loaded, the hook state cannot be changed!", __non_null_name);
+                        return Err(#skyline::hooks::HookError::ModuleNotLoaded(__non_null_name));
                     }
                 }
             })
@@ -152,37 +182,65 @@ fn evaluate_hooking_expression_for_set_enable(attrs: &HookAttributes) -> syn::Re
     }
 }
 
-fn generate_install_fn(attrs: &HookAttributes, ctx: &HookContext, kind: HookKind) -> syn::Result<TokenStream> {
-    let evaluation = evaluate_hooking_expression(attrs, ctx, kind)?;
+fn generate_install_fn(attrs: &HookAttributes, ctx: &HookContext) -> syn::Result<TokenStream> {
+    let evaluation = evaluate_hooking_expression(attrs, ctx)?;
     let skyline = crate::get_skyline_crate_name()?;
 
     let trampoline_ident = &ctx.trampoline_ident;
-    let base_ident = &ctx.base_ident;
-    let kind = kind.to_path(&skyline);
+    let replace = ctx.replace();
+    let kind = ctx.hook_type(&skyline);
+    let priority = super::priority_expr(attrs, &skyline);
+
+    // A stub keeps track of whether it is enabled itself, and needs to know the address it was installed on
+    let (set_hooked_pc, set_enabled) = match &ctx.manual {
+        Some(manual) => {
+            let hooked_pc_ident = &manual.hooked_pc_ident;
+            (quote::quote!(#hooked_pc_ident = __location as *const () as u64;), TokenStream::new())
+        },
+        None => (
+            TokenStream::new(),
+            quote::quote!(__SKEX_IS_ENABLED.store(true, ::std::sync::atomic::Ordering::Release);)
+        ),
+    };
 
     Ok(quote::quote! {
-        pub fn install() {
+        pub fn try_install() -> Result<(), #skyline::hooks::HookError> {
             unsafe {
                 let __location = #evaluation;
+                #set_hooked_pc
                 *(&mut #trampoline_ident as *mut u64 as *mut *const ()) = #skyline::hooks::ffi::skex_hooks_install(
                     __location as *const (),
-                    #base_ident as *const (),
-                    #kind
+                    #replace,
+                    #kind,
+                    #priority
                 );
+
+                if #trampoline_ident == 0 {
+                    Err(#skyline::hooks::HookError::InstallFailed)
+                } else {
+                    #set_enabled
+                    __SKEX_IS_INSTALLED.store(true, ::std::sync::atomic::Ordering::Release);
+                    Ok(())
+                }
             }
         }
+
+        pub fn install() {
+            let _ = try_install();
+        }
     })
 }
 
 fn generate_uninstall_fn(ctx: &HookContext) -> syn::Result<TokenStream> {
     let skyline = crate::get_skyline_crate_name()?;
-    let base_ident = &ctx.base_ident;
+    let replace = ctx.replace();
 
     Ok(quote::quote! {
         pub fn uninstall() {
             unsafe {
-                #skyline::hooks::ffi::skex_hooks_uninstall(#base_ident as *const ());
+                #skyline::hooks::ffi::skex_hooks_uninstall(#replace);
             }
+            __SKEX_IS_INSTALLED.store(false, ::std::sync::atomic::Ordering::Release);
         }
     })
 }
@@ -194,10 +252,18 @@ fn generate_enable_fn(ctx: &HookContext, args: &HookAttributes) -> syn::Result<T
     let expr = evaluate_hooking_expression_for_set_enable(args)?;
 
     Ok(quote::quote! {
-        pub fn enable() {
+        pub fn try_enable() -> Result<(), #skyline::hooks::HookError> {
             unsafe {
                 let __expr = #expr;
                 #skyline::hooks::ffi::skex_hooks_set_enable(#base_ident as *const (), __expr as *const (), true);
+                __SKEX_IS_ENABLED.store(true, ::std::sync::atomic::Ordering::Release);
+                Ok(())
+            }
+        }
+
+        pub fn enable() {
+            if let Err(e) = try_enable() {
+                panic!("{}", e);
             }
         }
     })
@@ -210,29 +276,95 @@ fn generate_disable_fn(ctx: &HookContext, args: &HookAttributes) -> syn::Result<
     let expr = evaluate_hooking_expression_for_set_enable(args)?;
 
     Ok(quote::quote! {
-        pub fn disable() {
+        pub fn try_disable() -> Result<(), #skyline::hooks::HookError> {
             unsafe {
                 let __expr = #expr;
                 #skyline::hooks::ffi::skex_hooks_set_enable(#base_ident as *const (), __expr as *const (), false);
+                __SKEX_IS_ENABLED.store(false, ::std::sync::atomic::Ordering::Release);
+                Ok(())
+            }
+        }
+
+        pub fn disable() {
+            if let Err(e) = try_disable() {
+                panic!("{}", e);
             }
         }
     })
 }
 
 pub fn make_jit_hook(mut user_function: syn::ItemFn, args: HookAttributes, kind: HookKind) -> syn::Result<TokenStream> {
-    let ctx = HookContext::new(user_function.sig.ident.clone(), kind);
+    let ctx = HookContext::new(user_function.sig.ident.clone(), kind, &args);
 
     if matches!(kind, HookKind::Hook) {
         super::push_original_utils(&mut user_function, &ctx.base_ident, &ctx.trampoline_ident)?;
     }
 
-    let install_fn = generate_install_fn(&args, &ctx, kind)?;
+    let group_member = super::generate_group_member(&args, &ctx.base_ident)?;
+    let install_fn = generate_install_fn(&args, &ctx)?;
     let uninstall_fn = generate_uninstall_fn(&ctx)?;
-    let enable_fn = generate_enable_fn(&ctx, &args)?;
-    let disable_fn = generate_disable_fn(&ctx, &args)?;
+
+    // Hooks with a stub are enabled and disabled through their own flag, like symbol hooks, while the
+    // backend does it for the rest
+    let (globals, enable_fn, disable_fn, state_fns, predicate_fn, manual_asm) = match &ctx.manual {
+        Some(manual) => {
+            let is_enabled_ident = &manual.is_enabled_ident;
+            let manual_ident = &manual.manual_ident;
+            let manual_asm = syn::LitStr::new(&symbol_hooks::write_manual_assembly(manual, kind), user_function.sig.ident.span());
+            let globals = symbol_hooks::generate_manual_globals(manual);
+
+            // A hook on a module which was loaded after the hook was installed is only given its address
+            // by the backend, so it is looked up the same way as when enabling the hook
+            let skyline = crate::get_skyline_crate_name()?;
+            let evaluation = evaluate_hooking_expression_for_set_enable(&args)?;
+            let resolve_pc_fn = symbol_hooks::generate_resolve_pc_fn(manual, kind, quote::quote! {{
+                fn __location() -> Result<*const (), #skyline::hooks::HookError> {
+                    unsafe { Ok(#evaluation as *const ()) }
+                }
+
+                __location().ok().map(|location| location as u64)
+            }});
+
+            (
+                quote::quote! {
+                    #globals
+
+                    extern "C" {
+                        fn #manual_ident();
+                    }
+
+                    #resolve_pc_fn
+                },
+                symbol_hooks::generate_enable_fn(manual)?,
+                symbol_hooks::generate_disable_fn(manual)?,
+                super::generate_state_fns(quote::quote! { unsafe { #is_enabled_ident } }),
+                super::generate_predicate_fn(&args, manual.predicate_ident.as_ref()),
+                quote::quote!(std::arch::global_asm!(#manual_asm);),
+            )
+        },
+        None => {
+            let trampoline_ident = &ctx.trampoline_ident;
+
+            (
+                quote::quote! {
+                    #[allow(non_upper_case_globals)]
+                    #[allow(non_snake_case)]
+                    pub(super) static mut #trampoline_ident: u64 = 0;
+
+                    static __SKEX_IS_ENABLED: ::std::sync::atomic::AtomicBool = ::std::sync::atomic::AtomicBool::new(true);
+                },
+                generate_enable_fn(&ctx, &args)?,
+                generate_disable_fn(&ctx, &args)?,
+                super::generate_state_fns(quote::quote! {
+                    __SKEX_IS_ENABLED.load(::std::sync::atomic::Ordering::Acquire)
+                }),
+                TokenStream::new(),
+                TokenStream::new(),
+            )
+        },
+    };
 
     let base_ident = &ctx.base_ident;
-    let trampoline_ident = &ctx.trampoline_ident;
 
     let vis = &user_function.vis;
 
@@ -240,9 +372,7 @@ pub fn make_jit_hook(mut user_function: syn::ItemFn, args: HookAttributes, kind:
         #vis mod #base_ident {
             use super::*;
 
-            #[allow(non_upper_case_globals)]
-            #[allow(non_snake_case)]
-            pub(super) static mut #trampoline_ident: u64 = 0;
+            #globals
 
             #install_fn
 
@@ -251,8 +381,16 @@ pub fn make_jit_hook(mut user_function: syn::ItemFn, args: HookAttributes, kind:
             #enable_fn
 
             #disable_fn
+
+            #state_fns
+
+            #group_member
+
+            #predicate_fn
         }
 
+        #manual_asm
+
         #user_function
     })
-}
\ No newline at end of file
+}
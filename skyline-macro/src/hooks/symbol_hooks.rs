@@ -2,6 +2,9 @@
 //! 
 //! These hooks have to be generated manually and they need their own enabling and trampoline
 //! flags.
+//!
+//! The assembly stubs are also used by the JIT hooks which need more than the backend generates,
+//! see [`super::needs_manual_stub`].
 //! 
 use proc_macro2::TokenStream;
 
@@ -90,37 +93,227 @@ static FPU_REGISTER_RESTORE: &'static str = { r#"
     ldp q30, q31, [sp, #0x2E0]
 "# };
 
+/// Assembly code to backup the condition flags and the floating point control/status registers,
+/// this must be run after the CPU registers are backed up
+static SYSTEM_REGISTER_BACKUP: &str = { r#"
+    mrs x0, nzcv
+    str x0, [sp, #0x300]
+    mrs x0, fpcr
+    str x0, [sp, #0x308]
+    mrs x0, fpsr
+    str x0, [sp, #0x310]
+"# };
+
+/// Assembly code to restore the system registers saved by [`SYSTEM_REGISTER_BACKUP`], this
+/// must be run before the CPU registers are restored
+static SYSTEM_REGISTER_RESTORE: &str = { r#"
+    ldr x0, [sp, #0x300]
+    msr nzcv, x0
+    ldr x0, [sp, #0x308]
+    msr fpcr, x0
+    ldr x0, [sp, #0x310]
+    msr fpsr, x0
+"# };
+
+/// Assembly code to backup the argument registers (and the link register) before calling the hook predicate.
+///
+/// This is only used by hooks which replace a function, which are installed at the start of it, so only the
+/// registers which can be used to pass arguments need to survive the call. Every other kind of hook can be
+/// installed on any instruction, and saves the full context around the predicate instead.
+static PREDICATE_REGISTER_BACKUP: &str = { r#"
+    stp  x0,  x1, [sp, #0x00]
+    stp  x2,  x3, [sp, #0x10]
+    stp  x4,  x5, [sp, #0x20]
+    stp  x6,  x7, [sp, #0x30]
+    stp  x8, x18, [sp, #0x40]
+    str x30, [sp, #0x50]
+    stp  q0,  q1, [sp, #0x60]
+    stp  q2,  q3, [sp, #0x80]
+    stp  q4,  q5, [sp, #0xA0]
+    stp  q6,  q7, [sp, #0xC0]
+"# };
+
+/// Assembly code to restore the registers saved by [`PREDICATE_REGISTER_BACKUP`]
+static PREDICATE_REGISTER_RESTORE: &str = { r#"
+    ldp  x0,  x1, [sp, #0x00]
+    ldp  x2,  x3, [sp, #0x10]
+    ldp  x4,  x5, [sp, #0x20]
+    ldp  x6,  x7, [sp, #0x30]
+    ldp  x8, x18, [sp, #0x40]
+    ldr x30, [sp, #0x50]
+    ldp  q0,  q1, [sp, #0x60]
+    ldp  q2,  q3, [sp, #0x80]
+    ldp  q4,  q5, [sp, #0xA0]
+    ldp  q6,  q7, [sp, #0xC0]
+"# };
+
 /// The context for generating the manual components of a symbol hook
-struct ManualHookContext {
+pub(super) struct ManualHookContext {
     /// The identifier of the user provided function
-    base_ident:       syn::Ident,
+    pub(super) base_ident:       syn::Ident,
 
     /// The identifier for the trampoline global
-    trampoline_ident: syn::Ident,
+    pub(super) trampoline_ident: syn::Ident,
 
     /// The identifier for the global flag for enabling/disabling the hook
-    is_enabled_ident: syn::Ident,
+    pub(super) is_enabled_ident: syn::Ident,
 
     /// The name of the assembly function
-    manual_ident:     syn::Ident,
+    pub(super) manual_ident:     syn::Ident,
 
     /// The name of the assembly jump-to-trampoline label
-    trampoline_name:  String,
+    trampoline_name:             String,
+
+    /// The name of the generated predicate function, if the user provided a `when` argument
+    pub(super) predicate_ident:  Option<syn::Ident>,
+
+    /// The identifier for the global holding the address of the hooked function
+    pub(super) hooked_pc_ident:  syn::Ident,
+
+    /// The identifier for the function which finds the address of the hooked function, for hooks
+    /// which were installed before it was loaded
+    pub(super) resolve_pc_ident: syn::Ident,
 }
 
 impl ManualHookContext {
     /// Constructs a new context from the given base identifier and the hook kind
-    pub fn new(base: syn::Ident, kind: HookKind) -> Self {
+    pub fn new(base: syn::Ident, kind: HookKind, args: &HookAttributes) -> Self {
         Self {
             trampoline_ident: quote::format_ident!("__skex_codegen_{}_{}_trampoline", base, kind.as_str()),
             is_enabled_ident: quote::format_ident!("__skex_codegen_{}_{}_is_enabled", base, kind.as_str()),
             manual_ident: quote::format_ident!("__skex_codegen_{}_manual_{}", base, kind.as_str()),
             trampoline_name: format!("__skex_codegen_{}_{}_jump_to_trampoline", base, kind.as_str()),
+            predicate_ident: args.when.as_ref().map(|_| super::predicate_ident(&base, kind)),
+            hooked_pc_ident: quote::format_ident!("__skex_codegen_{}_{}_hooked_pc", base, kind.as_str()),
+            resolve_pc_ident: quote::format_ident!("__skex_codegen_{}_{}_resolve_pc", base, kind.as_str()),
             base_ident: base
         }
     }
 }
 
+/// Writes the assembly code to backup the condition flags, the floating point control/status registers,
+/// and the hooked address into the context after the CPU registers have been backed up
+fn write_system_register_backup(ctx: &ManualHookContext) -> String {
+    format!(
+    r#"
+            {1}
+            adrp x0, :got:{0}
+            ldr x0, [x0, :got_lo12:{0}]
+            ldr x0, [x0]
+            str x0, [sp, #0x318]
+    "#,
+        ctx.hooked_pc_ident,
+        SYSTEM_REGISTER_BACKUP,
+    )
+}
+
+/// Writes the assembly code to find the hooked address if it was not known when the hook was installed,
+/// this must be run after every register has been backed up
+fn write_hooked_pc_resolve(ctx: &ManualHookContext) -> String {
+    format!(
+    r#"
+            ldr x0, [sp, #0x318]
+            cbnz x0, {0}_pc_known
+            bl {1}
+            str x0, [sp, #0x318]
+        {0}_pc_known:
+    "#,
+        ctx.manual_ident,
+        ctx.resolve_pc_ident,
+    )
+}
+
+/// Writes the call to the `when` predicate, which skips to the trampoline if it returns false
+///
+/// `save_full_context` has to be set for hooks which can be installed in the middle of a function,
+/// since every register (and the condition flags) could be live there
+fn write_predicate_check(ctx: &ManualHookContext, save_full_context: bool) -> String {
+    // If there is no predicate then the only check is the "is enabled" global
+    let Some(predicate_ident) = &ctx.predicate_ident else {
+        return String::new();
+    };
+
+    if save_full_context {
+        // {0}: The name of the predicate function
+        // {1}: The name of our trampoline label that we jump to
+        // {2}: The CPU register backup code
+        // {3}: The system register backup code
+        // {4}: The FPU register backup code
+        // {5}: The system register restore code
+        // {6}: The CPU register restore code
+        // {7}: The FPU register restore code
+        return format!(
+        r#"
+            sub sp, sp, #0x320
+            .cfi_def_cfa_offset 0x320
+
+            {2}
+            .cfi_offset w30, -0x230
+
+            {3}
+
+            {4}
+
+            bl {0}
+
+            // The result is handed over in x16 by storing it where x16 is restored from
+            str x0, [sp, #0x80]
+
+            {5}
+
+            {6}
+
+            {7}
+
+            add sp, sp, #0x320
+            .cfi_def_cfa_offset 0
+            .cfi_restore w30
+
+            // If the predicate returned false, skip the hook entirely
+            tbz w16, #0x0, {1}
+        "#,
+            predicate_ident,
+            ctx.trampoline_name,
+            CPU_REGISTER_BACKUP,
+            SYSTEM_REGISTER_BACKUP,
+            FPU_REGISTER_BACKUP,
+            SYSTEM_REGISTER_RESTORE,
+            CPU_REGISTER_RESTORE,
+            FPU_REGISTER_RESTORE,
+        );
+    }
+
+    // {0}: The name of the predicate function
+    // {1}: The name of our trampoline label that we jump to
+    // {2}: The argument register backup code
+    // {3}: The argument register restore code
+    format!(
+    r#"
+            sub sp, sp, #0xE0
+            .cfi_def_cfa_offset 0xE0
+
+            {2}
+            .cfi_offset w30, -0x90
+
+            bl {0}
+            mov w16, w0
+
+            {3}
+
+            add sp, sp, #0xE0
+            .cfi_def_cfa_offset 0
+            .cfi_restore w30
+
+            // If the predicate returned false, skip the hook entirely
+            tbz w16, #0x0, {1}
+    "#,
+        predicate_ident,
+        ctx.trampoline_name,
+        PREDICATE_REGISTER_BACKUP,
+        PREDICATE_REGISTER_RESTORE,
+    )
+}
+
 fn write_callback_assembly(ctx: &ManualHookContext) -> String {
     // {0}: The name of the user function provided during the callback
     // {1}: The name of our manual hook
@@ -131,6 +324,7 @@ fn write_callback_assembly(ctx: &ManualHookContext) -> String {
     // {6}: The CPU register restore code
     // {7}: The FPU register restore code
     // {8}: The name of our trampoline global
+    // {predicate}: The predicate check code, empty if there is no `when` argument
     format!(
     r#"
         .section .text.{0}, "ax", %progbits
@@ -146,6 +340,8 @@ fn write_callback_assembly(ctx: &ManualHookContext) -> String {
             ldr w16, [x16]
             tbz w16, #0x0, {3}
 
+            {predicate}
+
             sub sp, sp, #0x300
 
             {4}
@@ -181,6 +377,7 @@ fn write_callback_assembly(ctx: &ManualHookContext) -> String {
         CPU_REGISTER_RESTORE,
         FPU_REGISTER_RESTORE,
         ctx.trampoline_ident,
+        predicate = write_predicate_check(ctx, true),
     )
 }
 
@@ -194,6 +391,10 @@ fn write_inline_assembly(ctx: &ManualHookContext) -> String {
     // {6}: The CPU register restore code
     // {7}: The FPU register restore code
     // {8}: The name of our trampoline global
+    // {9}: The system register backup code
+    // {10}: The system register restore code
+    // {predicate}: The predicate check code, empty if there is no `when` argument
+    // {pc}: The code to find the hooked address if it was not known when the hook was installed
     format!(
     r#"
         .section .text.{0}, "ax", %progbits
@@ -209,24 +410,142 @@ fn write_inline_assembly(ctx: &ManualHookContext) -> String {
             ldr w16, [x16]
             tbz w16, #0x0, {3}
 
-            sub sp, sp, #0x300
+            {predicate}
+
+            sub sp, sp, #0x320
 
             {4}
 
-            add x0, sp, #0x300
+            add x0, sp, #0x320
             str x0, [sp, #0xF8]
 
+            {9}
+
             {5}
 
+            {pc}
+
             mov x0, sp
 
             bl {0}
 
+            {10}
+
             {6}
 
             {7}
 
-            add sp, sp, #0x300
+            add sp, sp, #0x320
+        {3}:
+            // If our hook is not enabled, then don't even run the function and jump to the next one
+            adrp x16, :got:{8}
+            ldr x16, [x16, :got_lo12:{8}]
+            ldr x16, [x16]
+            br x16
+        .cfi_endproc
+    "#,
+        ctx.base_ident,
+        ctx.manual_ident,
+        ctx.is_enabled_ident,
+        ctx.trampoline_name,
+        CPU_REGISTER_BACKUP,
+        FPU_REGISTER_BACKUP,
+        CPU_REGISTER_RESTORE,
+        FPU_REGISTER_RESTORE,
+        ctx.trampoline_ident,
+        write_system_register_backup(ctx),
+        SYSTEM_REGISTER_RESTORE,
+        predicate = write_predicate_check(ctx, true),
+        pc = write_hooked_pc_resolve(ctx),
+    )
+}
+
+fn write_inline_action_assembly(ctx: &ManualHookContext) -> String {
+    // {0}: The name of the user function provided during the callback
+    // {1}: The name of our manual hook
+    // {2}: The name of our "is enabled" global
+    // {3}: The name of our trampoline label that we jump to
+    // {4}: The CPU register backup code
+    // {5}: The FPU register backup code
+    // {6}: The CPU register restore code
+    // {7}: The FPU register restore code
+    // {8}: The name of our trampoline global
+    // {9}: The name of our return label
+    // {10}: The system register backup code
+    // {11}: The system register restore code
+    // {predicate}: The predicate check code, empty if there is no `when` argument
+    // {pc}: The code to find the hooked address if it was not known when the hook was installed
+    //
+    // This is the same as the inline hook, except there is an extra 0x10 bytes after the context
+    // to hold the `InlineAction` returned by the user function while the registers are restored.
+    //
+    // skyline resolves the action before the registers are restored, which turns a return from
+    // the hooked function into the caller's registers in the context, the caller's stack pointer
+    // in the context's `sp` and the return address as the action's value
+    format!(
+    r#"
+        .section .text.{0}, "ax", %progbits
+        .global {1}
+        .type {1}, %function
+        .align 2
+        .cfi_startproc
+        {1}:
+            // This is for PIC (Position Independent Code), since our 
+            // globals are stored in the global offset table (got)
+            adrp x16, :got:{2}
+            ldr x16, [x16, :got_lo12:{2}]
+            ldr w16, [x16]
+            tbz w16, #0x0, {3}
+
+            {predicate}
+
+            sub sp, sp, #0x330
+
+            {4}
+
+            add x0, sp, #0x330
+            str x0, [sp, #0xF8]
+
+            {10}
+
+            {5}
+
+            {pc}
+
+            mov x0, sp
+
+            bl {0}
+
+            // The action is returned in x0 (the kind) and x1 (the value)
+            mov x2, x1
+            mov x1, x0
+            mov x0, sp
+            bl skex_hooks_resolve_inline_action
+
+            add x16, sp, #0x320
+            stp x0, x1, [x16]
+
+            {11}
+
+            {6}
+
+            {7}
+
+            add x17, sp, #0x320
+            ldp x16, x17, [x17]
+
+            // None of these branches modify the condition flags, since we could be
+            // anywhere in the function.
+            // 0: Continue, 1: ResumeAt, 2: Return
+            tbnz x16, #0x1, {9}
+            add sp, sp, #0x330
+            cbz x16, {3}
+            br x17
+        {9}:
+            // Returning to the hooked function's caller, with its stack pointer
+            ldr x16, [sp, #0xF8]
+            mov sp, x16
+            br x17
         {3}:
             // If our hook is not enabled, then don't even run the function and jump to the next one
             adrp x16, :got:{8}
@@ -244,6 +563,11 @@ fn write_inline_assembly(ctx: &ManualHookContext) -> String {
         CPU_REGISTER_RESTORE,
         FPU_REGISTER_RESTORE,
         ctx.trampoline_ident,
+        format!("{}_return", ctx.manual_ident),
+        write_system_register_backup(ctx),
+        SYSTEM_REGISTER_RESTORE,
+        predicate = write_predicate_check(ctx, true),
+        pc = write_hooked_pc_resolve(ctx),
     )
 }
 
@@ -255,6 +579,7 @@ fn write_legacy_inline_assembly(ctx: &ManualHookContext) -> String {
     // {4}: The CPU register backup code
     // {5}: The CPU register restore code
     // {6}: The name of our trampoline global
+    // {predicate}: The predicate check code, empty if there is no `when` argument
     format!(
     r#"
         .section .text.{0}, "ax", %progbits
@@ -270,6 +595,8 @@ fn write_legacy_inline_assembly(ctx: &ManualHookContext) -> String {
             ldr w16, [x16]
             tbz w16, #0x0, {3}
 
+            {predicate}
+
             sub sp, sp, #0x100
 
             {4}
@@ -296,6 +623,7 @@ fn write_legacy_inline_assembly(ctx: &ManualHookContext) -> String {
         CPU_REGISTER_BACKUP,
         CPU_REGISTER_RESTORE,
         ctx.trampoline_ident,
+        predicate = write_predicate_check(ctx, true),
     )
 }
 
@@ -305,6 +633,7 @@ fn write_hook_assembly(ctx: &ManualHookContext) -> String {
     // {2}: The name of our "is enabled" global
     // {3}: The name of our trampoline label that we jump to
     // {4}: The name of our trampoline global
+    // {predicate}: The predicate check code, empty if there is no `when` argument
     format!(
     r#"
         .section .text.{0}, "ax", %progbits
@@ -319,6 +648,8 @@ fn write_hook_assembly(ctx: &ManualHookContext) -> String {
             ldr x16, [x16, :got_lo12:{2}]
             ldr w16, [x16]
             tbz w16, #0x0, {3}
+
+            {predicate}
             b {0}
 
         {3}:
@@ -334,6 +665,107 @@ fn write_hook_assembly(ctx: &ManualHookContext) -> String {
         ctx.is_enabled_ident,
         ctx.trampoline_name,
         ctx.trampoline_ident,
+        predicate = write_predicate_check(ctx, false),
+    )
+}
+
+fn write_post_hook_assembly(ctx: &ManualHookContext) -> String {
+    // {0}: The name of the user function provided during the callback
+    // {1}: The name of our manual hook
+    // {2}: The name of our "is enabled" global
+    // {3}: The name of our trampoline label that we jump to
+    // {4}: The name of our trampoline global
+    // {predicate}: The predicate check code, empty if there is no `when` argument
+    //
+    // The original function is called from our own stack frame, so the bottom 0x80 bytes of the frame
+    // are a copy of the arguments which were passed on the stack. Above that is the `PostHookCtx`
+    // structure, and then the frame record so that backtraces can still walk through the post hook.
+    //
+    // The stub is only installed at the start of a function, so x16/x17 and v16/v17 are free to use
+    format!(
+    r#"
+        .section .text.{0}, "ax", %progbits
+        .global {1}
+        .type {1}, %function
+        .align 2
+        .cfi_startproc
+        {1}:
+            // This is for PIC (Position Independent Code), since our 
+            // globals are stored in the global offset table (got)
+            adrp x16, :got:{2}
+            ldr x16, [x16, :got_lo12:{2}]
+            ldr w16, [x16]
+            tbz w16, #0x0, {3}
+
+            {predicate}
+
+            sub sp, sp, #0x1B0
+            .cfi_def_cfa_offset 0x1B0
+            stp x29, x30, [sp, #0x1A0]
+            .cfi_offset w30, -0x8
+            .cfi_offset w29, -0x10
+            add x29, sp, #0x1A0
+
+            add x16, sp, #0x1B0
+            ldp q16, q17, [x16, #0x00]
+            stp q16, q17, [sp, #0x00]
+            ldp q16, q17, [x16, #0x20]
+            stp q16, q17, [sp, #0x20]
+            ldp q16, q17, [x16, #0x40]
+            stp q16, q17, [sp, #0x40]
+            ldp q16, q17, [x16, #0x60]
+            stp q16, q17, [sp, #0x60]
+
+            stp  x0,  x1, [sp, #0x80]
+            stp  x2,  x3, [sp, #0x90]
+            stp  x4,  x5, [sp, #0xA0]
+            stp  x6,  x7, [sp, #0xB0]
+            stp  x8, x16, [sp, #0xC0]
+            stp  q0,  q1, [sp, #0xD0]
+            stp  q2,  q3, [sp, #0xF0]
+            stp  q4,  q5, [sp, #0x110]
+            stp  q6,  q7, [sp, #0x130]
+
+            // Call the original function, the argument registers have not been touched
+            adrp x16, :got:{4}
+            ldr x16, [x16, :got_lo12:{4}]
+            ldr x16, [x16]
+            blr x16
+
+            stp  x0,  x1, [sp, #0x150]
+            stp  q0,  q1, [sp, #0x160]
+            stp  q2,  q3, [sp, #0x180]
+
+            add x0, sp, #0x80
+
+            bl {0}
+
+            // Restore the (possibly modified) return value and go back to the caller
+            ldp  x0,  x1, [sp, #0x150]
+            ldp  q0,  q1, [sp, #0x160]
+            ldp  q2,  q3, [sp, #0x180]
+
+            ldp x29, x30, [sp, #0x1A0]
+            add sp, sp, #0x1B0
+            .cfi_def_cfa_offset 0
+            .cfi_restore w29
+            .cfi_restore w30
+            ret
+
+        {3}:
+            // If our hook is not enabled, then don't even run the function and jump to the next one
+            adrp x16, :got:{4}
+            ldr x16, [x16, :got_lo12:{4}]
+            ldr x16, [x16]
+            br x16
+        .cfi_endproc
+    "#,
+        ctx.base_ident,
+        ctx.manual_ident,
+        ctx.is_enabled_ident,
+        ctx.trampoline_name,
+        ctx.trampoline_ident,
+        predicate = write_predicate_check(ctx, false),
     )
 }
 
@@ -345,23 +777,20 @@ fn generate_install_fn(ctx: &ManualHookContext, args: HookAttributes, kind: Hook
     let ManualHookContext {
         manual_ident,
         trampoline_ident,
+        hooked_pc_ident,
         ..
     } = ctx;
 
     // Get the hook kind as a path
     let kind = kind.to_path(&skyline);
 
+    // Get the priority to install the hook with
+    let priority = super::priority_expr(&args, &skyline);
+
     // Here, we are performing the check to see if the provided expression is a string
     // If it is, then we are to assume that we are installing this on a symbol which is not yet resolved.
     let function_expr = &args.style.value;
-
-    let future_symbol = match function_expr {
-        syn::Expr::Lit(lit) => match &lit.lit {
-            syn::Lit::Str(str) => Some(str),
-            _ => None
-        },
-        _ => None
-    };
+    let future_symbol = get_future_symbol(&args);
 
     // Our call to the FFI export is different depending on whether or not we are using
     // future symbol
@@ -374,14 +803,19 @@ fn generate_install_fn(ctx: &ManualHookContext, args: HookAttributes, kind: Hook
         );
 
         // Generate the future symbol hook call
+        //
+        // The symbol might not be resolved yet, so an unset trampoline is not a failure here
         quote::quote! {
             #skyline::hooks::ffi::skex_hooks_install_on_symbol_future(
                 self_object,
                 #future_symbol.as_ptr(),
                 #manual_ident as *const (),
                 &mut #trampoline_ident as *mut u64 as *mut *mut (),
-                #kind
+                #kind,
+                #priority
             );
+            __SKEX_IS_INSTALLED.store(true, ::std::sync::atomic::Ordering::Release);
+            Ok(())
         }
     } else {
         // We don't have to do anything special, just use the user expression
@@ -389,19 +823,28 @@ fn generate_install_fn(ctx: &ManualHookContext, args: HookAttributes, kind: Hook
         // Note that since we are *not* a JIT hook, we don't have to attempt to evaluate an offset from a module or anything
         // and it is up to the user to know that if they are providing non-function under `replace` then they need to pass `force_jit`
         quote::quote! {
+            #hooked_pc_ident = (#function_expr) as *const () as u64;
             #skyline::hooks::ffi::skex_hooks_install_on_symbol(
                 self_object,
                 (#function_expr) as *const (),
                 #manual_ident as *const (),
                 &mut #trampoline_ident as *mut u64 as *mut *mut (),
-                #kind
+                #kind,
+                #priority
             );
+
+            if #trampoline_ident == 0 {
+                Err(#skyline::hooks::HookError::InstallFailed)
+            } else {
+                __SKEX_IS_INSTALLED.store(true, ::std::sync::atomic::Ordering::Release);
+                Ok(())
+            }
         }
     };
 
     // We have to extern "C" the manual ident since it is declared in assembly
     Ok(quote::quote! {
-        pub fn install() {
+        pub fn try_install() -> Result<(), #skyline::hooks::HookError> {
             extern "C" {
                 fn #manual_ident();
             }
@@ -413,6 +856,10 @@ fn generate_install_fn(ctx: &ManualHookContext, args: HookAttributes, kind: Hook
                 #ffi_function_call
             }
         }
+
+        pub fn install() {
+            let _ = try_install();
+        }
     })
 }
 
@@ -430,35 +877,126 @@ fn generate_uninstall_fn(ctx: &ManualHookContext) -> syn::Result<TokenStream> {
             unsafe {
                 #skyline::hooks::ffi::skex_hooks_uninstall_from_symbol(#manual_ident as *const ());
             }
+            __SKEX_IS_INSTALLED.store(false, ::std::sync::atomic::Ordering::Release);
         }
     })
 }
 
-fn generate_enable_fn(ctx: &ManualHookContext) -> TokenStream {
-    // This one doesn't return a result since there is no FFI here
-    // since we are the ones in control over the is enabled global
+pub(super) fn generate_enable_fn(ctx: &ManualHookContext) -> syn::Result<TokenStream> {
+    // The fallible version can never actually fail since there is no FFI here
+    // since we are the ones in control over the is enabled global, it only exists
+    // so that the API matches the JIT hooks
+    let skyline = crate::get_skyline_crate_name()?;
     let is_enabled_ident = &ctx.is_enabled_ident;
 
-    quote::quote! {
+    Ok(quote::quote! {
+        pub fn try_enable() -> Result<(), #skyline::hooks::HookError> {
+            enable();
+            Ok(())
+        }
+
         pub fn enable() {
             unsafe {
                 #is_enabled_ident = true;
             }
         }
-    }
+    })
 }
 
-fn generate_disable_fn(ctx: &ManualHookContext) -> TokenStream {
-    // This one doesn't return a result since there is no FFI here
-    // since we are the ones in control over the is enabled global
+pub(super) fn generate_disable_fn(ctx: &ManualHookContext) -> syn::Result<TokenStream> {
+    // The fallible version can never actually fail since there is no FFI here
+    // since we are the ones in control over the is enabled global, it only exists
+    // so that the API matches the JIT hooks
+    let skyline = crate::get_skyline_crate_name()?;
     let is_enabled_ident = &ctx.is_enabled_ident;
 
-    quote::quote! {
+    Ok(quote::quote! {
+        pub fn try_disable() -> Result<(), #skyline::hooks::HookError> {
+            disable();
+            Ok(())
+        }
+
         pub fn disable() {
             unsafe {
                 #is_enabled_ident = false;
             }
         }
+    })
+}
+
+/// Writes the assembly stub for a kind of hook, which is what the backend redirects to
+pub(super) fn write_manual_assembly(ctx: &ManualHookContext, kind: HookKind) -> String {
+    match kind {
+        HookKind::Callback => write_callback_assembly(ctx),
+        HookKind::Inline => write_inline_assembly(ctx),
+        HookKind::InlineAction => write_inline_action_assembly(ctx),
+        HookKind::LegacyInline => write_legacy_inline_assembly(ctx),
+        HookKind::Hook => write_hook_assembly(ctx),
+        HookKind::PostHook => write_post_hook_assembly(ctx),
+    }
+}
+
+/// Generates the globals which the assembly stub reads, they are not mangled so that the
+/// assembly can refer to them by name
+pub(super) fn generate_manual_globals(ctx: &ManualHookContext) -> TokenStream {
+    let ManualHookContext {
+        trampoline_ident,
+        is_enabled_ident,
+        hooked_pc_ident,
+        ..
+    } = ctx;
+
+    quote::quote! {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        #[allow(non_snake_case)]
+        pub(super) static mut #trampoline_ident: u64 = 0;
+
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        #[allow(non_snake_case)]
+        static mut #is_enabled_ident: bool = true;
+
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        #[allow(non_snake_case)]
+        static mut #hooked_pc_ident: u64 = 0;
+    }
+}
+
+/// Generates the function which the inline hook stubs call to find the hooked address when it is not known,
+/// which caches it in the hooked address global
+///
+/// `location` is the expression which finds the address as an `Option<u64>`
+pub(super) fn generate_resolve_pc_fn(ctx: &ManualHookContext, kind: HookKind, location: TokenStream) -> TokenStream {
+    if !matches!(kind, HookKind::Inline | HookKind::InlineAction) {
+        return TokenStream::new();
+    }
+
+    let ManualHookContext {
+        hooked_pc_ident,
+        resolve_pc_ident,
+        ..
+    } = ctx;
+
+    quote::quote! {
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        extern "C" fn #resolve_pc_ident() -> u64 {
+            unsafe {
+                #hooked_pc_ident = (#location).unwrap_or(0);
+                #hooked_pc_ident
+            }
+        }
+    }
+}
+
+/// Gets the name of the symbol to hook if it is provided as a string, in which case it might
+/// not be resolved yet when the hook is installed
+fn get_future_symbol(args: &HookAttributes) -> Option<&syn::LitStr> {
+    match &args.style.value {
+        syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(str), .. }) => Some(str),
+        _ => None
     }
 }
 
@@ -468,36 +1006,40 @@ pub fn make_symbol_hook(
     kind: HookKind
 ) -> syn::Result<TokenStream> {
     // Construct a new hook context
-    let ctx = ManualHookContext::new(user_function.sig.ident.clone(), kind);
+    let ctx = ManualHookContext::new(user_function.sig.ident.clone(), kind, &args);
+
+    // If we are a hook, we are also inserting the original macros/function
+    if matches!(kind, HookKind::Hook) {
+        super::push_original_utils(&mut user_function, &ctx.base_ident, &ctx.trampoline_ident)?;
+    }
 
     // Generate the assembly string to use for the global asm
-    let asm_string = match kind {
-        HookKind::Callback => write_callback_assembly(&ctx),
-        HookKind::Inline => write_inline_assembly(&ctx),
-        HookKind::LegacyInline => write_legacy_inline_assembly(&ctx),
-        HookKind::Hook => {
-            // If we are a hook, we are also inserting the original macros/function
-            super::push_original_utils(&mut user_function, &ctx.base_ident, &ctx.trampoline_ident)?;
-            write_hook_assembly(&ctx)
-        }
-    };
+    let asm_string = write_manual_assembly(&ctx, kind);
 
     // Convert the string into a string literal for tokenization
     let manual_asm = syn::LitStr::new(&asm_string, user_function.sig.ident.span());
 
     // Get all of the module functions
+    let group_member = super::generate_group_member(&args, &ctx.base_ident)?;
+    let predicate_fn = super::generate_predicate_fn(&args, ctx.predicate_ident.as_ref());
+    let skyline = crate::get_skyline_crate_name()?;
+    let location = match get_future_symbol(&args) {
+        Some(name) => quote::quote!(#skyline::rtld::find_symbol_by_name(#name)),
+        None => {
+            let function_expr = &args.style.value;
+            quote::quote!(Some((#function_expr) as *const () as u64))
+        }
+    };
+    let resolve_pc_fn = generate_resolve_pc_fn(&ctx, kind, location);
     let install_fn = generate_install_fn(&ctx, args, kind)?;
     let uninstall_fn = generate_uninstall_fn(&ctx)?;
-    let enable_fn = generate_enable_fn(&ctx);
-    let disable_fn = generate_disable_fn(&ctx);
+    let enable_fn = generate_enable_fn(&ctx)?;
+    let disable_fn = generate_disable_fn(&ctx)?;
+    let is_enabled_ident = &ctx.is_enabled_ident;
+    let state_fns = super::generate_state_fns(quote::quote! { unsafe { #is_enabled_ident } });
+    let globals = generate_manual_globals(&ctx);
 
-    // Extract the required context elements to make the module
-    let ManualHookContext {
-        base_ident,
-        trampoline_ident,
-        is_enabled_ident,
-        ..
-    } = &ctx;
+    let base_ident = &ctx.base_ident;
 
     // Use the user provided visibility on the hook
     let vis = &user_function.vis;
@@ -507,15 +1049,7 @@ pub fn make_symbol_hook(
         #vis mod #base_ident {
             use super::*;
 
-            #[no_mangle]
-            #[allow(non_upper_case_globals)]
-            #[allow(non_snake_case)]
-            pub(super) static mut #trampoline_ident: u64 = 0;
-
-            #[no_mangle]
-            #[allow(non_upper_case_globals)]
-            #[allow(non_snake_case)]
-            static mut #is_enabled_ident: bool = true;
+            #globals
 
             #install_fn
 
@@ -524,6 +1058,14 @@ pub fn make_symbol_hook(
             #enable_fn
 
             #disable_fn
+
+            #state_fns
+
+            #group_member
+
+            #predicate_fn
+
+            #resolve_pc_fn
         }
 
         std::arch::global_asm!(#manual_asm);
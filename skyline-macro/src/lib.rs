@@ -35,6 +35,11 @@ pub fn callback(attr: TokenStream, item: TokenStream) -> TokenStream {
     hooks::make_hook(attr, item, hooks::HookKind::Callback)
 }
 
+#[proc_macro_attribute]
+pub fn post_hook(attr: TokenStream, item: TokenStream) -> TokenStream {
+    hooks::make_hook(attr, item, hooks::HookKind::PostHook)
+}
+
 #[proc_macro_attribute]
 pub fn shim(attr: TokenStream, item: TokenStream) -> TokenStream {
     hooks::make_shim(attr, item)
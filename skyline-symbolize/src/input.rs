@@ -0,0 +1,197 @@
+use serde::Deserialize;
+
+use crate::SymbolizeError;
+
+/// A module as serialized in a crash dump
+#[derive(Deserialize)]
+pub struct DumpModule {
+    pub name: String,
+    pub base: String,
+    pub size: String,
+    pub build_id: String,
+}
+
+/// A frame as serialized in a crash dump
+#[derive(Deserialize)]
+pub struct DumpFrame {
+    pub address: String,
+    pub module: Option<usize>,
+    pub module_offset: Option<String>,
+    pub symbol: Option<String>,
+    pub symbol_offset: Option<String>,
+}
+
+/// The subset of a crash dump which is needed to symbolize it
+#[derive(Deserialize)]
+pub struct CrashDump {
+    pub version: u32,
+    pub program_id: String,
+    pub reason: Option<String>,
+    pub lr: String,
+    pub modules: Vec<DumpModule>,
+    pub frames: Vec<DumpFrame>,
+    pub error: Option<String>,
+}
+
+impl CrashDump {
+    /// Parses a crash dump from its JSON serialization
+    pub fn parse(input: &str) -> Result<Self, SymbolizeError> {
+        let dump: Self = serde_json::from_str(input)?;
+        if dump.version != 1 {
+            return Err(SymbolizeError::UnsupportedDumpVersion(dump.version));
+        }
+        Ok(dump)
+    }
+}
+
+/// Parses a hexadecimal number with an optional `0x` prefix
+pub fn parse_hex(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// Parses a hexadecimal build id
+///
+/// # Returns
+/// `None` if the id has an odd number of digits or any character which isn't a hex digit
+pub fn parse_build_id(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let digit = |byte: u8| (byte as char).to_digit(16);
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| Some((digit(pair[0])? << 4 | digit(pair[1])?) as u8))
+        .collect()
+}
+
+/// Finds the first `(module + 0x...)` frame in a line of a crash log
+///
+/// # Returns
+/// The module name, the offset of the frame from the module, and the byte index just past
+/// the closing parenthesis, so that the resolved frame can be printed after it
+pub fn parse_log_frame(line: &str) -> Option<(&str, u64, usize)> {
+    let mut search = 0;
+    while let Some(start) = line[search..].find('(') {
+        let start = search + start + 1;
+        let end = start + line[start..].find(')')?;
+        search = start;
+
+        let Some((name, offset)) = line[start..end].split_once(" + ") else {
+            continue;
+        };
+
+        // Symbols are formatted the same way, but module names never contain spaces or colons
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ':') {
+            continue;
+        }
+
+        if let Some(offset) = offset.strip_prefix("0x").and_then(parse_hex) {
+            return Some((name, offset, end + 1));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The output of `skyline::crash::CrashDump::to_json`, which the skyline crate checks it
+    /// still produces byte for byte
+    const CRASH_DUMP: &str = include_str!("../testdata/crash_dump.json");
+
+    #[test]
+    fn hex() {
+        assert_eq!(parse_hex("0x8123456"), Some(0x8123456));
+        assert_eq!(parse_hex("01006a800016e000"), Some(0x01006a800016e000));
+        assert_eq!(parse_hex(" 0xFF\n"), Some(0xff));
+        assert_eq!(parse_hex("0xffffffffffffffff"), Some(u64::MAX));
+        assert_eq!(parse_hex("0x10000000000000000"), None);
+        assert_eq!(parse_hex("0x"), None);
+        assert_eq!(parse_hex("0xg"), None);
+        assert_eq!(parse_hex(""), None);
+    }
+
+    #[test]
+    fn build_id() {
+        assert_eq!(parse_build_id(""), Some(Vec::new()));
+        assert_eq!(parse_build_id("1f2E3d"), Some(vec![0x1f, 0x2e, 0x3d]));
+
+        // Module ids are padded with zeroes, which have to be kept so the length matches
+        assert_eq!(parse_build_id("ab000000"), Some(vec![0xab, 0, 0, 0]));
+
+        assert_eq!(parse_build_id("1f2"), None);
+        assert_eq!(parse_build_id("1fx23d"), None);
+        assert_eq!(parse_build_id("+1f2"), None);
+        assert_eq!(parse_build_id("1f é"), None);
+        assert_eq!(parse_build_id("éé"), None);
+    }
+
+    #[test]
+    fn log_frame() {
+        let line = "0000000008123456 (main + 0x11f456) (_ZN4Game6UpdateEv + 0x40) [fp: 0x3fff1230]";
+        let end = line.find(')').unwrap() + 1;
+        assert_eq!(parse_log_frame(line), Some(("main", 0x11f456, end)));
+
+        // Demangled symbols are skipped, even when their parentheses are nested
+        let line = "0000000009a01234 (<(u8, u16) as core::fmt::Debug>::fmt + 0x4) (libplugin.nro + 0x1234)";
+        assert_eq!(parse_log_frame(line), Some(("libplugin.nro", 0x1234, line.len())));
+        let line = "0000000009a01234 (fn(u8) + 0x4) (closure (u8) + 0x8) (main + 0x20)";
+        assert_eq!(parse_log_frame(line), Some(("main", 0x20, line.len())));
+
+        assert_eq!(parse_log_frame("0000000008123456 (main + 20)"), None);
+        assert_eq!(parse_log_frame("0000000008123456 ( + 0x20)"), None);
+        assert_eq!(parse_log_frame("0000000008123456 (main + 0x20"), None);
+        assert_eq!(parse_log_frame("Thread 'main' panicked (no frames)"), None);
+        assert_eq!(parse_log_frame(""), None);
+    }
+
+    #[test]
+    fn parse_crash_dump() {
+        let dump = CrashDump::parse(CRASH_DUMP).unwrap();
+        assert_eq!(dump.version, 1);
+        assert_eq!(parse_hex(&dump.program_id), Some(0x01006a800016e000));
+        assert_eq!(
+            dump.reason.as_deref(),
+            Some("Thread 'main' panicked at src\\lib.rs:10:5:\n\t\"index out of bounds\"")
+        );
+        assert_eq!(parse_hex(&dump.lr), Some(0x8123456));
+        assert_eq!(dump.error.as_deref(), Some("The frame pointer is null"));
+
+        assert_eq!(dump.modules.len(), 2);
+        assert_eq!(dump.modules[0].name, "main");
+        assert_eq!(parse_hex(&dump.modules[0].base), Some(0x8004000));
+        assert_eq!(parse_hex(&dump.modules[0].size), Some(0x2a4b000));
+        assert_eq!(
+            parse_build_id(&dump.modules[0].build_id).unwrap(),
+            [
+                0x1f, 0x2e, 0x3d, 0x4c, 0x5b, 0x6a, 0x79, 0x88, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66,
+                0x77, 0x88, 0x99, 0xaa, 0xbb
+            ]
+        );
+        assert_eq!(dump.modules[1].name, "libplugin.nro");
+        assert_eq!(parse_build_id(&dump.modules[1].build_id), Some(Vec::new()));
+
+        assert_eq!(dump.frames.len(), 3);
+        let frame = &dump.frames[0];
+        assert_eq!(parse_hex(&frame.address), Some(0x8123456));
+        assert_eq!(frame.module, Some(0));
+        assert_eq!(frame.module_offset.as_deref().and_then(parse_hex), Some(0x11f456));
+        assert_eq!(frame.symbol.as_deref(), Some("_ZN4Game6UpdateEv"));
+        assert_eq!(frame.symbol_offset.as_deref().and_then(parse_hex), Some(0x40));
+
+        let frame = &dump.frames[1];
+        assert_eq!(frame.module, Some(1));
+        assert_eq!(frame.module_offset.as_deref().and_then(parse_hex), Some(0x1234));
+        assert!(frame.symbol.is_none() && frame.symbol_offset.is_none());
+
+        let frame = &dump.frames[2];
+        assert_eq!(parse_hex(&frame.address), Some(0xdeadbeef));
+        assert!(frame.module.is_none() && frame.module_offset.is_none());
+    }
+}
@@ -0,0 +1,206 @@
+//! Symbolizes skyline crash logs and crash dumps offline.
+//!
+//! Every frame which refers to a module, such as `main + 0x1a2b3c`, is resolved to the
+//! function, source file, and line it belongs to using the ELF or NSO files of the modules.
+//! DWARF line tables are used when the ELF has them, otherwise only the symbol table is.
+//!
+//! ```text
+//! skyline-symbolize <crash log or dump> [name=]<module path>...
+//! ```
+use std::io::{Read, Write};
+use std::path::Path;
+
+use thiserror::Error;
+
+mod input;
+mod module;
+
+use input::{parse_build_id, parse_hex, parse_log_frame, CrashDump};
+use module::{Location, Module};
+
+const USAGE: &str = "\
+usage: skyline-symbolize <crash log or dump> [name=]<module path>...
+
+Resolves the `module + 0x...` frames of a crash log, or the frames of a JSON crash dump,
+to functions, files, and lines using the ELF or NSO files of the modules. Modules are
+matched by build id when symbolizing a dump, and by file name otherwise. Prefix a path
+with `name=` to match it against a different module name. Pass `-` to read from stdin.";
+
+/// The indentation of resolved locations, which lines them up under the frame addresses
+const INDENT: &str = "            ";
+
+/// An error which happened while symbolizing a crash log
+#[derive(Error, Debug)]
+pub enum SymbolizeError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse the module: {0}")]
+    Object(#[from] object::Error),
+    #[error("Failed to read the debug info: {0}")]
+    Dwarf(#[from] gimli::Error),
+    #[error("Failed to parse the crash dump: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Crash dump version {0} is not supported")]
+    UnsupportedDumpVersion(u32),
+    #[error("Invalid NSO, {0}")]
+    InvalidNso(&'static str),
+    #[error("{0}")]
+    Usage(&'static str),
+}
+
+fn write_locations<W: Write>(out: &mut W, locations: &[Location]) -> std::io::Result<()> {
+    for (idx, location) in locations.iter().enumerate() {
+        write!(out, "{}", INDENT)?;
+        if idx > 0 {
+            write!(out, "inlined into ")?;
+        }
+        match (location.function.as_deref(), location.offset) {
+            (Some(function), Some(offset)) => write!(out, "{} + {:#x}", function, offset)?,
+            (Some(function), None) => write!(out, "{}", function)?,
+            (None, _) => write!(out, "??")?,
+        }
+        if let Some(file) = location.file.as_deref() {
+            write!(out, " at {}", file)?;
+            if let Some(line) = location.line {
+                write!(out, ":{}", line)?;
+                if let Some(column) = location.column {
+                    write!(out, ":{}", column)?;
+                }
+            }
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Echoes a crash log, following every frame which refers to a loaded module with its locations
+fn symbolize_log<W: Write>(out: &mut W, log: &str, modules: &[Module]) -> std::io::Result<()> {
+    for line in log.lines() {
+        writeln!(out, "{}", line)?;
+
+        let Some((name, offset, _)) = parse_log_frame(line) else {
+            continue;
+        };
+
+        if let Some(module) = modules.iter().find(|module| module.matches_name(name)) {
+            write_locations(out, &module.resolve(offset))?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the frames of a crash dump along with their locations
+fn symbolize_dump<W: Write>(out: &mut W, dump: &CrashDump, modules: &[Module]) -> std::io::Result<()> {
+    // Every module of the dump is paired with the loaded module that it was built from
+    let matched: Vec<Option<&Module>> = dump
+        .modules
+        .iter()
+        .map(|dumped| {
+            // A malformed build id can't match anything, so the module is only matched by name
+            let build_id = parse_build_id(&dumped.build_id).unwrap_or_default();
+            modules
+                .iter()
+                .find(|module| module.matches_build_id(&build_id))
+                .or_else(|| modules.iter().find(|module| module.matches_name(&dumped.name)))
+        })
+        .collect();
+
+    let write_frame = |out: &mut W, address: u64, module: Option<usize>, offset: Option<u64>| -> std::io::Result<()> {
+        write!(out, "{:016x}", address)?;
+        let (Some(module), Some(offset)) = (module, offset) else {
+            return writeln!(out);
+        };
+        writeln!(out, " ({} + {:#x})", dump.modules[module].name, offset)?;
+        match matched[module] {
+            Some(loaded) => write_locations(out, &loaded.resolve(offset)),
+            None => Ok(()),
+        }
+    };
+
+    writeln!(out, "Program ID: {}", dump.program_id)?;
+    if let Some(reason) = dump.reason.as_deref() {
+        writeln!(out, "{}", reason)?;
+    }
+
+    // The link register isn't part of the frames, so its module is found from the module bounds
+    let lr = parse_hex(&dump.lr).unwrap_or(0);
+    let lr_module = dump.modules.iter().position(|module| {
+        let base = parse_hex(&module.base).unwrap_or(0);
+        let size = parse_hex(&module.size).unwrap_or(0);
+        base <= lr && lr < base + size
+    });
+    let lr_offset = lr_module.map(|module| lr - parse_hex(&dump.modules[module].base).unwrap_or(0));
+    write!(out, "Current LR: ")?;
+    write_frame(out, lr, lr_module, lr_offset)?;
+
+    for (idx, frame) in dump.frames.iter().enumerate() {
+        write!(out, "      [{:02}]: ", idx)?;
+        write_frame(
+            out,
+            parse_hex(&frame.address).unwrap_or(0),
+            frame.module.filter(|&module| module < dump.modules.len()),
+            frame.module_offset.as_deref().and_then(parse_hex),
+        )?;
+
+        // The symbol from the dump is still useful when the module wasn't provided
+        let has_module = frame.module.and_then(|module| matched.get(module).copied().flatten()).is_some();
+        if let (false, Some(symbol)) = (has_module, frame.symbol.as_deref()) {
+            let offset = frame.symbol_offset.as_deref().and_then(parse_hex).unwrap_or(0);
+            write_locations(
+                out,
+                &[Location {
+                    function: Some(addr2line::demangle_auto(symbol.into(), None).into_owned()),
+                    offset: Some(offset),
+                    file: None,
+                    line: None,
+                    column: None,
+                }],
+            )?;
+        }
+    }
+
+    if let Some(error) = dump.error.as_deref() {
+        writeln!(out, "      [{:02}]: {}", dump.frames.len(), error)?;
+    }
+
+    Ok(())
+}
+
+fn run() -> Result<(), SymbolizeError> {
+    let mut args = std::env::args().skip(1);
+    let input_path = args.next().ok_or(SymbolizeError::Usage(USAGE))?;
+    if input_path == "-h" || input_path == "--help" {
+        return Err(SymbolizeError::Usage(USAGE));
+    }
+
+    let modules = args
+        .map(|arg| match arg.split_once('=') {
+            Some((name, path)) => Module::load(Path::new(path), Some(name.to_owned())),
+            None => Module::load(Path::new(&arg), None),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut input = String::new();
+    if input_path == "-" {
+        std::io::stdin().read_to_string(&mut input)?;
+    } else {
+        input = std::fs::read_to_string(&input_path)?;
+    }
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if input.trim_start().starts_with('{') {
+        symbolize_dump(&mut out, &CrashDump::parse(&input)?, &modules)?;
+    } else {
+        symbolize_log(&mut out, &input, &modules)?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
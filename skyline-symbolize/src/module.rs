@@ -0,0 +1,373 @@
+use std::borrow::Cow;
+use std::path::Path;
+use std::rc::Rc;
+
+use gimli::{EndianRcSlice, RunTimeEndian};
+use object::{Object, ObjectSection, ObjectSegment, ObjectSymbol, SymbolKind};
+
+use crate::SymbolizeError;
+
+const NSO_MAGIC: &[u8; 4] = b"NSO0";
+const MOD0_MAGIC: &[u8; 4] = b"MOD0";
+
+const DT_NULL: u64 = 0;
+const DT_HASH: u64 = 4;
+const DT_STRTAB: u64 = 5;
+const DT_SYMTAB: u64 = 6;
+const DT_STRSZ: u64 = 10;
+
+const STT_FUNC: u8 = 2;
+const ELF64_SYM_SIZE: usize = 0x18;
+
+type Reader = EndianRcSlice<RunTimeEndian>;
+
+/// A function symbol from a module's symbol table
+struct Symbol {
+    address: u64,
+    size: u64,
+    name: String,
+}
+
+/// A single, possibly inlined, function that an address resolved to
+pub struct Location {
+    /// The demangled name of the function
+    pub function: Option<String>,
+
+    /// The offset of the address from the start of the function, only known for the outermost function
+    pub offset: Option<u64>,
+
+    /// The source file of the address
+    pub file: Option<String>,
+
+    /// The line in the source file
+    pub line: Option<u32>,
+
+    /// The column in the source line
+    pub column: Option<u32>,
+}
+
+/// An ELF or NSO file which frames can be resolved against
+pub struct Module {
+    /// The name that frames in crash logs refer to the module by
+    pub name: String,
+
+    /// The GNU build id of an ELF, or the module id of an NSO
+    pub build_id: Vec<u8>,
+
+    /// The address the start of the module is linked at
+    base: u64,
+
+    /// Function symbols sorted by address
+    symbols: Vec<Symbol>,
+
+    /// The DWARF debug info of the module, if it has any
+    context: Option<addr2line::Context<Reader>>,
+}
+
+impl Module {
+    /// Loads a module from an ELF or NSO file
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file
+    /// * `name` - The name the module is matched against, defaults to the file name
+    pub fn load(path: &Path, name: Option<String>) -> Result<Self, SymbolizeError> {
+        let data = std::fs::read(path)?;
+
+        let name = name.unwrap_or_else(|| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+
+        if data.starts_with(NSO_MAGIC) {
+            Self::from_nso(name, &data)
+        } else {
+            Self::from_elf(name, &data)
+        }
+    }
+
+    fn from_elf(name: String, data: &[u8]) -> Result<Self, SymbolizeError> {
+        let file = object::File::parse(data)?;
+
+        let base = file.segments().map(|segment| segment.address()).min().unwrap_or(0);
+
+        let mut symbols: Vec<Symbol> = file
+            .symbols()
+            .chain(file.dynamic_symbols())
+            .filter(|symbol| symbol.kind() == SymbolKind::Text && symbol.address() != 0)
+            .filter_map(|symbol| {
+                Some(Symbol {
+                    address: symbol.address(),
+                    size: symbol.size(),
+                    name: symbol.name().ok()?.to_owned(),
+                })
+            })
+            .collect();
+        symbols.sort_by_key(|symbol| symbol.address);
+        symbols.dedup_by_key(|symbol| symbol.address);
+
+        let endian = if file.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+
+        let load_section = |id: gimli::SectionId| -> Result<Reader, gimli::Error> {
+            let data = file
+                .section_by_name(id.name())
+                .and_then(|section| section.uncompressed_data().ok())
+                .unwrap_or(Cow::Borrowed(&[]));
+            Ok(EndianRcSlice::new(Rc::from(&*data), endian))
+        };
+
+        // Stripped ELFs are still useful for their symbol table, so missing debug info isn't an error
+        let context = if file.section_by_name(".debug_info").is_some() {
+            Some(addr2line::Context::from_dwarf(gimli::Dwarf::load(load_section)?)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name,
+            build_id: file.build_id()?.map(<[u8]>::to_vec).unwrap_or_default(),
+            base,
+            symbols,
+            context,
+        })
+    }
+
+    fn from_nso(name: String, data: &[u8]) -> Result<Self, SymbolizeError> {
+        let read_u32 = |offset: usize| -> Result<u32, SymbolizeError> {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or(SymbolizeError::InvalidNso("the header is truncated"))
+        };
+
+        let flags = read_u32(0xC)?;
+
+        // Each of the text, rodata, and data segments is lz4 compressed if its flag is set
+        let mut image = Vec::new();
+        for segment in 0..3 {
+            let header = 0x10 + segment * 0x10;
+            let file_offset = read_u32(header)? as usize;
+            let memory_offset = read_u32(header + 4)? as usize;
+            let size = read_u32(header + 8)? as usize;
+            let file_size = read_u32(0x60 + segment * 4)? as usize;
+
+            let contents = data
+                .get(file_offset..file_offset + file_size)
+                .ok_or(SymbolizeError::InvalidNso("a segment is outside of the file"))?;
+
+            let contents = if flags & (1 << segment) != 0 {
+                Cow::Owned(
+                    lz4_flex::block::decompress(contents, size)
+                        .map_err(|_| SymbolizeError::InvalidNso("a segment failed to decompress"))?,
+                )
+            } else {
+                Cow::Borrowed(contents)
+            };
+
+            if image.len() < memory_offset + contents.len() {
+                image.resize(memory_offset + contents.len(), 0);
+            }
+            image[memory_offset..memory_offset + contents.len()].copy_from_slice(&contents);
+        }
+
+        Ok(Self {
+            name,
+            build_id: data[0x40..0x60].to_vec(),
+            base: 0,
+            symbols: nso_symbols(&image).unwrap_or_default(),
+            context: None,
+        })
+    }
+
+    /// Whether the module was built from the same ELF as the provided build id
+    pub fn matches_build_id(&self, build_id: &[u8]) -> bool {
+        // NSO and NRO module ids are the build id padded with zeroes, while ELFs have the bare note
+        fn trim(build_id: &[u8]) -> &[u8] {
+            let end = build_id.iter().rposition(|&byte| byte != 0).map_or(0, |idx| idx + 1);
+            &build_id[..end]
+        }
+
+        let build_id = trim(build_id);
+        !build_id.is_empty() && trim(&self.build_id) == build_id
+    }
+
+    /// Whether crash logs refer to the module by the provided name
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.name == name
+            || Path::new(&self.name).file_stem().is_some_and(|stem| stem == name)
+    }
+
+    /// Resolves an offset from the start of the module to the functions containing it
+    ///
+    /// # Returns
+    /// The innermost inlined function first, followed by the functions it was inlined into
+    pub fn resolve(&self, offset: u64) -> Vec<Location> {
+        let address = self.base + offset;
+        let symbol = self.find_symbol(address);
+
+        let mut locations = Vec::new();
+        if let Some(context) = self.context.as_ref() {
+            if let Ok(mut frames) = context.find_frames(address) {
+                while let Ok(Some(frame)) = frames.next() {
+                    let location = frame.location.as_ref();
+                    locations.push(Location {
+                        function: frame
+                            .function
+                            .as_ref()
+                            .and_then(|function| function.demangle().ok())
+                            .map(Cow::into_owned),
+                        offset: None,
+                        file: location.and_then(|location| location.file).map(str::to_owned),
+                        line: location.and_then(|location| location.line),
+                        column: location.and_then(|location| location.column),
+                    });
+                }
+            }
+        }
+
+        if locations.is_empty() {
+            locations.push(Location {
+                function: None,
+                offset: None,
+                file: None,
+                line: None,
+                column: None,
+            });
+        }
+
+        // The debug info only has the names of inlined functions, the outermost one comes from the symbol table
+        let outermost = locations.last_mut().unwrap();
+        if let Some(symbol) = symbol {
+            if outermost.function.is_none() {
+                outermost.function = Some(addr2line::demangle_auto(Cow::Borrowed(&symbol.name), None).into_owned());
+            }
+            outermost.offset = Some(address - symbol.address);
+        }
+
+        locations.retain(|location| location.function.is_some() || location.file.is_some());
+        locations
+    }
+
+    fn find_symbol(&self, address: u64) -> Option<&Symbol> {
+        let index = self.symbols.partition_point(|symbol| symbol.address <= address).checked_sub(1)?;
+        let symbol = &self.symbols[index];
+
+        // Symbols without a size are assumed to extend up to the next one
+        if symbol.size == 0 || address < symbol.address + symbol.size {
+            Some(symbol)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reads the function symbols of an NSO from its dynamic symbol table
+///
+/// NSOs don't have section headers, so the table is found through the MOD0 header's
+/// `.dynamic` section instead.
+fn nso_symbols(image: &[u8]) -> Option<Vec<Symbol>> {
+    let read_u32 = |offset: usize| image.get(offset..offset + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+    let read_u64 = |offset: usize| image.get(offset..offset + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+
+    let mod0 = read_u32(4)? as usize;
+    if image.get(mod0..mod0 + 4)? != MOD0_MAGIC {
+        return None;
+    }
+
+    let mut dynamic = (mod0 as i64 + read_u32(mod0 + 4)? as i32 as i64) as usize;
+    let (mut hash, mut strtab, mut strsz, mut symtab) = (None, None, None, None);
+    loop {
+        let tag = read_u64(dynamic)?;
+        let value = read_u64(dynamic + 8)?;
+        match tag {
+            DT_NULL => break,
+            DT_HASH => hash = Some(value as usize),
+            DT_STRTAB => strtab = Some(value as usize),
+            DT_STRSZ => strsz = Some(value as usize),
+            DT_SYMTAB => symtab = Some(value as usize),
+            _ => {}
+        }
+        dynamic += 0x10;
+    }
+
+    let (strtab, strsz, symtab) = (strtab?, strsz?, symtab?);
+    let strings = image.get(strtab..strtab + strsz)?;
+
+    // The number of symbols is the number of chains in the hash table, otherwise the
+    // string table is assumed to directly follow the symbol table like lld lays them out
+    let count = match hash {
+        Some(hash) => read_u32(hash + 4)? as usize,
+        None => strtab.checked_sub(symtab)? / ELF64_SYM_SIZE,
+    };
+
+    let mut symbols: Vec<Symbol> = (0..count)
+        .filter_map(|index| {
+            let entry = symtab + index * ELF64_SYM_SIZE;
+            let name = read_u32(entry)? as usize;
+            let info = *image.get(entry + 4)?;
+            let address = read_u64(entry + 8)?;
+            let size = read_u64(entry + 0x10)?;
+
+            if info & 0xF != STT_FUNC || address == 0 {
+                return None;
+            }
+
+            let name = strings.get(name..)?;
+            let name = &name[..name.iter().position(|&c| c == 0)?];
+            Some(Symbol {
+                address,
+                size,
+                name: String::from_utf8_lossy(name).into_owned(),
+            })
+        })
+        .collect();
+
+    symbols.sort_by_key(|symbol| symbol.address);
+    symbols.dedup_by_key(|symbol| symbol.address);
+    Some(symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(build_id: &[u8]) -> Module {
+        Module { name: "main.elf".into(), build_id: build_id.to_vec(), base: 0, symbols: Vec::new(), context: None }
+    }
+
+    #[test]
+    fn build_id() {
+        let elf = module(&[0x1f, 0x2e, 0x3d, 0x4c]);
+        assert!(elf.matches_build_id(&[0x1f, 0x2e, 0x3d, 0x4c]));
+
+        // Module ids of NSOs and NROs are padded to 0x20 bytes
+        let mut padded = [0; 0x20];
+        padded[..4].copy_from_slice(&[0x1f, 0x2e, 0x3d, 0x4c]);
+        assert!(elf.matches_build_id(&padded));
+        assert!(module(&padded).matches_build_id(&[0x1f, 0x2e, 0x3d, 0x4c]));
+        assert!(module(&padded).matches_build_id(&padded));
+
+        // Ids which are a prefix of each other only match if the difference is padding
+        assert!(!elf.matches_build_id(&[0x1f, 0x2e, 0x3d]));
+        assert!(!elf.matches_build_id(&[0x1f, 0x2e, 0x3d, 0x4c, 0x5b]));
+        assert!(!module(&[0x1f, 0x2e, 0x3d]).matches_build_id(&[0x1f, 0x2e, 0x3d, 0x4c]));
+        assert!(!elf.matches_build_id(&[0x2e, 0x3d, 0x4c]));
+
+        // Modules without an id never match, even each other
+        assert!(!elf.matches_build_id(&[]));
+        assert!(!elf.matches_build_id(&[0; 0x20]));
+        assert!(!module(&[]).matches_build_id(&[]));
+    }
+
+    #[test]
+    fn name() {
+        let elf = module(&[]);
+        assert!(elf.matches_name("main.elf"));
+        assert!(elf.matches_name("main"));
+        assert!(!elf.matches_name("main.nso"));
+        assert!(!elf.matches_name("sdk"));
+    }
+}
@@ -0,0 +1,260 @@
+//! Crash reporting for plugins.
+//!
+//! Crash reports are always written to the debug log with `svcOutputDebugString`, and can
+//! optionally be written to a directory on the SD card as well, along with a [`CrashDump`].
+use std::any::Any;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::panic::Location;
+
+use crate::hooks::{Backtrace, BacktraceError};
+
+mod dump;
+
+pub use dump::*;
+
+/// The crash log directory used when one is requested without a path
+pub const DEFAULT_CRASH_LOG_DIRECTORY: &str = "sd:/skyline/crash_logs";
+
+/// The prefix of every crash log file, which is followed by a sequence number
+const CRASH_LOG_PREFIX: &str = "crash_";
+
+/// The extension of every crash log file
+const CRASH_LOG_EXTENSION: &str = "log";
+
+/// The extension of every crash dump file, which shares its sequence number with a crash log
+const CRASH_DUMP_EXTENSION: &str = "json";
+
+/// Configuration for where crash reports are written to
+#[derive(Debug, Clone)]
+pub struct CrashLogConfig {
+    directory: Option<PathBuf>,
+    max_logs: usize,
+    backtrace_limit: usize,
+    write_dumps: bool,
+}
+
+impl Default for CrashLogConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            max_logs: 10,
+            backtrace_limit: 32,
+            write_dumps: false,
+        }
+    }
+}
+
+impl CrashLogConfig {
+    /// Creates a configuration which only writes crash reports to the debug log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes crash reports to files in the provided directory, which is created if it doesn't exist
+    pub fn directory<P: Into<PathBuf>>(mut self, directory: P) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// Writes crash reports to files in [`DEFAULT_CRASH_LOG_DIRECTORY`]
+    pub fn default_directory(self) -> Self {
+        self.directory(DEFAULT_CRASH_LOG_DIRECTORY)
+    }
+
+    /// Sets the number of crash logs which are kept in the directory, the oldest logs
+    /// are deleted once there are more than this
+    pub fn max_logs(mut self, max_logs: usize) -> Self {
+        self.max_logs = max_logs.max(1);
+        self
+    }
+
+    /// Sets the maximum number of frames included in the backtrace of a crash report
+    pub fn backtrace_limit(mut self, limit: usize) -> Self {
+        self.backtrace_limit = limit;
+        self
+    }
+
+    /// Sets whether a [`CrashDump`] is written next to every crash log, which requires a directory
+    pub fn dumps(mut self, write_dumps: bool) -> Self {
+        self.write_dumps = write_dumps;
+        self
+    }
+
+    /// Gets the directory that crash logs are written to, if any
+    pub fn get_directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    /// Gets the maximum number of frames included in the backtrace of a crash report
+    pub fn get_backtrace_limit(&self) -> usize {
+        self.backtrace_limit
+    }
+
+    /// Gets whether a [`CrashDump`] is written next to every crash log
+    pub fn get_dumps(&self) -> bool {
+        self.write_dumps
+    }
+
+    /// Writes a crash report to the debug log and, if configured, to a new file in the crash log directory
+    ///
+    /// # Arguments
+    /// * `report` - The human-readable crash report
+    /// * `dump` - The crash dump, which is only written if dumps are enabled
+    ///
+    /// # Returns
+    /// The path of the crash log file, if one was written
+    pub fn write_report(&self, report: &str, dump: Option<&CrashDump>) -> Option<PathBuf> {
+        let _ = crate::nx::output_debug_string(report);
+        self.write_report_file(report, dump)
+    }
+
+    /// Writes a crash report to a new file in the crash log directory, without writing it to the debug log
+    ///
+    /// # Returns
+    /// The path of the crash log file, if one was written
+    pub(crate) fn write_report_file(&self, report: &str, dump: Option<&CrashDump>) -> Option<PathBuf> {
+        let directory = self.directory.as_ref()?;
+        let path = rotate_crash_logs(directory, self.max_logs).ok()?;
+        std::fs::write(&path, report).ok()?;
+
+        if let (true, Some(dump)) = (self.write_dumps, dump) {
+            let _ = std::fs::write(path.with_extension(CRASH_DUMP_EXTENSION), dump.to_json());
+        }
+
+        Some(path)
+    }
+}
+
+/// Gets the sequence number of a crash log or crash dump from its file name
+fn crash_log_index(path: &Path) -> Option<u64> {
+    let extension = path.extension()?;
+    if extension != CRASH_LOG_EXTENSION && extension != CRASH_DUMP_EXTENSION {
+        return None;
+    }
+
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix(CRASH_LOG_PREFIX)?
+        .parse()
+        .ok()
+}
+
+/// Deletes the oldest crash logs so that there is room for a new one
+///
+/// # Returns
+/// The path that the new crash log should be written to
+fn rotate_crash_logs(directory: &Path, max_logs: usize) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(directory)?;
+
+    let mut logs: Vec<(u64, PathBuf)> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| crash_log_index(&path).map(|index| (index, path)))
+        .collect();
+
+    logs.sort_by_key(|(index, _)| *index);
+
+    let next_index = logs.last().map_or(0, |(index, _)| index + 1);
+
+    // A crash log and its dump share an index, so the oldest indices are removed together
+    let mut indices: Vec<u64> = logs.iter().map(|(index, _)| *index).collect();
+    indices.dedup();
+
+    let excess = (indices.len() + 1).saturating_sub(max_logs);
+    if excess > 0 {
+        let newest_removed = indices[excess - 1];
+        for (_, path) in logs.iter().take_while(|(index, _)| *index <= newest_removed) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    Ok(directory.join(format!("{}{:06}.{}", CRASH_LOG_PREFIX, next_index, CRASH_LOG_EXTENSION)))
+}
+
+/// Gets the message of a panic from its payload
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+/// Formats the first line of a panic's crash report, saying where it happened
+fn panic_summary(location: Option<&Location<'_>>) -> String {
+    let thread = std::thread::current();
+    let mut summary = format!("Thread '{}' panicked", thread.name().unwrap_or("<unnamed>"));
+    if let Some(location) = location {
+        let _ = write!(summary, " at {}:{}:{}", location.file(), location.line(), location.column());
+    }
+    summary
+}
+
+/// Formats the crash report for a panic
+fn format_panic_report(summary: &str, payload: &(dyn Any + Send), backtrace: Result<Backtrace, BacktraceError>) -> String {
+    let mut report = String::new();
+
+    let _ = writeln!(report, "{}:\n{}", summary, panic_message(payload));
+    let _ = writeln!(report, "Program ID: {:016x}", crate::nx::get_program_id());
+    let _ = writeln!(report, "Thread: {}", crate::nx::ThreadInfo::current());
+
+    if let Some(module) = crate::rtld::get_module_for_self() {
+        let _ = writeln!(
+            report,
+            "Module: {} ({:#x})",
+            module.get_module_name().unwrap_or("unknown"),
+            module.module_base as u64
+        );
+    }
+
+    let _ = writeln!(report, "Backtrace:");
+    match backtrace {
+        Ok(backtrace) => {
+            let _ = write!(report, "{:#}", backtrace.strip_hashes(true));
+        },
+        Err(e) => {
+            let _ = writeln!(report, "      Failed to capture a backtrace: {}", e);
+        }
+    }
+
+    report
+}
+
+/// Installs a panic hook which writes a crash report to the debug log.
+///
+/// The report includes the panic message, where it happened, and a backtrace with symbol
+/// names and module offsets. Any panic hook which was already installed still runs afterwards.
+pub fn install_panic_hook() {
+    install_panic_hook_with(CrashLogConfig::default())
+}
+
+/// Installs a panic hook which writes crash reports as described by the provided configuration
+///
+/// # Example
+/// ```ignore
+/// skyline::crash::install_panic_hook_with(
+///     CrashLogConfig::new()
+///         .directory("sd:/my_plugin/crashes")
+///         .max_logs(5)
+/// );
+/// ```
+pub fn install_panic_hook_with(config: CrashLogConfig) {
+    let previous = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let backtrace = Backtrace::capture(config.backtrace_limit);
+        let summary = panic_summary(info.location());
+
+        let dump = match (&backtrace, config.write_dumps && config.directory.is_some()) {
+            (Ok(backtrace), true) => Some(CrashDump::from_backtrace(backtrace).with_reason(summary.clone())),
+            _ => None,
+        };
+
+        let report = format_panic_report(&summary, info.payload(), backtrace);
+        config.write_report(&report, dump.as_ref());
+        previous(info);
+    }));
+}
@@ -0,0 +1,285 @@
+use std::fmt::{self, Write as _};
+
+use crate::hooks::Backtrace;
+
+/// The version of the crash dump format, which is bumped whenever a field changes meaning
+pub const CRASH_DUMP_VERSION: u32 = 1;
+
+/// A module which was loaded when the crash dump was taken
+#[derive(Debug, Clone)]
+pub struct DumpModule {
+    /// The file name of the module
+    pub name: &'static str,
+
+    /// The address the module is loaded at
+    pub base: u64,
+
+    /// The size of the module in memory
+    pub size: u64,
+
+    /// The GNU build id of the module, which is empty if it couldn't be found
+    pub build_id: Vec<u8>,
+}
+
+/// A single frame of the backtrace in a crash dump
+#[derive(Debug, Clone)]
+pub struct DumpFrame {
+    /// The address that the frame returns to
+    pub address: u64,
+
+    /// The frame pointer of the function that is returned to
+    pub frame_pointer: u64,
+
+    /// The index of the module containing the address in [`CrashDump::modules`]
+    pub module: Option<usize>,
+
+    /// The offset of the address from the base of its module
+    pub module_offset: Option<u64>,
+
+    /// The mangled name of the symbol containing the address
+    pub symbol: Option<&'static str>,
+
+    /// The offset of the address from the start of its symbol
+    pub symbol_offset: Option<u64>,
+}
+
+/// A machine-readable description of a crash, which can be symbolized offline with the
+/// original ELF files of the modules.
+///
+/// The dump is serialized as JSON, where every address is a hexadecimal string so that
+/// 64-bit values survive parsers which only support doubles:
+/// ```json
+/// {
+///   "version": 1,
+///   "program_id": "01006a800016e000",
+///   "reason": "Thread 'main' panicked at src/lib.rs:10:5",
+///   "lr": "0x8123456",
+///   "modules": [
+///     { "name": "main", "base": "0x8004000", "size": "0x2a4b000", "build_id": "1f2e..." }
+///   ],
+///   "frames": [
+///     { "address": "0x8123456", "frame_pointer": "0x...", "module": 0, "module_offset": "0x11f456",
+///       "symbol": "_ZN...", "symbol_offset": "0x40" }
+///   ],
+///   "error": null
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CrashDump {
+    /// The program id of the running title
+    pub program_id: u64,
+
+    /// A short description of why the dump was taken
+    pub reason: Option<String>,
+
+    /// The link register at the point the backtrace was taken
+    pub lr: u64,
+
+    /// Every module which was loaded
+    pub modules: Vec<DumpModule>,
+
+    /// The frames of the backtrace, starting with the innermost one
+    pub frames: Vec<DumpFrame>,
+
+    /// The error which ended the backtrace early, if any
+    pub error: Option<String>,
+}
+
+impl CrashDump {
+    /// Creates a crash dump from a backtrace and the modules which are currently loaded
+    pub fn from_backtrace(backtrace: &Backtrace) -> Self {
+        let modules: Vec<DumpModule> = crate::rtld::loaded_modules()
+            .map(|module| DumpModule {
+                name: module.get_module_name().unwrap_or("unknown"),
+                base: module.module_base as u64,
+                size: module.get_module_size().unwrap_or(0),
+                build_id: module.get_build_id().map(<[u8]>::to_vec).unwrap_or_default(),
+            })
+            .collect();
+
+        let mut frames = Vec::new();
+        let mut error = None;
+        for frame in backtrace.frames() {
+            match frame {
+                Ok(frame) => frames.push(DumpFrame {
+                    address: frame.return_address,
+                    frame_pointer: frame.frame_pointer,
+                    module: frame.module.and_then(|module| {
+                        modules.iter().position(|dumped| dumped.base == module.module_base as u64)
+                    }),
+                    module_offset: frame.module_offset(),
+                    symbol: frame.symbol,
+                    symbol_offset: frame.symbol.map(|_| frame.offset),
+                }),
+                Err(e) => {
+                    error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+
+        Self {
+            program_id: crate::nx::get_program_id(),
+            reason: None,
+            lr: backtrace.current_lr(),
+            modules,
+            frames,
+            error,
+        }
+    }
+
+    /// Sets the description of why the dump was taken
+    pub fn with_reason<S: Into<String>>(mut self, reason: S) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Serializes the crash dump as JSON
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        let _ = self.write_json(&mut out);
+        out
+    }
+
+    /// Serializes the crash dump as JSON into a writer
+    pub fn write_json_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(self.to_json().as_bytes())
+    }
+
+    fn write_json(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"version\": {},", CRASH_DUMP_VERSION)?;
+        writeln!(out, "  \"program_id\": \"{:016x}\",", self.program_id)?;
+        write!(out, "  \"reason\": ")?;
+        write_optional_string(out, self.reason.as_deref())?;
+        writeln!(out, ",")?;
+        writeln!(out, "  \"lr\": \"{:#x}\",", self.lr)?;
+
+        writeln!(out, "  \"modules\": [")?;
+        for (idx, module) in self.modules.iter().enumerate() {
+            write!(out, "    {{ \"name\": ")?;
+            write_string(out, module.name)?;
+            write!(out, ", \"base\": \"{:#x}\", \"size\": \"{:#x}\", \"build_id\": \"", module.base, module.size)?;
+            for byte in module.build_id.iter() {
+                write!(out, "{:02x}", byte)?;
+            }
+            write!(out, "\" }}")?;
+            writeln!(out, "{}", if idx + 1 < self.modules.len() { "," } else { "" })?;
+        }
+        writeln!(out, "  ],")?;
+
+        writeln!(out, "  \"frames\": [")?;
+        for (idx, frame) in self.frames.iter().enumerate() {
+            write!(out, "    {{ \"address\": \"{:#x}\", \"frame_pointer\": \"{:#x}\", \"module\": ", frame.address, frame.frame_pointer)?;
+            match frame.module {
+                Some(module) => write!(out, "{}", module)?,
+                None => write!(out, "null")?,
+            }
+            write!(out, ", \"module_offset\": ")?;
+            write_optional_hex(out, frame.module_offset)?;
+            write!(out, ", \"symbol\": ")?;
+            write_optional_string(out, frame.symbol)?;
+            write!(out, ", \"symbol_offset\": ")?;
+            write_optional_hex(out, frame.symbol_offset)?;
+            write!(out, " }}")?;
+            writeln!(out, "{}", if idx + 1 < self.frames.len() { "," } else { "" })?;
+        }
+        writeln!(out, "  ],")?;
+
+        write!(out, "  \"error\": ")?;
+        write_optional_string(out, self.error.as_deref())?;
+        writeln!(out)?;
+        writeln!(out, "}}")
+    }
+}
+
+fn write_string(out: &mut String, string: &str) -> fmt::Result {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    Ok(())
+}
+
+fn write_optional_string(out: &mut String, string: Option<&str>) -> fmt::Result {
+    match string {
+        Some(string) => write_string(out, string),
+        None => write!(out, "null"),
+    }
+}
+
+fn write_optional_hex(out: &mut String, value: Option<u64>) -> fmt::Result {
+    match value {
+        Some(value) => write!(out, "\"{:#x}\"", value),
+        None => write!(out, "null"),
+    }
+}
+
+#[cfg(all(test, target_arch = "aarch64"))]
+mod tests {
+    use super::*;
+
+    /// The crash dump which skyline-symbolize's tests parse, so changes to the format have to
+    /// keep both sides in sync
+    const CRASH_DUMP: &str = include_str!("../../skyline-symbolize/testdata/crash_dump.json");
+
+    #[test]
+    fn matches_symbolizer_fixture() {
+        let dump = CrashDump {
+            program_id: 0x01006a800016e000,
+            reason: None,
+            lr: 0x8123456,
+            modules: vec![
+                DumpModule {
+                    name: "main",
+                    base: 0x8004000,
+                    size: 0x2a4b000,
+                    build_id: vec![
+                        0x1f, 0x2e, 0x3d, 0x4c, 0x5b, 0x6a, 0x79, 0x88, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55,
+                        0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb,
+                    ],
+                },
+                DumpModule { name: "libplugin.nro", base: 0x9a00000, size: 0x10000, build_id: Vec::new() },
+            ],
+            frames: vec![
+                DumpFrame {
+                    address: 0x8123456,
+                    frame_pointer: 0x3fff1230,
+                    module: Some(0),
+                    module_offset: Some(0x11f456),
+                    symbol: Some("_ZN4Game6UpdateEv"),
+                    symbol_offset: Some(0x40),
+                },
+                DumpFrame {
+                    address: 0x9a01234,
+                    frame_pointer: 0x3fff1270,
+                    module: Some(1),
+                    module_offset: Some(0x1234),
+                    symbol: None,
+                    symbol_offset: None,
+                },
+                DumpFrame {
+                    address: 0xdeadbeef,
+                    frame_pointer: 0x3fff12b0,
+                    module: None,
+                    module_offset: None,
+                    symbol: None,
+                    symbol_offset: None,
+                },
+            ],
+            error: Some("The frame pointer is null".into()),
+        }
+        .with_reason("Thread 'main' panicked at src\\lib.rs:10:5:\n\t\"index out of bounds\"");
+
+        assert_eq!(dump.to_json(), CRASH_DUMP);
+    }
+}
@@ -0,0 +1,428 @@
+//! User exception handling, for CPU faults such as data aborts and undefined instructions.
+//!
+//! The handler is registered with `nn::os::SetUserExceptionHandler`, which runs it on a dedicated
+//! stack in the thread that faulted. The handler can then either resume execution, after optionally
+//! modifying the faulting thread's registers, or let the kernel terminate the process.
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Once;
+
+use crate::crash::{CrashDump, CrashLogConfig};
+use crate::hooks::{Backtrace, BacktraceError, BacktraceFormat, CpuRegister, FpuRegister, InlineCtx};
+use crate::nx::MemoryInfo;
+
+/// The result passed to `svcReturnFromException` when an exception was not handled
+pub const UNHANDLED_EXCEPTION_RESULT: u32 = 0xF801;
+
+/// The size of the stack that exception handlers run on
+const EXCEPTION_STACK_SIZE: usize = 0x20000;
+
+#[repr(C, align(16))]
+struct ExceptionStack([u8; EXCEPTION_STACK_SIZE]);
+
+static mut EXCEPTION_STACK: ExceptionStack = ExceptionStack([0; EXCEPTION_STACK_SIZE]);
+
+static mut EXCEPTION_INFO: std::mem::MaybeUninit<ExceptionInfo> = std::mem::MaybeUninit::uninit();
+
+static REGISTER_HANDLER: Once = Once::new();
+
+static EXCEPTION_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+// The configuration is leaked rather than locked, so that reading it from the crash handler
+// can't deadlock on a lock held by the faulting thread
+static CRASH_LOG_CONFIG: AtomicPtr<CrashLogConfig> = AtomicPtr::new(std::ptr::null_mut());
+
+static IN_CRASH_HANDLER: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    #[link_name = "_ZN2nn2os23SetUserExceptionHandlerEPFvPNS0_17UserExceptionInfoEEPvmS2_"]
+    fn nn_os_set_user_exception_handler(
+        handler: extern "C" fn(*mut ExceptionInfo),
+        stack: *mut u8,
+        stack_size: usize,
+        info: *mut ExceptionInfo
+    );
+
+    fn skex_exception_resume(info: *const ExceptionInfo) -> !;
+}
+
+// Restores every register from the exception info and jumps to the faulting pc.
+// x16 and x17 are used as scratch registers, so they are the only ones which are not restored.
+std::arch::global_asm!(r#"
+.section .text.skex_exception_resume, "ax", %progbits
+.global skex_exception_resume
+.type skex_exception_resume, %function
+.hidden skex_exception_resume
+.align 2
+skex_exception_resume:
+    mov x16, x0
+    add x17, x16, #0x120
+    ldp q0, q1, [x17, #0x0]
+    ldp q2, q3, [x17, #0x20]
+    ldp q4, q5, [x17, #0x40]
+    ldp q6, q7, [x17, #0x60]
+    ldp q8, q9, [x17, #0x80]
+    ldp q10, q11, [x17, #0xA0]
+    ldp q12, q13, [x17, #0xC0]
+    ldp q14, q15, [x17, #0xE0]
+    ldp q16, q17, [x17, #0x100]
+    ldp q18, q19, [x17, #0x120]
+    ldp q20, q21, [x17, #0x140]
+    ldp q22, q23, [x17, #0x160]
+    ldp q24, q25, [x17, #0x180]
+    ldp q26, q27, [x17, #0x1A0]
+    ldp q28, q29, [x17, #0x1C0]
+    ldp q30, q31, [x17, #0x1E0]
+    ldr w17, [x16, #0x320]
+    msr nzcv, x17
+    ldr x17, [x16, #0x108]
+    mov sp, x17
+    ldp x0, x1, [x16, #0x10]
+    ldp x2, x3, [x16, #0x20]
+    ldp x4, x5, [x16, #0x30]
+    ldp x6, x7, [x16, #0x40]
+    ldp x8, x9, [x16, #0x50]
+    ldp x10, x11, [x16, #0x60]
+    ldp x12, x13, [x16, #0x70]
+    ldp x14, x15, [x16, #0x80]
+    ldr x18, [x16, #0xA0]
+    ldp x19, x20, [x16, #0xA8]
+    ldp x21, x22, [x16, #0xB8]
+    ldp x23, x24, [x16, #0xC8]
+    ldp x25, x26, [x16, #0xD8]
+    ldp x27, x28, [x16, #0xE8]
+    ldp x29, x30, [x16, #0xF8]
+    ldr x17, [x16, #0x110]
+    br x17
+"#);
+
+/// The kind of fault which caused an exception
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExceptionType {
+    /// Executing an address which isn't mapped as executable
+    InvalidInstructionAccess,
+
+    /// Reading or writing an address which isn't mapped with the right permissions, such as a null pointer
+    InvalidDataAccess,
+
+    /// Executing an address which isn't aligned to 4 bytes
+    UnalignedInstructionAccess,
+
+    /// Reading or writing an address which isn't aligned for the access, or a misaligned stack pointer
+    UnalignedDataAccess,
+
+    /// Executing an instruction which isn't defined, such as `udf`
+    UndefinedInstruction,
+
+    /// Executing a trapping instruction, such as `brk`
+    ExceptionalInstruction,
+
+    /// An asynchronous error reported by the memory system
+    MemorySystemError,
+
+    /// A trapped floating point exception
+    FloatingPointException,
+
+    /// Calling an SVC which the process doesn't have access to
+    InvalidSystemCall,
+
+    /// Any other exception type
+    Unknown(u32),
+}
+
+impl ExceptionType {
+    /// Converts the raw `nn::os::UserExceptionType` into an exception type
+    pub fn from_raw(raw: u32) -> Self {
+        match raw {
+            0x100 => Self::InvalidInstructionAccess,
+            0x101 => Self::InvalidDataAccess,
+            0x102 => Self::UnalignedInstructionAccess,
+            0x103 => Self::UnalignedDataAccess,
+            0x104 => Self::UndefinedInstruction,
+            0x105 => Self::ExceptionalInstruction,
+            0x106 => Self::MemorySystemError,
+            0x200 => Self::FloatingPointException,
+            0x301 => Self::InvalidSystemCall,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// What the exception handler should do once the user handler has returned
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExceptionAction {
+    /// Resumes the faulting thread with the registers in the [`ExceptionInfo`], which should have
+    /// been changed to avoid the fault. x16 and x17 are clobbered when resuming.
+    Resume,
+
+    /// Lets the kernel terminate the process as if there was no handler
+    Abort,
+}
+
+/// A user exception handler, which is provided with the state of the faulting thread
+pub type ExceptionHandler = fn(&mut ExceptionInfo) -> ExceptionAction;
+
+/// The state of the thread which caused an exception, this is `nn::os::UserExceptionInfo`
+#[repr(C)]
+pub struct ExceptionInfo {
+    raw_type: u32,
+    _reserved: [u32; 3],
+
+    /// The general purpose registers x0-x28
+    pub registers: [CpuRegister; 29],
+
+    /// The frame pointer (x29)
+    pub fp: CpuRegister,
+
+    /// The link register (x30)
+    pub lr: CpuRegister,
+
+    /// The stack pointer
+    pub sp: CpuRegister,
+
+    /// The address of the instruction which faulted
+    pub pc: CpuRegister,
+
+    _padding: u64,
+
+    /// The NEON/SIMD registers
+    pub fpu_registers: [FpuRegister; 32],
+
+    /// The processor state, the top four bits are the condition flags
+    pub pstate: u32,
+
+    /// The auxiliary fault status registers
+    pub afsr0: u32,
+    pub afsr1: u32,
+
+    /// The exception syndrome register, which describes the cause of the exception
+    pub esr: u32,
+
+    /// The fault address register, which holds the address that was being accessed
+    /// for data and instruction aborts
+    pub far: CpuRegister,
+}
+
+impl ExceptionInfo {
+    /// Gets the kind of fault which caused the exception
+    pub fn exception_type(&self) -> ExceptionType {
+        ExceptionType::from_raw(self.raw_type)
+    }
+
+    /// Gets the address that was being accessed when the exception happened
+    pub fn fault_address(&self) -> u64 {
+        self.far.x()
+    }
+
+    /// Queries the memory region containing the fault address
+    pub fn fault_memory_info(&self) -> Result<MemoryInfo, u32> {
+        crate::nx::query_memory(self.fault_address())
+    }
+
+    /// Converts the faulting thread's registers into an [`InlineCtx`]
+    ///
+    /// The exception info does not hold the floating point control and status registers, so
+    /// `fpcr` and `fpsr` are left zeroed.
+    pub fn to_inline_ctx(&self) -> InlineCtx {
+        // SAFETY: the inline context is made of plain registers, so all zeroes is a valid value
+        let mut ctx: InlineCtx = unsafe { std::mem::zeroed() };
+
+        for (dst, src) in ctx.registers.iter_mut().zip(self.registers.iter()) {
+            *dst = *src;
+        }
+        ctx.registers[29] = self.fp;
+        ctx.registers[30] = self.lr;
+        ctx.sp = self.sp;
+        ctx.fpu_registers = self.fpu_registers;
+        ctx.pstate.set_bits(self.pstate as u64);
+        ctx.pc = self.pc.x();
+        ctx
+    }
+
+    /// Copies the registers from an [`InlineCtx`] back into the exception info, so that
+    /// the changes are applied when resuming with [`ExceptionAction::Resume`]
+    ///
+    /// The floating point control and status registers are not restored.
+    pub fn apply_inline_ctx(&mut self, ctx: &InlineCtx) {
+        for (dst, src) in self.registers.iter_mut().zip(ctx.registers.iter()) {
+            *dst = *src;
+        }
+        self.fp = ctx.registers[29];
+        self.lr = ctx.registers[30];
+        self.sp = ctx.sp;
+        self.fpu_registers = ctx.fpu_registers;
+        self.pstate = (self.pstate & 0x0FFF_FFFF) | (ctx.pstate.bits() as u32 & 0xF000_0000);
+        self.pc.set_x(ctx.pc);
+    }
+
+    /// Builds a backtrace of the faulting thread
+    ///
+    /// # Arguments
+    /// * `limit` - The maximum number of stack frames to move back through
+    pub fn backtrace(&self, limit: usize) -> Result<Backtrace, BacktraceError> {
+        Backtrace::new_from_inline_ctx(&self.to_inline_ctx(), limit)
+    }
+
+    /// Creates a machine-readable crash dump of the exception
+    ///
+    /// # Arguments
+    /// * `backtrace_limit` - The maximum number of frames in the backtrace
+    pub fn crash_dump(&self, backtrace_limit: usize) -> Result<CrashDump, BacktraceError> {
+        let backtrace = self.backtrace(backtrace_limit)?;
+        let reason = format!(
+            "{:?} at {:#x} accessing {:#x}",
+            self.exception_type(),
+            self.pc.x(),
+            self.fault_address()
+        );
+
+        Ok(CrashDump::from_backtrace(&backtrace).with_reason(reason))
+    }
+
+    /// Formats a crash report with the exception, the memory at the fault address,
+    /// the registers, and a backtrace with demangled symbols
+    ///
+    /// # Arguments
+    /// * `backtrace_limit` - The maximum number of frames in the backtrace
+    pub fn report(&self, backtrace_limit: usize) -> String {
+        let mut report = String::new();
+        let format = BacktraceFormat::new().demangle(true).strip_hashes(true);
+        let _ = self.write_report(&mut report, backtrace_limit, &format);
+        report
+    }
+
+    /// Writes a crash report into a formatting sink, see [`ExceptionInfo::report`]
+    ///
+    /// This never allocates unless the format demangles symbols, so it can be used with a
+    /// [`crate::nx::DebugLogWriter`] even if the exception happened inside of the allocator.
+    ///
+    /// # Arguments
+    /// * `out` - The sink to write the report to
+    /// * `backtrace_limit` - The maximum number of frames in the backtrace
+    /// * `format` - How the frames of the backtrace are written
+    pub fn write_report(&self, report: &mut dyn fmt::Write, backtrace_limit: usize, format: &BacktraceFormat) -> fmt::Result {
+        writeln!(report, "Exception: {:?} ({:#x})", self.exception_type(), self.raw_type)?;
+        writeln!(report, "Fault address: {:016x}  esr: {:08x}", self.fault_address(), self.esr)?;
+        writeln!(report, "Program ID: {:016x}", crate::nx::get_program_id())?;
+        writeln!(report, "Thread: {}", crate::nx::ThreadInfo::current())?;
+
+        match self.fault_memory_info() {
+            Ok(info) => writeln!(
+                report,
+                "Fault memory: {:016x}-{:016x}  type: {:#x}  perm: {}{}{}",
+                info.addr,
+                info.addr + info.size,
+                info.ty,
+                if info.perm & 1 != 0 { 'r' } else { '-' },
+                if info.perm & 2 != 0 { 'w' } else { '-' },
+                if info.perm & 4 != 0 { 'x' } else { '-' },
+            )?,
+            Err(e) => writeln!(report, "Fault memory: failed to query ({:#x})", e)?,
+        }
+
+        writeln!(report, "Registers:")?;
+        write!(report, "{}", self.to_inline_ctx())?;
+
+        writeln!(report, "Backtrace:")?;
+        match self.backtrace(backtrace_limit) {
+            Ok(backtrace) => backtrace.write_formatted(report, format),
+            Err(e) => writeln!(report, "      Failed to build a backtrace: {}", e),
+        }
+    }
+}
+
+extern "C" fn exception_entry(info: *mut ExceptionInfo) {
+    let info = unsafe { &mut *info };
+
+    let raw = EXCEPTION_HANDLER.load(Ordering::Acquire);
+    let action = if raw == 0 {
+        ExceptionAction::Abort
+    } else {
+        let handler = unsafe { std::mem::transmute::<usize, ExceptionHandler>(raw) };
+        handler(info)
+    };
+
+    match action {
+        ExceptionAction::Resume => unsafe { skex_exception_resume(info) },
+        ExceptionAction::Abort => crate::nx::return_from_exception(UNHANDLED_EXCEPTION_RESULT),
+    }
+}
+
+/// Sets the handler which is called when any thread in the process causes a CPU exception,
+/// replacing the previous one
+///
+/// This replaces any user exception handler which was registered by the game.
+///
+/// # Example
+/// ```ignore
+/// fn handler(info: &mut ExceptionInfo) -> ExceptionAction {
+///     if info.exception_type() == ExceptionType::UndefinedInstruction {
+///         // skip the instruction
+///         info.pc.set_x(info.pc.x() + 4);
+///         ExceptionAction::Resume
+///     } else {
+///         ExceptionAction::Abort
+///     }
+/// }
+///
+/// skyline::exception::set_exception_handler(handler);
+/// ```
+pub fn set_exception_handler(handler: ExceptionHandler) {
+    EXCEPTION_HANDLER.store(handler as usize, Ordering::Release);
+
+    REGISTER_HANDLER.call_once(|| unsafe {
+        nn_os_set_user_exception_handler(
+            exception_entry,
+            std::ptr::addr_of_mut!(EXCEPTION_STACK) as *mut u8,
+            EXCEPTION_STACK_SIZE,
+            std::ptr::addr_of_mut!(EXCEPTION_INFO) as *mut ExceptionInfo
+        );
+    });
+}
+
+fn crash_log_handler(info: &mut ExceptionInfo) -> ExceptionAction {
+    // A fault while writing the report would otherwise come straight back here
+    if IN_CRASH_HANDLER.swap(true, Ordering::AcqRel) {
+        return ExceptionAction::Abort;
+    }
+
+    let config = unsafe { CRASH_LOG_CONFIG.load(Ordering::Acquire).as_ref() };
+    let backtrace_limit = config.map_or(CrashLogConfig::default().get_backtrace_limit(), |config| config.get_backtrace_limit());
+
+    // The exception can come from inside of the allocator or while a lock is held, so the report
+    // is written to the debug log first without allocating, with symbols left mangled since
+    // demangling allocates
+    {
+        let mut log = crate::nx::DebugLogWriter::new();
+        let _ = info.write_report(&mut log, backtrace_limit, &BacktraceFormat::new());
+    }
+
+    // Writing the file needs the allocator and the file system, so it is only a best effort
+    if let Some(config) = config.filter(|config| config.get_directory().is_some()) {
+        let report = info.report(backtrace_limit);
+        let dump = if config.get_dumps() {
+            info.crash_dump(backtrace_limit).ok()
+        } else {
+            None
+        };
+        config.write_report_file(&report, dump.as_ref());
+    }
+
+    ExceptionAction::Abort
+}
+
+/// Sets an exception handler which writes a crash report for every exception and then
+/// lets the kernel terminate the process
+///
+/// The report is always written to the debug log first, without allocating or taking any locks.
+/// Writing it to the crash log directory afterwards needs both, so it is only attempted as a
+/// best effort and can fail if the exception happened inside of the allocator.
+///
+/// # Arguments
+/// * `config` - Where the crash reports are written to
+pub fn install_crash_handler(config: CrashLogConfig) {
+    // The previous configuration is leaked, as the handler could be reading it on another thread
+    CRASH_LOG_CONFIG.store(Box::into_raw(Box::new(config)), Ordering::Release);
+
+    set_exception_handler(crash_log_handler);
+}
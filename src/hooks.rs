@@ -1,18 +1,138 @@
+mod arguments;
 mod backtrace;
 mod contexts;
+mod demangle;
+mod group;
 mod registers;
+mod stack;
+mod unwind;
 
+pub use arguments::*;
 pub use backtrace::*;
 pub use contexts::*;
+pub use demangle::*;
+pub use group::*;
 pub use registers::*;
+pub use stack::*;
+pub use unwind::*;
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+use thiserror::Error;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum HookType {
     Callback,
     Inline,
     LegacyInline,
     Hook,
+    PostHook,
+    InlineAction,
+}
+
+impl HookType {
+    /// Converts the raw value used by the hooking backend into a hook type
+    ///
+    /// # Returns
+    /// `None` if the value is a hook type which this version of skyline doesn't know about
+    pub fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(Self::Callback),
+            1 => Some(Self::Inline),
+            2 => Some(Self::LegacyInline),
+            3 => Some(Self::Hook),
+            4 => Some(Self::PostHook),
+            5 => Some(Self::InlineAction),
+            _ => None,
+        }
+    }
+}
+
+/// The priority given to hooks which do not provide a `priority` argument
+pub const DEFAULT_HOOK_PRIORITY: i32 = 0;
+
+/// A single hook in the chain of hooks installed on an address
+///
+/// When multiple hooks (possibly from multiple plugins) are installed on the same address,
+/// they are ordered by their priority, highest first. The hook with the highest priority
+/// is the first to be called, and its trampoline leads to the hook with the next highest priority,
+/// and so on until the original function is reached.
+///
+/// Hooks with the same priority are ordered by the time they were installed, with the
+/// first hook to be installed being called first.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct HookChainEntry {
+    /// The user function which is called by this hook
+    pub user: *const (),
+
+    /// The priority that this hook was installed with
+    pub priority: i32,
+
+    /// The kind of hook, which is kept raw as the backend can know about more kinds than skyline
+    ty: u8,
+
+    /// Whether or not this hook is enabled. Disabled hooks remain in the chain
+    /// but will jump straight to their trampoline
+    pub is_enabled: bool,
+}
+
+impl HookChainEntry {
+    /// Gets the kind of hook
+    ///
+    /// Offset hooks which the backend can't generate the code for, such as ones returning an
+    /// [`InlineAction`] or with a `when` predicate, redirect to code generated by skyline instead. The
+    /// backend installs those like any other function hook, so they are reported as [`HookType::Hook`].
+    ///
+    /// # Returns
+    /// `None` if the backend installed a kind of hook which this version of skyline doesn't know about
+    pub fn ty(&self) -> Option<HookType> {
+        HookType::from_raw(self.ty)
+    }
+
+    /// Gets the raw value of the kind of hook, as written by the backend
+    pub fn raw_ty(&self) -> u8 {
+        self.ty
+    }
+
+    /// Gets the module which contains the user function of this hook
+    pub fn module(&self) -> Option<&'static crate::rtld::ModuleObject> {
+        crate::rtld::find_module_for_address(self.user as u64)
+    }
+}
+
+/// Gets every hook which is installed on the provided address, in the order that they are called
+///
+/// # Arguments
+/// * `address` - The address of the hooked function or instruction
+///
+/// # Returns
+/// The chain of hooks, the first entry being the first hook which runs. If nothing
+/// is hooked at the address then the chain is empty.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn get_hook_chain(address: *const ()) -> Vec<HookChainEntry> {
+    unsafe {
+        let count = ffi::skex_hooks_get_chain(address, std::ptr::null_mut(), 0);
+        let mut chain = Vec::with_capacity(count);
+        let written = ffi::skex_hooks_get_chain(address, chain.as_mut_ptr(), count);
+        chain.set_len(written.min(count));
+        chain
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum HookError {
+    #[error("The hooking backend failed to install the hook")]
+    InstallFailed,
+
+    #[error("Dynamic module \"{0}\" is not currently loaded, the hook state cannot be changed!")]
+    ModuleNotLoaded(&'static str),
+
+    #[error("Hook \"{member}\" of group \"{group}\" failed: {source}")]
+    GroupMemberFailed {
+        group: &'static str,
+        member: &'static str,
+        source: Box<HookError>,
+    },
 }
 
 #[doc(hidden)]
@@ -23,7 +143,8 @@ pub mod ffi {
             function: *const (),
             replace: *const (),
             out_trampoline: *mut *mut (),
-            hook_ty: super::HookType
+            hook_ty: super::HookType,
+            priority: i32
         );
 
         pub fn skex_hooks_install_on_symbol_future(
@@ -31,13 +152,15 @@ pub mod ffi {
             name: *const u8,
             replace: *const (),
             out_trampoline: *mut *mut (),
-            hook_ty: super::HookType
+            hook_ty: super::HookType,
+            priority: i32
         );
 
         pub fn skex_hooks_install(
             symbol: *const (),
             replace: *const (), 
-            hook_ty: super::HookType
+            hook_ty: super::HookType,
+            priority: i32
         ) -> *const ();
 
         pub fn skex_hooks_install_on_dynamic_load(
@@ -45,7 +168,8 @@ pub mod ffi {
             replace: *const (),
             out_trampoline: *mut *mut (),
             name: *const u8,
-            hook_ty: super::HookType
+            hook_ty: super::HookType,
+            priority: i32
         );
 
         pub fn skex_hooks_set_enable(user: *const (), symbol: *const (), enable: bool);
@@ -53,5 +177,11 @@ pub mod ffi {
         pub fn skex_hooks_uninstall(user: *const ());
         
         pub fn skex_hooks_uninstall_from_symbol(user: *const ());
+
+        pub fn skex_hooks_get_chain(
+            symbol: *const (),
+            out_entries: *mut super::HookChainEntry,
+            max_entries: usize
+        ) -> usize;
     }
 }
\ No newline at end of file
@@ -0,0 +1,111 @@
+/// The register class which an argument is passed in, as defined by the AAPCS64.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArgumentClass {
+    /// Integers, booleans, and pointers, which are passed in x0-x7
+    General,
+
+    /// Floating point values, which are passed in v0-v7
+    Vector,
+}
+
+/// A value which can be passed in a single register under the AAPCS64.
+///
+/// Arguments of each class are assigned to registers in order, the first general argument
+/// goes in x0, the first vector argument goes in v0, and so on. Once the eight registers of a
+/// class are used up, the remaining arguments of that class are passed on the stack in 8-byte slots.
+pub trait Argument: Copy {
+    /// The register class that this type is passed in
+    const CLASS: ArgumentClass;
+
+    /// Converts the lowest bits of a register into this type
+    fn from_bits(bits: u64) -> Self;
+
+    /// Converts this type into the bits of a register.
+    ///
+    /// Signed integers are sign extended to fill all 64 bits, while unsigned integers, booleans,
+    /// and `f32` are zero extended.
+    fn to_bits(self) -> u64;
+}
+
+macro_rules! impl_int_argument {
+    ($extension:literal: $($ty:ty),*) => {
+        $(
+            impl Argument for $ty {
+                const CLASS: ArgumentClass = ArgumentClass::General;
+
+                fn from_bits(bits: u64) -> Self {
+                    bits as Self
+                }
+
+                #[doc = concat!("Converts the integer into the bits of a register, ", $extension, " it")]
+                fn to_bits(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    }
+}
+
+impl_int_argument!("zero extending": u8, u16, u32, u64, usize);
+impl_int_argument!("sign extending": i8, i16, i32, i64, isize);
+
+impl Argument for bool {
+    const CLASS: ArgumentClass = ArgumentClass::General;
+
+    fn from_bits(bits: u64) -> Self {
+        // Only the lowest byte of a boolean argument is defined
+        bits as u8 != 0
+    }
+
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+}
+
+impl<T> Argument for *const T {
+    const CLASS: ArgumentClass = ArgumentClass::General;
+
+    fn from_bits(bits: u64) -> Self {
+        bits as usize as Self
+    }
+
+    fn to_bits(self) -> u64 {
+        self as usize as u64
+    }
+}
+
+impl<T> Argument for *mut T {
+    const CLASS: ArgumentClass = ArgumentClass::General;
+
+    fn from_bits(bits: u64) -> Self {
+        bits as usize as Self
+    }
+
+    fn to_bits(self) -> u64 {
+        self as usize as u64
+    }
+}
+
+impl Argument for f32 {
+    const CLASS: ArgumentClass = ArgumentClass::Vector;
+
+    fn from_bits(bits: u64) -> Self {
+        f32::from_bits(bits as u32)
+    }
+
+    fn to_bits(self) -> u64 {
+        f32::to_bits(self) as u64
+    }
+}
+
+impl Argument for f64 {
+    const CLASS: ArgumentClass = ArgumentClass::Vector;
+
+    fn from_bits(bits: u64) -> Self {
+        f64::from_bits(bits)
+    }
+
+    fn to_bits(self) -> u64 {
+        f64::to_bits(self)
+    }
+}
@@ -1,6 +1,10 @@
 use std::fmt;
 
 use super::contexts;
+use super::demangle::demangle;
+use super::unwind::{UnwindError, UnwindRegisters};
+
+use crate::rtld::ModuleObject;
 
 use thiserror::Error;
 
@@ -20,7 +24,10 @@ pub enum BacktraceError {
     RecursiveFramePointer,
 
     #[error("The backtrace is longer than the provided limit")]
-    BacktraceLimitReached
+    BacktraceLimitReached,
+
+    #[error("Failed to unwind the stack: {0}")]
+    Unwind(#[from] UnwindError),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -50,260 +57,623 @@ impl BacktraceEntry {
     }
 }
 
-#[derive(Debug)]
-pub struct Backtrace {
-    current_frame: Option<BacktraceEntry>,
-    current_lr: u64,
-    backtrace: [Option<Result<BacktraceEntry, BacktraceError>>; 33],
+/// A single frame of a backtrace, with the module and symbol that it returns into
+#[derive(Copy, Clone, Default)]
+pub struct Frame {
+    /// The address that this frame returns to
+    pub return_address: u64,
+
+    /// The frame pointer of the function that is returned to
+    pub frame_pointer: u64,
+
+    /// The module containing the return address
+    pub module: Option<&'static ModuleObject>,
+
+    /// The symbol containing the return address
+    pub symbol: Option<&'static str>,
+
+    /// The offset of the return address from the start of the symbol, or from
+    /// the start of the module if there is no symbol
+    pub offset: u64,
 }
 
-impl Backtrace {
-    fn demangle_symbol(symbol: &'static str) -> String {
-        extern "C" {
-            fn __cxa_demangle(mangled: *const u8, buffer: *mut u8, length: &mut usize, status: &mut i32) -> *mut u8;
-            fn free(ptr: *mut u8);
-            fn strlen(str: *const u8) -> i32;
+impl Frame {
+    /// Creates a frame, looking up the module and symbol which contain the return address
+    ///
+    /// # Arguments
+    /// * `return_address` - The address that the frame returns to
+    /// * `frame_pointer` - The frame pointer of the function that is returned to
+    pub fn resolve(return_address: u64, frame_pointer: u64) -> Self {
+        let module = crate::rtld::find_module_for_address(return_address);
+        let symbol = module.and_then(|module| module.find_symbol_for_address(return_address));
+
+        let offset = match (module, symbol) {
+            (_, Some((_, start))) => return_address - start,
+            (Some(module), None) => return_address - module.module_base as u64,
+            (None, None) => 0,
+        };
+
+        Self {
+            return_address,
+            frame_pointer,
+            module,
+            symbol: symbol.map(|(name, _)| name),
+            offset,
         }
+    }
+
+    /// Gets the name of the module containing the return address
+    pub fn module_name(&self) -> Option<&'static str> {
+        self.module.map(|module| module.get_module_name().unwrap_or("unknown"))
+    }
 
-        unsafe {
-            let mut out_length = 0usize;
-            let mut out_status = 0i32;
-            let out_buffer = __cxa_demangle([symbol, "\0"].concat().as_ptr(), std::ptr::null_mut(), &mut out_length, &mut out_status);
-            let result = if out_status == 0 && !out_buffer.is_null() {
-                let len = strlen(out_buffer);
-                std::str::from_utf8_unchecked(std::slice::from_raw_parts(out_buffer, len as usize)).to_string()
+    /// Gets the offset of the return address from the start of its module
+    pub fn module_offset(&self) -> Option<u64> {
+        self.module.map(|module| self.return_address - module.module_base as u64)
+    }
+
+    /// Gets the demangled name of the symbol containing the return address
+    ///
+    /// # Arguments
+    /// * `strip_hash` - Whether to leave out the hashes in Rust symbols
+    pub fn demangled_symbol(&self, strip_hash: bool) -> Option<String> {
+        self.symbol.map(|symbol| demangle(symbol, strip_hash))
+    }
+
+    /// Writes the frame into a formatting sink as described by the provided format
+    ///
+    /// The return address always comes first, followed by whichever of the module offset,
+    /// symbol, and frame pointer the format includes.
+    pub fn write_formatted(&self, out: &mut dyn fmt::Write, format: &BacktraceFormat) -> fmt::Result {
+        write!(out, "{:016x}", self.return_address)?;
+        if let (true, Some(name), Some(offset)) = (format.module_offsets, self.module_name(), self.module_offset()) {
+            write!(out, " ({} + {:#x})", name, offset)?;
+        }
+        if let Some(symbol) = self.symbol {
+            let demangled;
+            let symbol = if format.demangle {
+                demangled = demangle(symbol, format.strip_hashes);
+                demangled.as_str()
             } else {
-                symbol.to_string()
+                symbol
             };
-            if !out_buffer.is_null() {
-                free(out_buffer);
+
+            if format.symbol_offsets {
+                write!(out, " ({} + {:#x})", symbol, self.offset)?;
+            } else {
+                write!(out, " ({})", symbol)?;
+            }
+        }
+        if format.frame_pointers && self.frame_pointer != 0 {
+            write!(out, " [fp: {:#x}]", self.frame_pointer)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&BacktraceEntry> for Frame {
+    fn from(entry: &BacktraceEntry) -> Self {
+        Self::resolve(entry.frame.return_address, entry.frame.previous_frame as u64)
+    }
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("return_address", &format_args!("{:#x}", self.return_address))
+            .field("frame_pointer", &format_args!("{:#x}", self.frame_pointer))
+            .field("module", &self.module_name())
+            .field("symbol", &self.symbol)
+            .field("offset", &format_args!("{:#x}", self.offset))
+            .finish()
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = BacktraceFormat::new().demangle(f.alternate());
+        self.write_formatted(f, &format)
+    }
+}
+
+// The unwinding state is kept inline so that walking the stack never allocates
+#[allow(clippy::large_enum_variant)]
+enum FramesState {
+    FramePointer {
+        previous: *mut StackFrame,
+        current: *mut StackFrame,
+    },
+    Unwind(UnwindRegisters),
+    Done,
+}
+
+/// An iterator which walks the stack one frame at a time.
+///
+/// This doesn't allocate and has no depth limit of its own, use [`Iterator::take`] to cap it
+/// or [`Frames::fill`] to resolve the frames into a buffer owned by the caller.
+/// The iterator ends after the first error.
+pub struct Frames {
+    state: FramesState,
+}
+
+impl Frames {
+    /// Walks the stack by following the frame pointer chain, starting with the provided frame
+    pub fn from_frame_pointer(fp: *mut StackFrame) -> Self {
+        Self {
+            state: FramesState::FramePointer {
+                previous: std::ptr::null_mut(),
+                current: fp,
+            }
+        }
+    }
+
+    /// Walks the stack by unwinding from the provided registers, see [`Backtrace::new_unwound`]
+    pub fn from_registers(registers: UnwindRegisters) -> Self {
+        Self {
+            state: FramesState::Unwind(registers)
+        }
+    }
+
+    /// Walks the stack by unwinding from the location this is called from
+    #[inline(always)]
+    pub fn current() -> Self {
+        Self::from_registers(UnwindRegisters::current())
+    }
+
+    /// Resolves the next frames into the provided buffer
+    ///
+    /// # Arguments
+    /// * `buffer` - The buffer to write the frames to
+    ///
+    /// # Returns
+    /// The number of frames that were written, which is less than the length of the buffer
+    /// if the end of the stack or an error was reached first
+    pub fn fill(&mut self, buffer: &mut [Frame]) -> usize {
+        let mut count = 0;
+        for slot in buffer.iter_mut() {
+            match self.next() {
+                Some(Ok(frame)) => *slot = frame,
+                _ => break,
             }
-            result
+            count += 1;
         }
+        count
     }
 
-    fn get_formatted_addr_(address: u64, demangle: bool) -> String {
-        if let Some(object) = crate::rtld::find_module_for_address(address) {
-            let module_offset = address - object.module_base as u64;
-            let name = object.get_module_name().unwrap_or("unknown");
-            if let Some((sym_name, start)) = object.find_symbol_for_address(address) {
-                let symbol_offset = address - start;
-                if demangle {
-                    format!("{:016x} ({} + {:#x}) ({} + {:#x})", address, name, module_offset, Self::demangle_symbol(sym_name), symbol_offset)
-                } else {
-                    format!("{:016x} ({} + {:#x}) ({} + {:#x})", address, name, module_offset, sym_name, symbol_offset)
+    fn next_entry(&mut self) -> Option<Result<BacktraceEntry, BacktraceError>> {
+        match self.state {
+            FramesState::FramePointer { previous, current } => {
+                // check if the frame pointer is null, if so we are done with the backtrace
+                if current.is_null() {
+                    self.state = FramesState::Done;
+                    return None;
                 }
-            } else {
-                format!("{:016x} ({} + {:#x})", address, name, module_offset)
+
+                // check if the previous frame pointer is equal to our current one
+                // if so, we are going to be recursive so we might as well just end
+                if previous == current {
+                    self.state = FramesState::Done;
+                    return Some(Err(BacktraceError::RecursiveFramePointer));
+                }
+
+                let entry = BacktraceEntry::new(std::ptr::NonNull::new(current).unwrap());
+
+                // move forwards in the list
+                self.state = FramesState::FramePointer {
+                    previous: current,
+                    current: entry.frame.previous_frame,
+                };
+
+                Some(Ok(entry))
+            },
+            FramesState::Unwind(registers) => match registers.unwind() {
+                Ok(caller) => {
+                    self.state = FramesState::Unwind(caller);
+
+                    // There isn't necessarily a frame record for every unwound frame, so the entry
+                    // is placed directly below the caller's stack pointer to keep
+                    // `get_previous_stack_pointer` meaningful
+                    Some(Ok(BacktraceEntry {
+                        ptr: (caller.sp as *mut StackFrame).wrapping_sub(1),
+                        frame: StackFrame {
+                            previous_frame: caller.fp() as _,
+                            return_address: caller.pc,
+                        }
+                    }))
+                },
+                Err(UnwindError::EndOfStack) => {
+                    self.state = FramesState::Done;
+                    None
+                },
+                Err(e) => {
+                    self.state = FramesState::Done;
+                    Some(Err(e.into()))
+                }
+            },
+            FramesState::Done => None,
+        }
+    }
+}
+
+impl Iterator for Frames {
+    type Item = Result<Frame, BacktraceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().map(|entry| entry.map(|entry| Frame::from(&entry)))
+    }
+}
+
+/// Describes how a [`Backtrace`] or [`Frame`] is formatted.
+///
+/// The same format is used whether the backtrace is written with [`fmt::Display`],
+/// into a [`fmt::Write`], or into an [`std::io::Write`].
+///
+/// # Example
+/// ```ignore
+/// let format = BacktraceFormat::new()
+///     .demangle(true)
+///     .strip_hashes(true)
+///     .frame_pointers(true);
+/// println!("{}", backtrace.display(format));
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct BacktraceFormat {
+    demangle: bool,
+    strip_hashes: bool,
+    module_offsets: bool,
+    symbol_offsets: bool,
+    frame_pointers: bool,
+    multi_line: bool,
+}
+
+impl Default for BacktraceFormat {
+    fn default() -> Self {
+        Self {
+            demangle: false,
+            strip_hashes: false,
+            module_offsets: true,
+            symbol_offsets: true,
+            frame_pointers: false,
+            multi_line: true,
+        }
+    }
+}
+
+impl BacktraceFormat {
+    /// Creates the default format, which writes every frame on its own line with mangled
+    /// symbols and both the module and symbol offsets
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether symbol names are demangled
+    pub fn demangle(mut self, demangle: bool) -> Self {
+        self.demangle = demangle;
+        self
+    }
+
+    /// Sets whether the hashes in Rust symbols are left out when they are demangled
+    pub fn strip_hashes(mut self, strip: bool) -> Self {
+        self.strip_hashes = strip;
+        self
+    }
+
+    /// Sets whether the module and the offset from its base are written for every frame
+    pub fn module_offsets(mut self, show: bool) -> Self {
+        self.module_offsets = show;
+        self
+    }
+
+    /// Sets whether the offset from the start of the symbol is written after its name
+    pub fn symbol_offsets(mut self, show: bool) -> Self {
+        self.symbol_offsets = show;
+        self
+    }
+
+    /// Sets whether the frame pointer of every frame is written
+    pub fn frame_pointers(mut self, show: bool) -> Self {
+        self.frame_pointers = show;
+        self
+    }
+
+    /// Sets whether every frame is written on its own line, or the whole backtrace on a single line
+    pub fn multi_line(mut self, multi_line: bool) -> Self {
+        self.multi_line = multi_line;
+        self
+    }
+}
+
+/// Lets an [`std::io::Write`] be used as a [`fmt::Write`], keeping the io error which
+/// [`fmt::Error`] can't carry
+struct IoAdapter<'a, W: std::io::Write> {
+    writer: &'a mut W,
+    error: std::io::Result<()>,
+}
+
+impl<W: std::io::Write> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Err(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Runs a formatter against an [`std::io::Write`]
+fn write_io<W, F>(writer: &mut W, formatter: F) -> std::io::Result<()>
+where
+    W: std::io::Write,
+    F: FnOnce(&mut dyn fmt::Write) -> fmt::Result,
+{
+    let mut adapter = IoAdapter { writer, error: Ok(()) };
+    match formatter(&mut adapter) {
+        Ok(()) => Ok(()),
+        // A formatting error without an io error came from a Display impl, which std reports the same way
+        Err(_) => adapter.error.and(Err(std::io::Error::other("formatter error"))),
+    }
+}
+
+/// A [`Backtrace`] paired with the format it is displayed in, see [`Backtrace::display`]
+pub struct DisplayBacktrace<'a> {
+    backtrace: &'a Backtrace,
+    format: BacktraceFormat,
+}
+
+impl fmt::Display for DisplayBacktrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.backtrace.write_formatted(f, &self.format)
+    }
+}
+
+/// The most frames a [`Backtrace`] can hold, deeper stacks can be walked with [`Frames`]
+pub const MAX_BACKTRACE_DEPTH: usize = 128;
+
+const EMPTY_ENTRY: BacktraceEntry = BacktraceEntry {
+    ptr: std::ptr::null_mut(),
+    frame: StackFrame {
+        previous_frame: std::ptr::null_mut(),
+        return_address: 0,
+    },
+};
+
+/// A captured stack backtrace.
+///
+/// The frames are stored inline, so capturing a backtrace never allocates. Limits above
+/// [`MAX_BACKTRACE_DEPTH`] are capped to it, use [`Frames::fill`] with a buffer owned by the
+/// caller to capture deeper stacks.
+pub struct Backtrace {
+    current_frame: Option<BacktraceEntry>,
+    current_lr: u64,
+    entries: [BacktraceEntry; MAX_BACKTRACE_DEPTH],
+    len: usize,
+    error: Option<BacktraceError>,
+    format: BacktraceFormat,
+}
+
+impl Backtrace {
+    /// Creates a backtrace from up to `limit` entries of the stack walker, marking it as
+    /// truncated if there were more
+    fn from_frames(mut frames: Frames, current_frame: Option<BacktraceEntry>, current_lr: u64, limit: usize) -> Self {
+        let mut backtrace = Self {
+            current_frame,
+            current_lr,
+            entries: [EMPTY_ENTRY; MAX_BACKTRACE_DEPTH],
+            len: 0,
+            error: None,
+            format: BacktraceFormat::new(),
+        };
+
+        for _ in 0..limit.min(MAX_BACKTRACE_DEPTH) {
+            match frames.next_entry() {
+                Some(Ok(entry)) => {
+                    backtrace.entries[backtrace.len] = entry;
+                    backtrace.len += 1;
+                },
+                Some(Err(e)) => {
+                    backtrace.error = Some(e);
+                    return backtrace;
+                },
+                None => return backtrace,
             }
-        } else {
-            format!("{:016x}", address)
         }
+
+        // if there are frames left then we reached our limit, we should record an error to reflect that
+        if frames.next_entry().is_some() {
+            backtrace.error = Some(BacktraceError::BacktraceLimitReached);
+        }
+
+        backtrace
     }
 
     /// Builds a new stack backtrace based on the provided frame pointer and return address
-    /// 
+    ///
     /// # Arguments
     /// * `current_fp` - The pointer to the current stack frame
     /// * `current_lr` - The current return address
     /// * `limit` - The maximum number of stack frames to move back through
-    /// 
+    ///
     /// # Returns
     /// * `Ok(Backtrace)` - A successfully created backtrace
     /// * `Err(BacktraceError)` - A failed backtrace
     #[allow(clippy::not_unsafe_ptr_arg_deref)]
-    pub fn new(mut current_fp: *mut StackFrame, current_lr: u64, mut limit: usize) -> Result<Self, BacktraceError> {
+    pub fn new(current_fp: *mut StackFrame, current_lr: u64, limit: usize) -> Result<Self, BacktraceError> {
         // if the frame pointer is null then we can't really generate a stack trace any more meaningful
         // than the provided lr, which the caller should already have
         if current_fp.is_null() {
             return Err(BacktraceError::InitialFPNull);
         }
 
-        limit = limit.max(32);
-
-        unsafe {
-            let current_frame = *current_fp;
-            // If the current stack frame's LR is not the same as what
-            // was provided, we can assume that the backtrace is being generated in 
-            // one of three contexts:
-            // 1. The surrounding function does not make use of the frame pointer and does not
-            //      push it, which usually means that they aren't calling any other functions
-            //      You can see an example of this here: https://godbolt.org/z/Weza98z3q
-            //      Here, `main` pushes the frame pointer, calls `something` which pushes the frame pointer
-            //      which then calls `something2`, which uses the stack but doesn't push the frame pointer
-            //      since it doesn't need to worry about any internal function calls messing up
-            //      the x30 register (which is used as the return address)
-            // 2. We are generating a backtrace before the function has changed the frame pointer
-            // 3. We are generating a backtrace after the function has called (and returned from)
-            //      another function. This case is indistinguishable from 1 without human intervention
-            let mut prev_fp;
-            let start_frame = if current_frame.return_address != current_lr {
-                prev_fp = std::ptr::null_mut();
-                None
-            } else {
-                let entry = BacktraceEntry::new(std::ptr::NonNull::new(current_fp).unwrap());
-                prev_fp = current_fp;
-                current_fp = entry.frame.previous_frame;
-                Some(entry)
-            };
-
-            // count the current entry as one of our max count
-            limit -= 1;
-
-            // create our backtrace vector
-            let mut entries = [None; 33];
-
-            let mut count = 0;
-            while limit > 0 {
-                // check if the frame pointer is null, if so we are done with the backtrace
-                if current_fp.is_null() {
-                    break;
-                }
-                
-                // check if the previous frame pointer is equal to our current one
-                // if so, we are going to be recursive so we might as well just end
-                if prev_fp == current_fp {
-                    entries[count] = Some(Err(BacktraceError::RecursiveFramePointer));
-                    count += 1;
-                    break;
-                }
-
-                let entry = BacktraceEntry::new(std::ptr::NonNull::new(current_fp).unwrap());
-
-                // move forwards in the list
-                prev_fp = current_fp;
-                current_fp = entry.frame.previous_frame;
+        let mut frames = Frames::from_frame_pointer(current_fp);
+
+        let current_frame = unsafe { *current_fp };
+        // If the current stack frame's LR is not the same as what
+        // was provided, we can assume that the backtrace is being generated in
+        // one of three contexts:
+        // 1. The surrounding function does not make use of the frame pointer and does not
+        //      push it, which usually means that they aren't calling any other functions
+        //      You can see an example of this here: https://godbolt.org/z/Weza98z3q
+        //      Here, `main` pushes the frame pointer, calls `something` which pushes the frame pointer
+        //      which then calls `something2`, which uses the stack but doesn't push the frame pointer
+        //      since it doesn't need to worry about any internal function calls messing up
+        //      the x30 register (which is used as the return address)
+        // 2. We are generating a backtrace before the function has changed the frame pointer
+        // 3. We are generating a backtrace after the function has called (and returned from)
+        //      another function. This case is indistinguishable from 1 without human intervention
+        let start_frame = if current_frame.return_address != current_lr {
+            None
+        } else {
+            frames.next_entry().and_then(Result::ok)
+        };
 
-                // push current entry
-                entries[count] = Some(Ok(entry));
-                count += 1;
+        // count the current entry as one of our max count
+        let limit = limit.saturating_sub(1);
 
-                limit -= 1;
-            }
+        Ok(Self::from_frames(frames, start_frame, current_lr, limit))
+    }
 
-            // if we reached our limit then we should push an error to reflect that
-            if limit == 0 {
-                entries[count] = Some(Err(BacktraceError::BacktraceLimitReached));
-            }
+    /// Builds a new stack backtrace by unwinding from the provided registers
+    ///
+    /// This uses the call frame information of each module, so unlike [`Backtrace::new`] it
+    /// is accurate for leaf functions and functions which don't keep a frame pointer.
+    /// Frames without call frame information fall back on the frame pointer chain.
+    ///
+    /// # Arguments
+    /// * `registers` - The registers at the location to unwind from
+    /// * `limit` - The maximum number of stack frames to move back through
+    ///
+    /// # Returns
+    /// * `Ok(Backtrace)` - A successfully created backtrace
+    /// * `Err(BacktraceError)` - A failed backtrace
+    pub fn new_unwound(registers: UnwindRegisters, limit: usize) -> Result<Self, BacktraceError> {
+        Ok(Self::from_frames(Frames::from_registers(registers), None, registers.lr(), limit))
+    }
 
-            Ok(Self {
-                current_frame: start_frame,
-                current_lr,
-                backtrace: entries
-            })
-        }
+    /// Builds a new stack backtrace by unwinding from the location this is called from
+    ///
+    /// # Arguments
+    /// * `limit` - The maximum number of stack frames to move back through
+    ///
+    /// # Returns
+    /// * `Ok(Backtrace)` - A successfully created backtrace
+    /// * `Err(BacktraceError)` - A failed backtrace
+    #[inline(always)]
+    pub fn capture(limit: usize) -> Result<Self, BacktraceError> {
+        Self::new_unwound(UnwindRegisters::current(), limit)
     }
 
-    /// Builds a new callstack backtrace based on the [`contexts::InlineCtx`]
-    /// 
+    /// Builds a new callstack backtrace based on the [`contexts::LegacyInlineCtx`]
+    ///
     /// # Arguments
     /// * `ctx` - The inline hook context
     /// * `limit` - The maximum number of stack frames to move back through
-    /// 
+    ///
     /// # Returns
     /// * `Ok(Backtrace)` - A successfully created backtrace
     /// * `Err(BacktraceError)` - A failed backtrace
     pub fn new_from_legacy_inline_ctx(ctx: &contexts::LegacyInlineCtx, limit: usize) -> Result<Self, BacktraceError> {
-        Self::new(ctx.registers[29].x() as _, ctx.registers[30].x(), limit)
+        Self::new(ctx.fp().x() as _, ctx.lr().x(), limit)
     }
 
-    /// Builds a new callstack backtrace based on the [`contexts::ExInlineCtx`]
-    /// 
+    /// Builds a new callstack backtrace based on the [`contexts::InlineCtx`]
+    ///
+    /// The stack is unwound with the call frame information, see [`Backtrace::new_unwound`]
+    ///
     /// # Arguments
     /// * `ctx` - The extended inline hook context
     /// * `limit` - The maximum number of stack frames to move back through
-    /// 
+    ///
     /// # Returns
     /// * `Ok(Backtrace)` - A successfully created backtrace
     /// * `Err(BacktraceError)` - A failed backtrace
     pub fn new_from_inline_ctx(ctx: &contexts::InlineCtx, limit: usize) -> Result<Self, BacktraceError> {
-        Self::new(ctx.registers[29].x() as _, ctx.registers[30].x(), limit)
+        Self::new_unwound(UnwindRegisters::from_inline_ctx(ctx), limit)
     }
-}
 
-impl fmt::Display for Backtrace {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "Current LR: {}", Self::get_formatted_addr_(self.current_lr, f.alternate()))?;
-        let mut current = 0;
-        if let Some(current_frame) = self.current_frame.as_ref() {
-            writeln!(f, "      [{:02}]: {}", current, Self::get_formatted_addr_(current_frame.frame.return_address, f.alternate()))?;
-            current += 1;
-        }
-        for entry in self.backtrace.iter() {
-            match entry {
-                Some(Ok(entry)) => writeln!(
-                    f,
-                    "      [{:02}]: {}",
-                    current,
-                    Self::get_formatted_addr_(entry.frame.return_address, f.alternate())
-                )?,
-                Some(Err(e)) => writeln!(
-                    f,
-                    "      [{:02}]: {}",
-                    current,
-                    e
-                )?,
-                None => break,
-            }
-            current += 1;
-        }
-        Ok(())
+    /// Sets whether the hashes in Rust symbols are left out when the backtrace is displayed
+    /// in the alternate format (`{:#}`), which demangles symbols
+    pub fn strip_hashes(mut self, strip: bool) -> Self {
+        self.format = self.format.strip_hashes(strip);
+        self
     }
-}
 
+    /// Sets the format used when the backtrace is displayed or written with [`Backtrace::write`]
+    pub fn format(mut self, format: BacktraceFormat) -> Self {
+        self.format = format;
+        self
+    }
 
+    /// Gets the return address that the backtrace was started from
+    pub fn current_lr(&self) -> u64 {
+        self.current_lr
+    }
 
-#[cfg(feature = "static-module")]
-impl Backtrace {
-    pub fn write_formatted_addr<W: std::io::Write>(writer: &mut W, address: u64) -> std::io::Result<()> {
-        if let Some(object) = crate::rtld::find_module_for_address(address) {
-            let module_offset = address - object.module_base as u64;
-            let name = object.get_module_name().unwrap_or("unknown");
-            if let Some((sym_name, start)) = object.find_symbol_for_address(address) {
-                let symbol_offset = address - start;
-                write!(writer, "{:016x} ({} + {:#x}) ({} + {:#x})", address, name, module_offset, sym_name, symbol_offset)
+    /// Writes the backtrace into a formatting sink as described by the provided format
+    pub fn write_formatted(&self, out: &mut dyn fmt::Write, format: &BacktraceFormat) -> fmt::Result {
+        write!(out, "Current LR: ")?;
+        Frame::resolve(self.current_lr, 0).write_formatted(out, format)?;
+
+        for (idx, frame) in self.frames().enumerate() {
+            if format.multi_line {
+                write!(out, "\n      [{:02}]: ", idx)?;
             } else {
-                write!(writer, "{:016x} ({} + {:#x})", address, name, module_offset)
+                write!(out, " | [{:02}]: ", idx)?;
             }
-        } else {
-            write!(writer, "{:016x}", address)
-        }
-    }
 
-    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-        write!(writer, "Current LR: ")?;
-        Self::write_formatted_addr(writer, self.current_lr)?;
-        writeln!(writer)?;
-        let mut current = 0;
-        if let Some(current_frame) = self.current_frame.as_ref() {
-            write!(writer, "      [{:02}]: ", current)?;
-            Self::write_formatted_addr(writer, current_frame.frame.return_address)?;
-            writeln!(writer)?;
-            current += 1;
-        }
-        for entry in self.backtrace.iter() {
-            match entry {
-                Some(Ok(entry)) => {
-                    write!(
-                        writer,
-                        "      [{:02}]: ",
-                        current
-                    )?;
-                    Self::write_formatted_addr(writer, entry.frame.return_address)?;
-                    writeln!(writer)?;
-                },
-                Some(Err(e)) => writeln!(
-                    writer,
-                    "      [{:02}]: {}",
-                    current,
-                    e
-                )?,
-                None => break,
+            match frame {
+                Ok(frame) => frame.write_formatted(out, format)?,
+                Err(e) => write!(out, "{}", e)?,
             }
-            current += 1;
         }
+
+        if format.multi_line {
+            writeln!(out)?;
+        }
+
         Ok(())
     }
+
+    /// Writes the backtrace into an [`std::io::Write`] in the format set with [`Backtrace::format`]
+    pub fn write<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        write_io(writer, |out| self.write_formatted(out, &self.format))
+    }
+
+    /// Writes a single address into an [`std::io::Write`], along with its module and symbol
+    pub fn write_formatted_addr<W: std::io::Write>(writer: &mut W, address: u64) -> std::io::Result<()> {
+        write_io(writer, |out| Frame::resolve(address, 0).write_formatted(out, &BacktraceFormat::new()))
+    }
+
+    /// Pairs the backtrace with a format, for use with `format!` and friends
+    pub fn display(&self, format: BacktraceFormat) -> DisplayBacktrace<'_> {
+        DisplayBacktrace { backtrace: self, format }
+    }
+
+    /// Gets an iterator over the frames of the backtrace, starting with the innermost one
+    pub fn frames(&self) -> impl Iterator<Item = Result<Frame, BacktraceError>> + '_ {
+        self.current_frame
+            .iter()
+            .map(|entry| Ok(Frame::from(entry)))
+            .chain(self.entries[..self.len].iter().map(|entry| Ok(Frame::from(entry))))
+            .chain(self.error.map(Err))
+    }
+}
+
+impl fmt::Debug for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Backtrace")
+            .field("current_frame", &self.current_frame)
+            .field("current_lr", &format_args!("{:#x}", self.current_lr))
+            .field("entries", &&self.entries[..self.len])
+            .field("error", &self.error)
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = if f.alternate() { self.format.demangle(true) } else { self.format };
+        self.write_formatted(f, &format)
+    }
 }
 
 #[macro_export]
@@ -324,4 +694,4 @@ macro_rules! get_backtrace {
     }}
 }
 
-pub use get_backtrace;
\ No newline at end of file
+pub use get_backtrace;
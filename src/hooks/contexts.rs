@@ -1,4 +1,10 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use super::arguments::*;
 use super::registers::*;
+use super::stack::{StackError, StackView};
+use super::unwind::{UnwindError, UnwindRegisters};
 
 /// The state of the general purpose registers.
 /// 
@@ -13,13 +19,66 @@ pub struct LegacyInlineCtx {
     pub registers: [CpuRegister; 31]
 }   
 
+impl LegacyInlineCtx {
+    /// Gets the frame pointer (x29)
+    pub fn fp(&self) -> CpuRegister {
+        self[X::FP]
+    }
+
+    /// Gets the link register (x30)
+    pub fn lr(&self) -> CpuRegister {
+        self[X::LR]
+    }
+
+    /// Gets the `n`th argument of type `T`, assuming the hook is at the start of a function
+    ///
+    /// Arguments are counted separately for each [`ArgumentClass`], so for
+    /// `fn(a: *const Foo, b: f32, c: u32)`, `c` is argument 1, not argument 2.
+    ///
+    /// # Panics
+    /// This context does not contain the NEON/SIMD registers or the stack pointer, so this will panic
+    /// if `T` is a floating point type or if `n` is not one of the eight argument registers
+    pub fn arg<T: Argument>(&self, n: usize) -> T {
+        assert!(T::CLASS == ArgumentClass::General, "LegacyInlineCtx does not contain the NEON/SIMD registers");
+        assert!(n < 8, "LegacyInlineCtx does not contain the stack pointer, so argument {} cannot be read", n);
+        T::from_bits(self.registers[n].x())
+    }
+
+    /// Sets the `n`th argument of type `T`, assuming the hook is at the start of a function
+    ///
+    /// # Panics
+    /// See [`LegacyInlineCtx::arg`]
+    pub fn set_arg<T: Argument>(&mut self, n: usize, value: T) {
+        assert!(T::CLASS == ArgumentClass::General, "LegacyInlineCtx does not contain the NEON/SIMD registers");
+        assert!(n < 8, "LegacyInlineCtx does not contain the stack pointer, so argument {} cannot be set", n);
+        self.registers[n].set_x(value.to_bits());
+    }
+
+    /// Gets the return value of type `T`, assuming the hook is right after a function call
+    ///
+    /// # Panics
+    /// This will panic if `T` is a floating point type
+    pub fn ret<T: Argument>(&self) -> T {
+        self.arg(0)
+    }
+
+    /// Sets the return value of type `T`, assuming the hook is at the end of a function
+    ///
+    /// # Panics
+    /// This will panic if `T` is a floating point type
+    pub fn set_ret<T: Argument>(&mut self, value: T) {
+        self.set_arg(0, value)
+    }
+}
+
 /// A more complete system context than [`InlineCtx`].
 /// 
 /// Due to the larger stack size requirement (3 times as much stack), this extended
 /// context is only provided by an ex inline hook, which is not the default.
 /// 
 /// As with the [`InlineCtx`], this is provided by the hook to the callback, and
-/// its contents are restored after the callback (with the exception of the stack pointer).
+/// its contents are restored after the callback (with the exception of the stack pointer
+/// and the program counter).
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct InlineCtx {
@@ -31,10 +90,147 @@ pub struct InlineCtx {
     pub sp: CpuRegister,
 
     /// The NEON/SIMD registers
-    pub fpu_registers: [FpuRegister; 32]
+    pub fpu_registers: [FpuRegister; 32],
+
+    /// The condition flags, which can be modified to change the outcome of
+    /// a conditional instruction after the hook
+    pub pstate: Pstate,
+
+    /// The floating point control register
+    pub fpcr: u64,
+
+    /// The floating point status register
+    pub fpsr: u64,
+
+    /// The address of the hooked instruction, this is not restored by the hooking environment,
+    /// use an [`InlineAction`] to resume somewhere else.
+    ///
+    /// Hooks on symbols or modules which were not loaded when the hook was installed look this up
+    /// the first time they run, it is only zero if the hooked address could not be found.
+    pub pc: u64,
+}
+
+/// What an inline hook should do once its callback has returned.
+///
+/// An inline hook callback which returns this (instead of nothing) is able to change
+/// the control flow of the hooked function. All of the registers are still restored from
+/// the [`InlineCtx`] before the action is taken, with the exception of `x16` and `x17`,
+/// which are used as scratch registers to carry out the action.
+#[repr(C, u64)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InlineAction {
+    /// Resume execution at the hooked instruction, as if the hook had returned nothing
+    Continue,
+
+    /// Resume execution at the provided absolute address instead of the hooked instruction
+    ResumeAt(u64),
+
+    /// Return from the enclosing function with the provided value in `x0`
+    ///
+    /// The enclosing function's frame is unwound using its call frame information, so the
+    /// registers it saved and the stack it allocated are given back to its caller, no matter
+    /// where in the function the hook is. Registers which the function had not saved yet are
+    /// returned as they are in the context.
+    ///
+    /// # Panics
+    /// The hook panics if the function has no call frame information, or if the address of
+    /// the hook is not known (see [`InlineCtx::pc`]).
+    Return(u64),
+}
+
+/// Turns an [`InlineAction::Return`] into a return to the caller's address, with the caller's
+/// registers and stack pointer in the context, which is called by the hook assembly once the
+/// user function has returned
+#[no_mangle]
+extern "C" fn skex_hooks_resolve_inline_action(ctx: &mut InlineCtx, action: InlineAction) -> InlineAction {
+    let value = match action {
+        InlineAction::Return(value) => value,
+        action => return action,
+    };
+
+    if ctx.pc == 0 {
+        panic!("Cannot return from a hooked function when the address of the hook is not known");
+    }
+
+    let caller = StackView::from_inline_ctx(ctx)
+        .map_err(UnwindError::from)
+        .and_then(|stack| UnwindRegisters::from_inline_ctx(ctx).unwind_cfi(&stack));
+
+    let caller = match caller {
+        Ok(caller) => caller,
+        Err(e) => panic!("Cannot return from the function hooked at {:#x}: {}", ctx.pc, e),
+    };
+
+    for (dst, src) in ctx.registers.iter_mut().zip(caller.registers.iter()) {
+        dst.set_x(*src);
+    }
+    ctx.registers[0].set_x(value);
+    ctx.sp.set_x(caller.sp);
+
+    InlineAction::Return(caller.pc)
 }
 
 impl InlineCtx {
+    /// Gets the frame pointer (x29)
+    pub fn fp(&self) -> CpuRegister {
+        self[X::FP]
+    }
+
+    /// Gets the link register (x30)
+    pub fn lr(&self) -> CpuRegister {
+        self[X::LR]
+    }
+
+    /// Gets the stack pointer
+    pub fn sp(&self) -> CpuRegister {
+        self.sp
+    }
+
+    /// Gets the `n`th argument of type `T`, assuming the hook is at the start of a function
+    ///
+    /// Arguments are counted separately for each [`ArgumentClass`], so for
+    /// `fn(a: *const Foo, b: f32, c: u32)`, `c` is argument 1, not argument 2.
+    ///
+    /// The first eight arguments of each class are read from the registers, and the rest
+    /// are read from the stack. Reading the stack assumes that only arguments of class `T` were
+    /// spilled on to it, use [`InlineCtx::get_from_stack`] if the classes are mixed.
+    pub fn arg<T: Argument>(&self, n: usize) -> T {
+        match T::CLASS {
+            ArgumentClass::General if n < 8 => T::from_bits(self.registers[n].x()),
+            ArgumentClass::Vector if n < 8 => T::from_bits(self.fpu_registers[n].q() as u64),
+            _ => *self.get_from_stack::<T>(((n - 8) * 8) as isize)
+        }
+    }
+
+    /// Sets the `n`th argument of type `T`, assuming the hook is at the start of a function
+    ///
+    /// # Panics
+    /// This will panic if the argument is not in one of the eight argument registers of its class,
+    /// use [`InlineCtx::get_from_stack_mut`] to modify arguments on the stack
+    pub fn set_arg<T: Argument>(&mut self, n: usize, value: T) {
+        assert!(n < 8, "Argument {} is passed on the stack and cannot be set through the context", n);
+        match T::CLASS {
+            ArgumentClass::General => self.registers[n].set_x(value.to_bits()),
+            ArgumentClass::Vector => self.fpu_registers[n].set_q(value.to_bits() as u128)
+        }
+    }
+
+    /// Gets the return value of type `T`, assuming the hook is right after a function call
+    pub fn ret<T: Argument>(&self) -> T {
+        self.arg(0)
+    }
+
+    /// Sets the return value of type `T`, assuming the hook is at the end of a function
+    pub fn set_ret<T: Argument>(&mut self, value: T) {
+        self.set_arg(0, value)
+    }
+
+    /// Gets a bounds-checked view of the stack that the hook was called on, which unlike
+    /// [`InlineCtx::get_from_stack`] can't read past the end of the stack
+    pub fn stack(&self) -> Result<StackView, StackError> {
+        StackView::from_inline_ctx(self)
+    }
+
     /// Gets a reference to a value on the stack
     /// # Arguments
     /// * `offset` - The offset from the stack pointer to get
@@ -53,4 +249,136 @@ impl InlineCtx {
     pub unsafe fn get_from_stack_mut<T: Sized>(&mut self, offset: isize) -> &mut T {
         &mut *((self.sp.x() as *mut u8).offset(offset) as *mut T)
     }
-}
\ No newline at end of file
+}
+
+/// The state of the argument and return registers around a call to a hooked function.
+///
+/// This context is provided by a post hook (`#[post_hook]` or `#[callback(after)]`), which calls the
+/// original function first and then provides the arguments it was called with alongside its return value.
+/// After the callback, the return registers are restored from this context, meaning the return value
+/// can be modified.
+///
+/// Since the post hook calls the original function from its own stack frame, it copies the first 0x80
+/// bytes of arguments passed on the stack down for it. It can't be used on functions which take more
+/// arguments than that on the stack.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct PostHookCtx {
+    /// The general purpose argument registers (x0-x7) as they were when the function was called
+    pub arguments: [CpuRegister; 8],
+
+    /// The indirect result location register (x8), which points to the memory that
+    /// large return values are written to
+    pub indirect_result: CpuRegister,
+
+    /// The stack pointer as it was when the function was called, this is read-only
+    pub sp: CpuRegister,
+
+    /// The NEON/SIMD argument registers (v0-v7) as they were when the function was called
+    pub fpu_arguments: [FpuRegister; 8],
+
+    /// The general purpose return registers (x0-x1) after the original function returned
+    pub return_registers: [CpuRegister; 2],
+
+    /// The NEON/SIMD return registers (v0-v3) after the original function returned
+    pub fpu_return_registers: [FpuRegister; 4],
+}
+
+macro_rules! impl_gpr_index {
+    ($ctx:ty, $($reg:ty),*) => {
+        $(
+            impl Index<$reg> for $ctx {
+                type Output = CpuRegister;
+
+                fn index(&self, reg: $reg) -> &Self::Output {
+                    &self.registers[reg.index()]
+                }
+            }
+
+            impl IndexMut<$reg> for $ctx {
+                fn index_mut(&mut self, reg: $reg) -> &mut Self::Output {
+                    &mut self.registers[reg.index()]
+                }
+            }
+        )*
+    }
+}
+
+impl_gpr_index!(LegacyInlineCtx, X, W);
+impl_gpr_index!(InlineCtx, X, W);
+
+impl Index<V> for InlineCtx {
+    type Output = FpuRegister;
+
+    fn index(&self, reg: V) -> &Self::Output {
+        &self.fpu_registers[reg.index()]
+    }
+}
+
+impl IndexMut<V> for InlineCtx {
+    fn index_mut(&mut self, reg: V) -> &mut Self::Output {
+        &mut self.fpu_registers[reg.index()]
+    }
+}
+
+/// Writes the general purpose registers four to a line, using the aliases for the frame pointer
+/// and link register since those are what people look for first in a crash log
+fn write_gprs(f: &mut fmt::Formatter<'_>, registers: &[CpuRegister; 31], sp: Option<CpuRegister>) -> fmt::Result {
+    let named = X::ALL
+        .iter()
+        .map(|reg| {
+            let name: &dyn fmt::Display = match reg {
+                &X::FP => &"fp",
+                &X::LR => &"lr",
+                reg => reg
+            };
+            (name, registers[reg.index()])
+        })
+        .chain(sp.map(|sp| (&"sp" as &dyn fmt::Display, sp)));
+
+    for (idx, (name, value)) in named.enumerate() {
+        if idx != 0 {
+            if idx % 4 == 0 {
+                writeln!(f)?;
+            } else {
+                write!(f, "  ")?;
+            }
+        }
+        write!(f, "{:>3}: {:016x}", name, value.x())?;
+    }
+
+    writeln!(f)
+}
+
+impl fmt::Display for LegacyInlineCtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_gprs(f, &self.registers, None)
+    }
+}
+
+impl fmt::Display for InlineCtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_gprs(f, &self.registers, Some(self.sp))?;
+        writeln!(
+            f,
+            " pc: {:016x}  nzcv: {}  fpcr: {:08x}  fpsr: {:08x}",
+            self.pc,
+            self.pstate,
+            self.fpcr,
+            self.fpsr
+        )?;
+
+        for (idx, reg) in V::ALL.iter().enumerate() {
+            if idx != 0 {
+                if idx % 2 == 0 {
+                    writeln!(f)?;
+                } else {
+                    write!(f, "  ")?;
+                }
+            }
+            write!(f, "{:>3}: {:032x}", reg, self[*reg].q())?;
+        }
+
+        writeln!(f)
+    }
+}
@@ -0,0 +1,949 @@
+//! Symbol demangling for backtraces.
+//!
+//! Rust symbols are demangled here, both the legacy scheme (`_ZN...17h<hash>E`) and the
+//! v0 scheme (`_R...`). Anything else is handed to the C++ runtime's `__cxa_demangle`.
+use std::fmt::Write;
+
+/// The deepest that a v0 symbol is allowed to nest, to protect against malicious backreferences
+const MAX_DEPTH: usize = 500;
+
+/// Demangles a C++ or Rust symbol, returning it unchanged if it isn't mangled
+///
+/// # Arguments
+/// * `symbol` - The mangled symbol
+/// * `strip_hash` - Whether to leave out the hashes which Rust adds to make symbols unique
+pub fn demangle(symbol: &str, strip_hash: bool) -> String {
+    demangle_rust(symbol, strip_hash)
+        .or_else(|| demangle_cxx(symbol))
+        .unwrap_or_else(|| symbol.to_string())
+}
+
+/// Demangles a Rust symbol in either the legacy or the v0 mangling scheme
+///
+/// # Arguments
+/// * `symbol` - The mangled symbol
+/// * `strip_hash` - Whether to leave out the hashes which Rust adds to make symbols unique
+///
+/// # Returns
+/// * `Some(String)` - The demangled symbol
+/// * `None` - The symbol is not a valid Rust symbol
+pub fn demangle_rust(symbol: &str, strip_hash: bool) -> Option<String> {
+    // LLVM appends `.llvm.<hash>` to symbols which it has made local during LTO, which is only
+    // left out along with the other hashes
+    let symbol = match symbol.find(".llvm.") {
+        Some(idx) if strip_hash && symbol[idx + 6..].bytes().all(|c| matches!(c, b'A'..=b'F' | b'0'..=b'9' | b'@')) => {
+            &symbol[..idx]
+        },
+        _ => symbol,
+    };
+
+    if let Some(mangled) = symbol.strip_prefix("_R") {
+        V0Demangler::new(mangled, strip_hash).demangle()
+    } else if let Some(mangled) = symbol.strip_prefix("_ZN") {
+        demangle_legacy(mangled, strip_hash)
+    } else {
+        None
+    }
+}
+
+/// Demangles a C++ symbol using the C++ runtime
+///
+/// # Returns
+/// * `Some(String)` - The demangled symbol
+/// * `None` - The symbol is not a valid C++ symbol
+pub fn demangle_cxx(symbol: &str) -> Option<String> {
+    extern "C" {
+        fn __cxa_demangle(mangled: *const u8, buffer: *mut u8, length: &mut usize, status: &mut i32) -> *mut u8;
+        fn free(ptr: *mut u8);
+        fn strlen(str: *const u8) -> i32;
+    }
+
+    unsafe {
+        let mut out_length = 0usize;
+        let mut out_status = 0i32;
+        let out_buffer = __cxa_demangle([symbol, "\0"].concat().as_ptr(), std::ptr::null_mut(), &mut out_length, &mut out_status);
+        let result = if out_status == 0 && !out_buffer.is_null() {
+            let len = strlen(out_buffer);
+            Some(String::from_utf8_lossy(std::slice::from_raw_parts(out_buffer, len as usize)).into_owned())
+        } else {
+            None
+        };
+        if !out_buffer.is_null() {
+            free(out_buffer);
+        }
+        result
+    }
+}
+
+/// Checks whether the text after a symbol is a suffix appended by LLVM, such as `.llvm.1234ABCD`
+/// for symbols made local during LTO or `.0`/`.cold` for copies of a function. It is kept in the
+/// demangled symbol, since it tells apart symbols which would otherwise demangle the same.
+fn is_symbol_suffix(suffix: &str) -> bool {
+    suffix.starts_with('.') && suffix.bytes().all(|c| c.is_ascii_alphanumeric() || c.is_ascii_punctuation())
+}
+
+/// Checks whether a legacy path component is the hash that rustc appends to every symbol
+fn is_legacy_hash(component: &str) -> bool {
+    component.len() == 17
+        && component.starts_with('h')
+        && component[1..].bytes().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Demangles the part of a legacy symbol after the `_ZN`
+fn demangle_legacy(mut mangled: &str, strip_hash: bool) -> Option<String> {
+    let mut components = Vec::new();
+    while !mangled.starts_with('E') {
+        let digits = mangled.bytes().take_while(|c| c.is_ascii_digit()).count();
+        let len: usize = mangled[..digits].parse().ok()?;
+        let component = mangled.get(digits..digits + len)?;
+        components.push(component);
+        mangled = &mangled[digits + len..];
+    }
+
+    // Anything after the `E` other than a suffix means this is a C++ function with parameters. Every
+    // Rust symbol ends with a hash, which keeps us from claiming C++ variables that look the same.
+    let suffix = &mangled[1..];
+    if !(suffix.is_empty() || is_symbol_suffix(suffix))
+        || components.len() < 2
+        || !is_legacy_hash(components[components.len() - 1])
+    {
+        return None;
+    }
+
+    if strip_hash {
+        components.pop();
+    }
+
+    let mut out = String::new();
+    for (idx, component) in components.into_iter().enumerate() {
+        if idx != 0 {
+            out.push_str("::");
+        }
+        unescape_legacy_component(component, &mut out);
+    }
+    out.push_str(suffix);
+
+    Some(out)
+}
+
+/// Writes a legacy path component, replacing the `$..$` escapes with the characters they represent
+fn unescape_legacy_component(mut component: &str, out: &mut String) {
+    // Components which would start with a `$` are prefixed with an underscore
+    if component.starts_with("_$") {
+        component = &component[1..];
+    }
+
+    while let Some(c) = component.chars().next() {
+        if c == '$' {
+            let escape = component[1..].find('$').map(|end| &component[1..end + 1]);
+            let unescaped = escape.and_then(|escape| match escape {
+                "SP" => Some('@'),
+                "BP" => Some('*'),
+                "RF" => Some('&'),
+                "LT" => Some('<'),
+                "GT" => Some('>'),
+                "LP" => Some('('),
+                "RP" => Some(')'),
+                "C" => Some(','),
+                _ => escape
+                    .strip_prefix('u')
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .and_then(std::char::from_u32),
+            });
+
+            match (escape, unescaped) {
+                (Some(escape), Some(unescaped)) => {
+                    out.push(unescaped);
+                    component = &component[escape.len() + 2..];
+                },
+                _ => {
+                    out.push('$');
+                    component = &component[1..];
+                }
+            }
+        } else if component.starts_with("..") {
+            out.push_str("::");
+            component = &component[2..];
+        } else {
+            out.push(c);
+            component = &component[c.len_utf8()..];
+        }
+    }
+}
+
+/// Decodes a punycode identifier, as used by v0 symbols for non-ASCII identifiers
+fn decode_punycode(ascii: &str, encoded: &str) -> Option<String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+    const SKEW: u32 = 38;
+    const DAMP: u32 = 700;
+
+    let mut output: Vec<char> = ascii.chars().collect();
+    let mut n: u32 = 0x80;
+    let mut i: u32 = 0;
+    let mut bias: u32 = 72;
+
+    let mut first = true;
+    let mut digits = encoded.bytes().peekable();
+    while digits.peek().is_some() {
+        let previous_i = i;
+        let mut weight = 1u32;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next()? {
+                c @ b'a'..=b'z' => (c - b'a') as u32,
+                c @ b'0'..=b'9' => (c - b'0') as u32 + 26,
+                _ => return None,
+            };
+            i = i.checked_add(digit.checked_mul(weight)?)?;
+
+            let t = if k <= bias {
+                T_MIN
+            } else if k >= bias + T_MAX {
+                T_MAX
+            } else {
+                k - bias
+            };
+
+            if digit < t {
+                break;
+            }
+
+            weight = weight.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+
+        let length = output.len() as u32 + 1;
+
+        // Adapt the bias for the next character
+        let mut delta = if first { (i - previous_i) / DAMP } else { (i - previous_i) / 2 };
+        first = false;
+        delta += delta / length;
+        let mut k = 0;
+        while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+            delta /= BASE - T_MIN;
+            k += BASE;
+        }
+        bias = k + ((BASE - T_MIN + 1) * delta) / (delta + SKEW);
+
+        n = n.checked_add(i / length)?;
+        i %= length;
+
+        output.insert(i as usize, std::char::from_u32(n)?);
+        i += 1;
+    }
+
+    Some(output.into_iter().collect())
+}
+
+/// A demangler for the v0 mangling scheme, see RFC 2603
+struct V0Demangler<'a> {
+    mangled: &'a [u8],
+    position: usize,
+    out: String,
+    strip_hash: bool,
+    depth: usize,
+    bound_lifetimes: u64,
+}
+
+impl<'a> V0Demangler<'a> {
+    fn new(mangled: &'a str, strip_hash: bool) -> Self {
+        Self {
+            mangled: mangled.as_bytes(),
+            position: 0,
+            out: String::new(),
+            strip_hash,
+            depth: 0,
+            bound_lifetimes: 0,
+        }
+    }
+
+    fn demangle(mut self) -> Option<String> {
+        // An encoding version means a newer scheme which we don't know about
+        if self.peek()?.is_ascii_digit() {
+            return None;
+        }
+
+        self.path(true)?;
+
+        // The path may be followed by the crate which instantiated it, which is never printed
+        if self.peek().is_some_and(|c| c.is_ascii_uppercase()) {
+            self.skip(|this| this.path(false))?;
+        }
+
+        let suffix = std::str::from_utf8(&self.mangled[self.position..]).ok()?;
+        if !(suffix.is_empty() || is_symbol_suffix(suffix)) {
+            return None;
+        }
+        self.print(suffix);
+
+        Some(self.out)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.mangled.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.position += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn print(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    /// Parses something without printing it
+    fn skip(&mut self, f: impl FnOnce(&mut Self) -> Option<()>) -> Option<()> {
+        let len = self.out.len();
+        let result = f(self);
+        self.out.truncate(len);
+        result
+    }
+
+    /// Tracks the nesting depth, so that malformed symbols can't recurse forever
+    fn nested(&mut self, f: impl FnOnce(&mut Self) -> Option<()>) -> Option<()> {
+        if self.depth >= MAX_DEPTH {
+            return None;
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Parses a base-62 number, where `_` is 0 and everything else is offset by one
+    fn integer_62(&mut self) -> Option<u64> {
+        if self.eat(b'_') {
+            return Some(0);
+        }
+
+        let mut value = 0u64;
+        loop {
+            let digit = match self.next()? {
+                b'_' => break,
+                c @ b'0'..=b'9' => c - b'0',
+                c @ b'a'..=b'z' => c - b'a' + 10,
+                c @ b'A'..=b'Z' => c - b'A' + 36,
+                _ => return None,
+            };
+            value = value.checked_mul(62)?.checked_add(digit as u64)?;
+        }
+
+        value.checked_add(1)
+    }
+
+    /// Parses a base-62 number prefixed by a tag, which is 0 when the tag is missing
+    fn opt_integer_62(&mut self, tag: u8) -> Option<u64> {
+        if self.eat(tag) {
+            self.integer_62()?.checked_add(1)
+        } else {
+            Some(0)
+        }
+    }
+
+    fn decimal(&mut self) -> Option<usize> {
+        // A zero is always on its own, so that an empty identifier can be followed by a digit
+        if self.eat(b'0') {
+            return Some(0);
+        }
+
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        std::str::from_utf8(&self.mangled[start..self.position]).ok()?.parse().ok()
+    }
+
+    fn undisambiguated_identifier(&mut self) -> Option<String> {
+        let is_punycode = self.eat(b'u');
+        let len = self.decimal()?;
+        self.eat(b'_');
+
+        let bytes = self.mangled.get(self.position..self.position + len)?;
+        self.position += len;
+        let identifier = std::str::from_utf8(bytes).ok()?;
+
+        if is_punycode {
+            match identifier.rfind('_') {
+                Some(idx) => decode_punycode(&identifier[..idx], &identifier[idx + 1..]),
+                None => decode_punycode("", identifier),
+            }
+        } else {
+            Some(identifier.to_string())
+        }
+    }
+
+    fn identifier(&mut self) -> Option<(u64, String)> {
+        let disambiguator = self.opt_integer_62(b's')?;
+        let name = self.undisambiguated_identifier()?;
+        Some((disambiguator, name))
+    }
+
+    /// Follows a backreference to an earlier part of the symbol
+    fn backref(&mut self, f: impl FnOnce(&mut Self) -> Option<()>) -> Option<()> {
+        let start = self.position - 1;
+        let target = self.integer_62()? as usize;
+        if target >= start {
+            return None;
+        }
+
+        let position = std::mem::replace(&mut self.position, target);
+        let result = self.nested(f);
+        self.position = position;
+        result
+    }
+
+    fn lifetime(&mut self, lifetime: u64) -> Option<()> {
+        if lifetime == 0 {
+            self.print("'_");
+            return Some(());
+        }
+
+        let depth = self.bound_lifetimes.checked_sub(lifetime)?;
+        if depth < 26 {
+            let name = (b'a' + depth as u8) as char;
+            write!(self.out, "'{}", name).ok()
+        } else {
+            write!(self.out, "'_{}", depth).ok()
+        }
+    }
+
+    /// Parses an optional `for<'a, ...>` binder and the item it applies to
+    fn binder(&mut self, f: impl FnOnce(&mut Self) -> Option<()>) -> Option<()> {
+        let count = self.opt_integer_62(b'G')?;
+
+        if count > 0 {
+            self.print("for<");
+            for idx in 0..count {
+                if idx != 0 {
+                    self.print(", ");
+                }
+                self.bound_lifetimes += 1;
+                self.lifetime(1)?;
+            }
+            self.print("> ");
+        }
+
+        let result = f(self);
+        self.bound_lifetimes -= count;
+        result
+    }
+
+    fn path(&mut self, in_value: bool) -> Option<()> {
+        self.nested(|this| this.path_inner(in_value))
+    }
+
+    fn path_inner(&mut self, in_value: bool) -> Option<()> {
+        match self.next()? {
+            b'C' => {
+                let (disambiguator, name) = self.identifier()?;
+                self.print(&name);
+                if !self.strip_hash && disambiguator != 0 {
+                    write!(self.out, "[{:x}]", disambiguator).ok()?;
+                }
+            },
+            b'N' => {
+                let namespace = self.next()?;
+                self.path(in_value)?;
+                let (disambiguator, name) = self.identifier()?;
+
+                if namespace.is_ascii_uppercase() {
+                    // Closures and shims don't have names, they are printed as `{closure#0}`
+                    self.print("::{");
+                    match namespace {
+                        b'C' => self.print("closure"),
+                        b'S' => self.print("shim"),
+                        other => self.out.push(other as char),
+                    }
+                    if !name.is_empty() {
+                        self.print(":");
+                        self.print(&name);
+                    }
+                    write!(self.out, "#{}}}", disambiguator).ok()?;
+                } else if !name.is_empty() {
+                    self.print("::");
+                    self.print(&name);
+                }
+            },
+            b'M' => {
+                self.skip(|this| {
+                    this.opt_integer_62(b's')?;
+                    this.path(false)
+                })?;
+                self.print("<");
+                self.ty()?;
+                self.print(">");
+            },
+            b'X' => {
+                self.skip(|this| {
+                    this.opt_integer_62(b's')?;
+                    this.path(false)
+                })?;
+                self.print("<");
+                self.ty()?;
+                self.print(" as ");
+                self.path(false)?;
+                self.print(">");
+            },
+            b'Y' => {
+                self.print("<");
+                self.ty()?;
+                self.print(" as ");
+                self.path(false)?;
+                self.print(">");
+            },
+            b'I' => {
+                self.path(in_value)?;
+                if in_value {
+                    self.print("::");
+                }
+                self.print("<");
+                self.generic_args()?;
+                self.print(">");
+            },
+            b'B' => self.backref(|this| this.path_inner(in_value))?,
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    /// Parses generic arguments up to and including the terminating `E`
+    fn generic_args(&mut self) -> Option<()> {
+        let mut first = true;
+        while !self.eat(b'E') {
+            if !first {
+                self.print(", ");
+            }
+            first = false;
+
+            if self.eat(b'L') {
+                let lifetime = self.integer_62()?;
+                self.lifetime(lifetime)?;
+            } else if self.eat(b'K') {
+                self.constant()?;
+            } else {
+                self.ty()?;
+            }
+        }
+
+        Some(())
+    }
+
+    fn basic_type(tag: u8) -> Option<&'static str> {
+        Some(match tag {
+            b'a' => "i8",
+            b'b' => "bool",
+            b'c' => "char",
+            b'd' => "f64",
+            b'e' => "str",
+            b'f' => "f32",
+            b'h' => "u8",
+            b'i' => "isize",
+            b'j' => "usize",
+            b'l' => "i32",
+            b'm' => "u32",
+            b'n' => "i128",
+            b'o' => "u128",
+            b's' => "i16",
+            b't' => "u16",
+            b'u' => "()",
+            b'v' => "...",
+            b'x' => "i64",
+            b'y' => "u64",
+            b'z' => "!",
+            b'p' => "_",
+            _ => return None,
+        })
+    }
+
+    fn ty(&mut self) -> Option<()> {
+        self.nested(|this| this.ty_inner())
+    }
+
+    fn ty_inner(&mut self) -> Option<()> {
+        let tag = self.next()?;
+        if let Some(name) = Self::basic_type(tag) {
+            self.print(name);
+            return Some(());
+        }
+
+        match tag {
+            b'R' | b'Q' => {
+                self.print("&");
+                if self.eat(b'L') {
+                    let lifetime = self.integer_62()?;
+                    if lifetime != 0 {
+                        self.lifetime(lifetime)?;
+                        self.print(" ");
+                    }
+                }
+                if tag == b'Q' {
+                    self.print("mut ");
+                }
+                self.ty()?;
+            },
+            b'P' => {
+                self.print("*const ");
+                self.ty()?;
+            },
+            b'O' => {
+                self.print("*mut ");
+                self.ty()?;
+            },
+            b'A' => {
+                self.print("[");
+                self.ty()?;
+                self.print("; ");
+                self.constant()?;
+                self.print("]");
+            },
+            b'S' => {
+                self.print("[");
+                self.ty()?;
+                self.print("]");
+            },
+            b'T' => {
+                self.print("(");
+                let mut count = 0;
+                while !self.eat(b'E') {
+                    if count != 0 {
+                        self.print(", ");
+                    }
+                    self.ty()?;
+                    count += 1;
+                }
+                if count == 1 {
+                    self.print(",");
+                }
+                self.print(")");
+            },
+            b'F' => self.binder(|this| {
+                if this.eat(b'U') {
+                    this.print("unsafe ");
+                }
+
+                if this.eat(b'K') {
+                    let abi = if this.eat(b'C') {
+                        "C".to_string()
+                    } else {
+                        this.undisambiguated_identifier()?.replace('_', "-")
+                    };
+                    write!(this.out, "extern \"{}\" ", abi).ok()?;
+                }
+
+                this.print("fn(");
+                let mut first = true;
+                while !this.eat(b'E') {
+                    if !first {
+                        this.print(", ");
+                    }
+                    first = false;
+                    this.ty()?;
+                }
+                this.print(")");
+
+                if !this.eat(b'u') {
+                    this.print(" -> ");
+                    this.ty()?;
+                }
+
+                Some(())
+            })?,
+            b'D' => {
+                self.print("dyn ");
+                self.binder(|this| {
+                    let mut first = true;
+                    while !this.eat(b'E') {
+                        if !first {
+                            this.print(" + ");
+                        }
+                        first = false;
+                        this.dyn_trait()?;
+                    }
+                    Some(())
+                })?;
+
+                if !self.eat(b'L') {
+                    return None;
+                }
+                let lifetime = self.integer_62()?;
+                if lifetime != 0 {
+                    self.print(" + ");
+                    self.lifetime(lifetime)?;
+                }
+            },
+            b'B' => self.backref(|this| this.ty_inner())?,
+            _ => {
+                self.position -= 1;
+                self.path(false)?;
+            }
+        }
+
+        Some(())
+    }
+
+    /// Parses a trait path, leaving the generic arguments open if there are any
+    ///
+    /// # Returns
+    /// Whether the generic arguments were left open
+    fn path_maybe_open_generics(&mut self) -> Option<bool> {
+        if self.eat(b'B') {
+            let mut open = false;
+            self.backref(|this| {
+                open = this.path_maybe_open_generics()?;
+                Some(())
+            })?;
+            Some(open)
+        } else if self.eat(b'I') {
+            self.path(false)?;
+            self.print("<");
+            let mut first = true;
+            while !self.eat(b'E') {
+                if !first {
+                    self.print(", ");
+                }
+                first = false;
+                self.generic_arg()?;
+            }
+            Some(true)
+        } else {
+            self.path(false)?;
+            Some(false)
+        }
+    }
+
+    fn generic_arg(&mut self) -> Option<()> {
+        if self.eat(b'L') {
+            let lifetime = self.integer_62()?;
+            self.lifetime(lifetime)
+        } else if self.eat(b'K') {
+            self.constant()
+        } else {
+            self.ty()
+        }
+    }
+
+    fn dyn_trait(&mut self) -> Option<()> {
+        let mut open = self.path_maybe_open_generics()?;
+
+        // Associated type bindings go in with the generic arguments, like `Iterator<Item = u8>`
+        while self.eat(b'p') {
+            if open {
+                self.print(", ");
+            } else {
+                self.print("<");
+                open = true;
+            }
+            let name = self.undisambiguated_identifier()?;
+            self.print(&name);
+            self.print(" = ");
+            self.ty()?;
+        }
+
+        if open {
+            self.print(">");
+        }
+
+        Some(())
+    }
+
+    fn constant(&mut self) -> Option<()> {
+        if self.eat(b'B') {
+            return self.backref(|this| this.constant());
+        }
+
+        if self.eat(b'p') {
+            self.print("_");
+            return Some(());
+        }
+
+        let ty = self.next()?;
+
+        let negative = self.eat(b'n');
+        let start = self.position;
+        while self.next()? != b'_' {}
+        let hex = std::str::from_utf8(&self.mangled[start..self.position - 1]).ok()?;
+
+        match ty {
+            // Signed and unsigned integers
+            b'a' | b's' | b'l' | b'x' | b'n' | b'i' | b'h' | b't' | b'm' | b'y' | b'o' | b'j' => {
+                if negative {
+                    self.print("-");
+                }
+                match u64::from_str_radix(if hex.is_empty() { "0" } else { hex }, 16) {
+                    Ok(value) => write!(self.out, "{}", value).ok()?,
+                    Err(_) => write!(self.out, "0x{}", hex).ok()?,
+                }
+                if !self.strip_hash {
+                    self.print(Self::basic_type(ty)?);
+                }
+            },
+            b'b' => match hex {
+                "0" => self.print("false"),
+                "1" => self.print("true"),
+                _ => return None,
+            },
+            b'c' => {
+                let c = std::char::from_u32(u32::from_str_radix(hex, 16).ok()?)?;
+                write!(self.out, "{:?}", c).ok()?;
+            },
+            _ => return None,
+        }
+
+        Some(())
+    }
+}
+
+#[cfg(all(test, target_arch = "aarch64"))]
+mod tests {
+    use super::*;
+
+    fn check(symbol: &str, expected: &str, expected_stripped: &str) {
+        assert_eq!(demangle_rust(symbol, false).as_deref(), Some(expected), "{}", symbol);
+        assert_eq!(demangle_rust(symbol, true).as_deref(), Some(expected_stripped), "{}", symbol);
+    }
+
+    #[test]
+    fn legacy_corpus() {
+        check("_ZN3foo17h05af221e174051e9E", "foo::h05af221e174051e9", "foo");
+        check(
+            "_ZN151_$LT$alloc..boxed..Box$LT$alloc..boxed..FnBox$LT$A$C$$u20$Output$u3d$R$GT$$u20$$u2b$$u20$$u27$a$GT$$u20$as$u20$core..ops..function..FnOnce$LT$A$GT$$GT$9call_once17h69e8f44b3723e1caE",
+            "<alloc::boxed::Box<alloc::boxed::FnBox<A, Output=R> + 'a> as core::ops::function::FnOnce<A>>::call_once::h69e8f44b3723e1ca",
+            "<alloc::boxed::Box<alloc::boxed::FnBox<A, Output=R> + 'a> as core::ops::function::FnOnce<A>>::call_once",
+        );
+        check(
+            "_ZN88_$LT$core..result..Result$LT$$u21$$C$$u20$E$GT$$u20$as$u20$std..process..Termination$GT$6report17hfc41d0da4a40b3e8E",
+            "<core::result::Result<!, E> as std::process::Termination>::report::hfc41d0da4a40b3e8",
+            "<core::result::Result<!, E> as std::process::Termination>::report",
+        );
+        check(
+            "_ZN12test$BP$test17h05af221e174051e9E",
+            "test*test::h05af221e174051e9",
+            "test*test",
+        );
+        check(
+            "_ZN13_$LT$test$GT$17h05af221e174051e9E",
+            "<test>::h05af221e174051e9",
+            "<test>",
+        );
+        // Hashes which aren't 16 hex digits after an `h` are kept
+        check(
+            "_ZN3foo16ffaf221e174051e917h05af221e174051e9E",
+            "foo::ffaf221e174051e9::h05af221e174051e9",
+            "foo::ffaf221e174051e9",
+        );
+    }
+
+    #[test]
+    fn legacy_rejects_cxx() {
+        // A C++ function with parameters, and a C++ variable without a hash
+        assert_eq!(demangle_rust("_ZN3foo3barEv", true), None);
+        assert_eq!(demangle_rust("_ZN3foo3barE", true), None);
+        assert_eq!(demangle_rust("_ZN3foo17h05af221e174051e9", true), None);
+    }
+
+    #[test]
+    fn v0_corpus() {
+        check("_RNvC6_123foo3bar", "123foo::bar", "123foo::bar");
+        check(
+            "_RNvNtCs1234_7mycrate3foo3bar",
+            "mycrate[3c1c0]::foo::bar",
+            "mycrate::foo::bar",
+        );
+        check(
+            "_RNqCs4fqI2P2rA04_11utf8_identsu30____7hkackfecea1cbdathfdh9hlq6y",
+            "utf8_idents[317d481089b8c8fe]::საჭმელად_გემრიელი_სადილი",
+            "utf8_idents::საჭმელად_გემრიელი_სადილი",
+        );
+        check(
+            "_RNCNCNgCs6DXkGYLi8lr_2cc5spawn00B5_",
+            "cc[4d6468d6c9fd4bb3]::spawn::{closure#0}::{closure#0}",
+            "cc::spawn::{closure#0}::{closure#0}",
+        );
+        assert_eq!(
+            demangle_rust(
+                "_RNCINkXs25_NgCsbmNqQUJIY6D_4core5sliceINyB9_4IterhENuNgNoBb_4iter8iterator8Iterator9rpositionNCNgNpB9_6memchr7memrchrs_0E0Bb_",
+                true
+            )
+            .as_deref(),
+            Some("<core::slice::Iter<u8> as core::iter::iterator::Iterator>::rposition::<core::slice::memchr::memrchr::{closure#1}>::{closure#0}")
+        );
+        check(
+            "_RINbNbCskIICzLVDPPb_5alloc5alloc8box_freeDINbNiB4_5boxed5FnBoxuEp6OutputuEL_ECs1iopQbuBiw2_3std",
+            "alloc[f15a878b47eb696b]::alloc::box_free::<dyn alloc[f15a878b47eb696b]::boxed::FnBox<(), Output = ()>>",
+            "alloc::alloc::box_free::<dyn alloc::boxed::FnBox<(), Output = ()>>",
+        );
+        check("_RINvC4test3fooKj1f_E", "test::foo::<31usize>", "test::foo::<31>");
+        check("_RINvC4test3fooRShE", "test::foo::<&[u8]>", "test::foo::<&[u8]>");
+    }
+
+    #[test]
+    fn crate_root_without_disambiguator() {
+        check("_RC4f128", "f128", "f128");
+        check("_RCs_7mycrate", "mycrate[1]", "mycrate");
+    }
+
+    #[test]
+    fn suffixes_are_kept() {
+        check(
+            "_ZN3foo3bar17h05af221e174051e9E.0",
+            "foo::bar::h05af221e174051e9.0",
+            "foo::bar.0",
+        );
+        check(
+            "_ZN4core5slice77_$LT$impl$u20$core..ops..index..IndexMut$LT$I$GT$$u20$for$u20$$u5b$T$u5d$$GT$9index_mut17haf9727c2edfbc47bE.exit.i.i",
+            "core::slice::<impl core::ops::index::IndexMut<I> for [T]>::index_mut::haf9727c2edfbc47b.exit.i.i",
+            "core::slice::<impl core::ops::index::IndexMut<I> for [T]>::index_mut.exit.i.i",
+        );
+        check(
+            "_RNvNtNtNtNtCs92dm3009vxr_4rand4rngs7adapter9reseeding4fork23FORK_HANDLER_REGISTERED.0.0",
+            "rand[693ea8e72247470f]::rngs::adapter::reseeding::fork::FORK_HANDLER_REGISTERED.0.0",
+            "rand::rngs::adapter::reseeding::fork::FORK_HANDLER_REGISTERED.0.0",
+        );
+        check("_RNvC6_123foo3bar.cold.1", "123foo::bar.cold.1", "123foo::bar.cold.1");
+    }
+
+    #[test]
+    fn llvm_suffixes_are_hashes() {
+        check(
+            "_ZN9backtrace3foo17hbb467fcdaea5d79bE.llvm.A5310EB9",
+            "backtrace::foo::hbb467fcdaea5d79b.llvm.A5310EB9",
+            "backtrace::foo",
+        );
+        check("_RC3foo.llvm.9D1C9369@@16", "foo.llvm.9D1C9369@@16", "foo");
+        check("_RNvC9backtrace3foo.llvm.A5310EB9", "backtrace::foo.llvm.A5310EB9", "backtrace::foo");
+
+        // Only a hash is left out, anything else after `.llvm.` is kept
+        check("_RNvC9backtrace3foo.llvm.exit", "backtrace::foo.llvm.exit", "backtrace::foo.llvm.exit");
+    }
+
+    #[test]
+    fn trailing_garbage_is_rejected() {
+        assert_eq!(demangle_rust("_RNvC6_123foo3barxyz", true), None);
+        assert_eq!(demangle_rust("_RNvC6_123foo3bar_", true), None);
+        assert_eq!(demangle_rust("_RNvC6_123foo3bar.llvm moocow", true), None);
+        assert_eq!(demangle_rust("_ZN3foo3bar17h05af221e174051e9Exyz", true), None);
+    }
+
+    #[test]
+    fn malformed_symbols() {
+        assert_eq!(demangle_rust("_RNvC6_123foo", true), None);
+        assert_eq!(demangle_rust("_RB_", true), None);
+        assert_eq!(demangle_rust(&format!("_R{}C3foo", "I".repeat(1000)), true), None);
+        assert_eq!(demangle_rust("foo", true), None);
+    }
+}
@@ -0,0 +1,162 @@
+use super::HookError;
+
+/// A single hook which has been tagged with a `group = "..."` argument.
+///
+/// These are emitted by the hook macros into a dedicated linker section, so that
+/// every member of a group can be found at runtime without any manual registration.
+/// There is no reason to construct one of these by hand.
+#[doc(hidden)]
+#[repr(C)]
+pub struct GroupMember {
+    pub group: &'static str,
+    pub name: &'static str,
+    pub install: fn() -> Result<(), HookError>,
+    pub uninstall: fn(),
+    pub enable: fn() -> Result<(), HookError>,
+    pub disable: fn() -> Result<(), HookError>,
+    pub is_installed: fn() -> bool,
+    pub is_enabled: fn() -> bool,
+}
+
+// Ensure that the section exists even when no hook in this module is part of a group,
+// otherwise the start/stop symbols would never be defined by the linker
+#[used]
+#[link_section = "skex_hook_groups"]
+static GROUP_MEMBERS_ANCHOR: [GroupMember; 0] = [];
+
+extern "C" {
+    #[link_name = "__start_skex_hook_groups"]
+    static GROUP_MEMBERS_START: u8;
+
+    #[link_name = "__stop_skex_hook_groups"]
+    static GROUP_MEMBERS_END: u8;
+}
+
+fn all_group_members() -> &'static [GroupMember] {
+    unsafe {
+        let start = &GROUP_MEMBERS_START as *const u8 as *const GroupMember;
+        let end = &GROUP_MEMBERS_END as *const u8 as *const GroupMember;
+        let len = (end as usize - start as usize) / std::mem::size_of::<GroupMember>();
+        std::slice::from_raw_parts(start, len)
+    }
+}
+
+/// A set of hooks which are installed, enabled, disabled, and uninstalled together.
+///
+/// Hooks join a group through the `group` argument on the hook macros:
+/// ```ignore
+/// #[skyline::hook(offset = 0x1234, group = "feature_x")]
+/// fn my_hook(arg: u64) -> u64 {
+///     call_original!(arg)
+/// }
+///
+/// skyline::hooks::Group::new("feature_x").install()?;
+/// ```
+///
+/// Groups are local to the module that declares the hooks, two plugins using the
+/// same group name will not affect each other.
+///
+/// Every operation on a group is all-or-nothing: if any member fails, the members
+/// which were changed by the operation are rolled back before the error is returned.
+/// Members which were already in the requested state are left alone, and are not rolled back.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Group {
+    name: &'static str,
+}
+
+impl Group {
+    /// Creates a handle to the group with the provided name
+    pub const fn new(name: &'static str) -> Self {
+        Self { name }
+    }
+
+    /// Gets the name of this group
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Gets an iterator over every hook which is a member of this group
+    pub fn members(&self) -> impl Iterator<Item = &'static GroupMember> {
+        let name = self.name;
+        all_group_members()
+            .iter()
+            .filter(move |member| member.group == name)
+    }
+
+    /// Gets the names of every hook which is a member of this group
+    pub fn member_names(&self) -> impl Iterator<Item = &'static str> {
+        self.members().map(|member| member.name)
+    }
+
+    /// Applies `apply` to every member of the group which `is_applied` returns false for,
+    /// calling `rollback` on the members which were changed if any of them fail
+    fn apply_all(
+        &self,
+        is_applied: impl Fn(&GroupMember) -> bool,
+        apply: impl Fn(&GroupMember) -> Result<(), HookError>,
+        rollback: impl Fn(&GroupMember),
+    ) -> Result<(), HookError> {
+        let mut changed = Vec::new();
+        for member in self.members() {
+            if is_applied(member) {
+                continue;
+            }
+
+            if let Err(e) = apply(member) {
+                changed.into_iter().rev().for_each(rollback);
+                return Err(HookError::GroupMemberFailed {
+                    group: self.name,
+                    member: member.name,
+                    source: Box::new(e),
+                });
+            }
+
+            changed.push(member);
+        }
+
+        Ok(())
+    }
+
+    /// Installs every hook in the group
+    ///
+    /// # Returns
+    /// * `Ok(())` - Every member of the group was installed
+    /// * `Err(HookError)` - One of the members failed to install, and every member
+    ///   which this call installed before it has been uninstalled
+    pub fn install(&self) -> Result<(), HookError> {
+        self.apply_all(|member| (member.is_installed)(), |member| (member.install)(), |member| (member.uninstall)())
+    }
+
+    /// Uninstalls every hook in the group
+    pub fn uninstall(&self) {
+        self.members().for_each(|member| (member.uninstall)());
+    }
+
+    /// Enables every hook in the group
+    ///
+    /// # Returns
+    /// * `Ok(())` - Every member of the group was enabled
+    /// * `Err(HookError)` - One of the members could not be enabled, and every member
+    ///   which this call enabled before it has been disabled again
+    pub fn enable(&self) -> Result<(), HookError> {
+        self.apply_all(
+            |member| (member.is_enabled)(),
+            |member| (member.enable)(),
+            |member| { let _ = (member.disable)(); },
+        )
+    }
+
+    /// Disables every hook in the group
+    ///
+    /// # Returns
+    /// * `Ok(())` - Every member of the group was disabled
+    /// * `Err(HookError)` - One of the members could not be disabled, and every member
+    ///   which this call disabled before it has been enabled again
+    pub fn disable(&self) -> Result<(), HookError> {
+        self.apply_all(
+            |member| !(member.is_enabled)(),
+            |member| (member.disable)(),
+            |member| { let _ = (member.enable)(); },
+        )
+    }
+}
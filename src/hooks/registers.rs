@@ -1,4 +1,4 @@
-use std::fmt;
+use std::fmt::{self, Write as _};
 
 macro_rules! cpu_disp_impl {
     ($self:ident, $impl:ident, $f:expr) => {
@@ -14,6 +14,216 @@ macro_rules! cpu_disp_impl {
     }
 }
 
+// REGISTER NAMES
+
+macro_rules! register_names {
+    ($(#[$meta:meta])* $name:ident, $prefix:literal, $($reg:ident),*) => {
+        $(#[$meta])*
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[repr(u8)]
+        pub enum $name {
+            $($reg),*
+        }
+
+        impl $name {
+            /// Every register of this kind, in order
+            pub const ALL: &'static [Self] = &[$(Self::$reg),*];
+
+            /// Gets the index of this register in the context
+            pub fn index(self) -> usize {
+                self as usize
+            }
+
+            /// Gets the register with the provided index, if it exists
+            pub fn from_index(index: usize) -> Option<Self> {
+                Self::ALL.get(index).copied()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write_register_name(f, $prefix, self.index())
+            }
+        }
+    }
+}
+
+/// Writes a register name, honoring the width and alignment like [`fmt::Formatter::pad`] does
+/// without having to put the name in a `String` first, so that crash handlers can use it
+fn write_register_name(f: &mut fmt::Formatter<'_>, prefix: &str, index: usize) -> fmt::Result {
+    let len = prefix.len() + if index < 10 { 1 } else { 2 };
+    let padding = f.width().unwrap_or(0).saturating_sub(len);
+    let (before, after) = match f.align() {
+        Some(fmt::Alignment::Right) => (padding, 0),
+        Some(fmt::Alignment::Center) => (padding / 2, padding - padding / 2),
+        _ => (0, padding),
+    };
+
+    let fill = f.fill();
+    for _ in 0..before {
+        f.write_char(fill)?;
+    }
+    write!(f, "{}{}", prefix, index)?;
+    for _ in 0..after {
+        f.write_char(fill)?;
+    }
+    Ok(())
+}
+
+register_names!(
+    /// The 64-bit view of a general purpose register, used to index into the hook contexts
+    X, "x",
+    X0,
+    X1,
+    X2,
+    X3,
+    X4,
+    X5,
+    X6,
+    X7,
+    X8,
+    X9,
+    X10,
+    X11,
+    X12,
+    X13,
+    X14,
+    X15,
+    X16,
+    X17,
+    X18,
+    X19,
+    X20,
+    X21,
+    X22,
+    X23,
+    X24,
+    X25,
+    X26,
+    X27,
+    X28,
+    X29,
+    X30
+);
+
+register_names!(
+    /// The 32-bit view of a general purpose register, used to index into the hook contexts
+    ///
+    /// This refers to the same storage as [`X`], since the 32-bit registers are the lower half
+    /// of the 64-bit registers.
+    W, "w",
+    W0,
+    W1,
+    W2,
+    W3,
+    W4,
+    W5,
+    W6,
+    W7,
+    W8,
+    W9,
+    W10,
+    W11,
+    W12,
+    W13,
+    W14,
+    W15,
+    W16,
+    W17,
+    W18,
+    W19,
+    W20,
+    W21,
+    W22,
+    W23,
+    W24,
+    W25,
+    W26,
+    W27,
+    W28,
+    W29,
+    W30
+);
+
+register_names!(
+    /// One of the NEON/SIMD registers, used to index into the hook contexts
+    V, "v",
+    V0,
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    V6,
+    V7,
+    V8,
+    V9,
+    V10,
+    V11,
+    V12,
+    V13,
+    V14,
+    V15,
+    V16,
+    V17,
+    V18,
+    V19,
+    V20,
+    V21,
+    V22,
+    V23,
+    V24,
+    V25,
+    V26,
+    V27,
+    V28,
+    V29,
+    V30,
+    V31
+);
+
+impl X {
+    /// The intra-procedure-call scratch register, used by veneers and PLT stubs
+    pub const IP0: Self = Self::X16;
+
+    /// The second intra-procedure-call scratch register
+    pub const IP1: Self = Self::X17;
+
+    /// The platform register, which is reserved on Horizon
+    pub const PLATFORM: Self = Self::X18;
+
+    /// The frame pointer
+    pub const FP: Self = Self::X29;
+
+    /// The link register, which holds the return address
+    pub const LR: Self = Self::X30;
+}
+
+impl W {
+    /// The intra-procedure-call scratch register, used by veneers and PLT stubs
+    pub const IP0: Self = Self::W16;
+
+    /// The second intra-procedure-call scratch register
+    pub const IP1: Self = Self::W17;
+
+    /// The platform register, which is reserved on Horizon
+    pub const PLATFORM: Self = Self::W18;
+}
+
+impl From<W> for X {
+    fn from(w: W) -> Self {
+        Self::ALL[w.index()]
+    }
+}
+
+impl From<X> for W {
+    fn from(x: X) -> Self {
+        Self::ALL[x.index()]
+    }
+}
+
+// GENERAL PURPOSE REGISTERS
+
 #[repr(C)]
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub struct CpuRegister(u64);
@@ -56,6 +266,181 @@ impl CpuRegister {
     pub fn set_r(&mut self, r: u32) {
         self.0 = r as u64;
     }
+
+    /// Returns the Aarch64 64-bit representation of this register as a signed integer
+    pub fn sx(self) -> i64 {
+        self.0 as i64
+    }
+
+    /// Returns the Aarch64 32-bit representation of this register as a signed integer
+    pub fn sw(self) -> i32 {
+        self.w() as i32
+    }
+
+    /// Returns the lowest 16 bits of this register
+    pub fn h(self) -> u16 {
+        self.0 as u16
+    }
+
+    /// Returns the lowest 16 bits of this register as a signed integer
+    pub fn sh(self) -> i16 {
+        self.0 as i16
+    }
+
+    /// Returns the lowest 8 bits of this register
+    pub fn b(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Returns the lowest 8 bits of this register as a signed integer
+    pub fn sb(self) -> i8 {
+        self.0 as i8
+    }
+
+    /// Returns this register as a boolean
+    ///
+    /// Only the lowest 8 bits of a register holding a `bool` are defined, so the rest are ignored
+    pub fn as_bool(self) -> bool {
+        self.b() != 0
+    }
+
+    /// Returns this register as a pointer to a `T`
+    pub fn as_ptr<T>(self) -> *const T {
+        self.0 as usize as *const T
+    }
+
+    /// Returns this register as a mutable pointer to a `T`
+    pub fn as_mut_ptr<T>(self) -> *mut T {
+        self.0 as usize as *mut T
+    }
+
+    /// Returns this register as a reference to a `T`, or `None` if it is null
+    ///
+    /// # Safety
+    /// The register must either be null or point to a valid `T` for the lifetime `'a`
+    pub unsafe fn as_ref<'a, T>(self) -> Option<&'a T> {
+        self.as_ptr::<T>().as_ref()
+    }
+
+    /// Returns this register as a mutable reference to a `T`, or `None` if it is null
+    ///
+    /// # Safety
+    /// The register must either be null or point to a valid `T` for the lifetime `'a`, and
+    /// nothing else may access that `T` while the reference is alive
+    pub unsafe fn as_mut<'a, T>(self) -> Option<&'a mut T> {
+        self.as_mut_ptr::<T>().as_mut()
+    }
+
+    /// Sets the Aarch64 64-bit representation of this register from a signed integer
+    pub fn set_sx(&mut self, sx: i64) {
+        self.0 = sx as u64;
+    }
+
+    /// Sets the Aarch64 32-bit representation of this register from a signed integer,
+    /// sign extending it to fill all 64 bits
+    pub fn set_sw(&mut self, sw: i32) {
+        self.0 = sw as i64 as u64;
+    }
+
+    /// Sets the lowest 16 bits of this register, zeroing out the remaining bits
+    pub fn set_h(&mut self, h: u16) {
+        self.0 = h as u64;
+    }
+
+    /// Sets the lowest 16 bits of this register from a signed integer,
+    /// sign extending it to fill all 64 bits
+    pub fn set_sh(&mut self, sh: i16) {
+        self.0 = sh as i64 as u64;
+    }
+
+    /// Sets the lowest 8 bits of this register, zeroing out the remaining bits
+    pub fn set_b(&mut self, b: u8) {
+        self.0 = b as u64;
+    }
+
+    /// Sets the lowest 8 bits of this register from a signed integer,
+    /// sign extending it to fill all 64 bits
+    pub fn set_sb(&mut self, sb: i8) {
+        self.0 = sb as i64 as u64;
+    }
+
+    /// Sets this register to a boolean
+    pub fn set_bool(&mut self, value: bool) {
+        self.0 = value as u64;
+    }
+
+    /// Sets this register to the address of a pointer
+    pub fn set_ptr<T>(&mut self, ptr: *const T) {
+        self.0 = ptr as usize as u64;
+    }
+}
+
+macro_rules! cpu_from_impl {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for CpuRegister {
+                /// Creates a register from the signed value, sign extending it to fill all 64 bits
+                fn from(value: $ty) -> Self {
+                    #[allow(clippy::unnecessary_cast)]
+                    Self(value as i64 as u64)
+                }
+            }
+        )*
+    }
+}
+
+cpu_from_impl!(i8, i16, i32, i64, isize);
+
+macro_rules! cpu_from_unsigned_impl {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for CpuRegister {
+                /// Creates a register from the unsigned value or boolean, zero extending it to fill all 64 bits
+                fn from(value: $ty) -> Self {
+                    #[allow(clippy::unnecessary_cast)]
+                    Self(value as u64)
+                }
+            }
+        )*
+    }
+}
+
+cpu_from_unsigned_impl!(u8, u16, u32, u64, usize, bool);
+
+impl<T> From<*const T> for CpuRegister {
+    fn from(ptr: *const T) -> Self {
+        Self(ptr as usize as u64)
+    }
+}
+
+impl<T> From<*mut T> for CpuRegister {
+    fn from(ptr: *mut T) -> Self {
+        Self(ptr as usize as u64)
+    }
+}
+
+impl From<CpuRegister> for u64 {
+    fn from(reg: CpuRegister) -> Self {
+        reg.x()
+    }
+}
+
+impl From<CpuRegister> for i64 {
+    fn from(reg: CpuRegister) -> Self {
+        reg.sx()
+    }
+}
+
+impl From<CpuRegister> for usize {
+    fn from(reg: CpuRegister) -> Self {
+        reg.x() as usize
+    }
+}
+
+impl From<CpuRegister> for isize {
+    fn from(reg: CpuRegister) -> Self {
+        reg.sx() as isize
+    }
 }
 
 impl fmt::Debug for CpuRegister {
@@ -110,6 +495,107 @@ impl fmt::Display for CpuRegister {
     }
 }
 
+// PROCESSOR STATE
+
+/// The condition flags of the processor state (the NZCV system register)
+///
+/// These are set by comparison instructions such as `cmp` and `tst`, and then read by
+/// conditional instructions such as `b.eq` and `csel`. Only bits 28-31 hold any state.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Pstate(u64);
+
+impl Pstate {
+    const N_BIT: u64 = 1 << 31;
+    const Z_BIT: u64 = 1 << 30;
+    const C_BIT: u64 = 1 << 29;
+    const V_BIT: u64 = 1 << 28;
+
+    fn set_bit(&mut self, bit: u64, value: bool) {
+        if value {
+            self.0 |= bit;
+        } else {
+            self.0 &= !bit;
+        }
+    }
+
+    /// Returns the raw value of the NZCV register
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Sets the raw value of the NZCV register
+    pub fn set_bits(&mut self, bits: u64) {
+        self.0 = bits;
+    }
+
+    /// Returns the negative flag, set when the result of the last comparison was negative
+    pub fn n(self) -> bool {
+        self.0 & Self::N_BIT != 0
+    }
+
+    /// Returns the zero flag, set when the result of the last comparison was zero (i.e. the operands were equal)
+    pub fn z(self) -> bool {
+        self.0 & Self::Z_BIT != 0
+    }
+
+    /// Returns the carry flag, set when the last comparison produced an unsigned carry (i.e. no borrow)
+    pub fn c(self) -> bool {
+        self.0 & Self::C_BIT != 0
+    }
+
+    /// Returns the overflow flag, set when the last comparison produced a signed overflow
+    pub fn v(self) -> bool {
+        self.0 & Self::V_BIT != 0
+    }
+
+    /// Sets the negative flag
+    pub fn set_n(&mut self, n: bool) {
+        self.set_bit(Self::N_BIT, n);
+    }
+
+    /// Sets the zero flag
+    pub fn set_z(&mut self, z: bool) {
+        self.set_bit(Self::Z_BIT, z);
+    }
+
+    /// Sets the carry flag
+    pub fn set_c(&mut self, c: bool) {
+        self.set_bit(Self::C_BIT, c);
+    }
+
+    /// Sets the overflow flag
+    pub fn set_v(&mut self, v: bool) {
+        self.set_bit(Self::V_BIT, v);
+    }
+}
+
+impl fmt::Debug for Pstate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pstate")
+            .field("n", &self.n())
+            .field("z", &self.z())
+            .field("c", &self.c())
+            .field("v", &self.v())
+            .finish()
+    }
+}
+
+impl fmt::Display for Pstate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Set flags are uppercase and cleared flags are lowercase, i.e. `nZCv`
+        let flag = |set: bool, c: char| if set { c.to_ascii_uppercase() } else { c };
+        write!(
+            f,
+            "{}{}{}{}",
+            flag(self.n(), 'n'),
+            flag(self.z(), 'z'),
+            flag(self.c(), 'c'),
+            flag(self.v(), 'v')
+        )
+    }
+}
+
 // VECTOR REGISTERS
 
 macro_rules! vector_disp_array {
@@ -160,6 +646,102 @@ macro_rules! vector_fp_disp_impl {
     }
 }
 
+/// Splits a 128-bit value into its little-endian lanes of the provided type
+macro_rules! get_lanes {
+    ($value:expr, $ty:ty, $count:literal) => {{
+        const SIZE: usize = 16 / $count;
+        let bytes = $value.to_le_bytes();
+        let mut lanes = [<$ty>::default(); $count];
+        for (lane, chunk) in lanes.iter_mut().zip(bytes.chunks_exact(SIZE)) {
+            *lane = <$ty>::from_le_bytes(std::convert::TryFrom::try_from(chunk).unwrap());
+        }
+        lanes
+    }}
+}
+
+/// Sets a single little-endian lane of a 128-bit value, panicking if the index is out of bounds
+macro_rules! set_lane {
+    ($value:expr, $ty:ty, $count:literal, $index:expr, $lane:expr) => {{
+        const SIZE: usize = 16 / $count;
+        let index: usize = $index;
+        assert!(index < $count, "lane index {} is out of bounds for a register with {} lanes", index, $count);
+        let mut bytes = $value.to_le_bytes();
+        bytes[index * SIZE..(index + 1) * SIZE].copy_from_slice(&<$ty>::to_le_bytes($lane));
+        $value = u128::from_le_bytes(bytes);
+    }}
+}
+
+/// Joins little-endian lanes into a 128-bit value
+macro_rules! from_lanes {
+    ($lanes:expr, $ty:ty) => {{
+        let mut bytes = [0u8; 16];
+        for (chunk, lane) in bytes.chunks_exact_mut(std::mem::size_of::<$ty>()).zip($lanes) {
+            chunk.copy_from_slice(&lane.to_le_bytes());
+        }
+        u128::from_le_bytes(bytes)
+    }}
+}
+
+/// Converts the bits of an IEEE 754 half precision float into an [`f32`]
+pub(crate) fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+
+    match exponent {
+        // Zero and subnormals, which are normal as an f32 so they are easiest to scale
+        0 => {
+            let value = mantissa as f32 * f32::from_bits(0x3380_0000);
+            f32::from_bits(sign | value.to_bits())
+        },
+        // Infinity and NaN
+        0x1F => f32::from_bits(sign | 0x7F80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13))
+    }
+}
+
+/// Converts an [`f32`] into the bits of an IEEE 754 half precision float, rounding to the nearest even
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    // Infinity and NaN, making sure that a NaN stays a NaN
+    if exponent == 0xFF {
+        let nan = if mantissa != 0 { 0x200 | (mantissa >> 13) as u16 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    // Too large to represent, so round to infinity
+    if exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    // Too small to be a normal half, so it is either a subnormal or zero
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round = remainder > halfway || (remainder == halfway && half & 1 == 1);
+        return sign | (half + round as u32) as u16;
+    }
+
+    // Rounding can carry into the exponent, which is still the correct result
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1FFF;
+    let round = remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1);
+    sign | (half + round as u32) as u16
+}
+
 /// A structure to represent one of the Aarch64 NEON/SIMD registers.
 /// 
 /// There are 32 128-bit SIMD registers on Aarch64 systems, and they can be split into "lanes".
@@ -186,64 +768,74 @@ impl VectorRegister {
 
     /// Returns the two 64-bit components of this register as [`f64`] values
     pub fn d(self) -> [f64; 2] {
-        unsafe {
-            [
-                *(&self as *const Self as *const f64).add(0),
-                *(&self as *const Self as *const f64).add(1),
-            ]
-        }
+        get_lanes!(self.0, f64, 2)
     }
 
     /// Returns the four 32-bit components of this register as [`f32`] values
     pub fn s(self) -> [f32; 4] {
-        unsafe {
-            [
-                *(&self as *const Self as *const f32).add(0),
-                *(&self as *const Self as *const f32).add(1),
-                *(&self as *const Self as *const f32).add(2),
-                *(&self as *const Self as *const f32).add(3),
-            ]
-        }
+        get_lanes!(self.0, f32, 4)
     }
 
     /// Returns the eight 16-bit components of this register
     pub fn h(self) -> [u16; 8] {
-        unsafe {
-            [
-                *(&self as *const Self as *const u16).add(0),
-                *(&self as *const Self as *const u16).add(1),
-                *(&self as *const Self as *const u16).add(2),
-                *(&self as *const Self as *const u16).add(3),
-                *(&self as *const Self as *const u16).add(4),
-                *(&self as *const Self as *const u16).add(5),
-                *(&self as *const Self as *const u16).add(6),
-                *(&self as *const Self as *const u16).add(7),
-            ]
-        }
+        get_lanes!(self.0, u16, 8)
     }
 
     /// Returns the sixteen 8-bit components of this register
     pub fn b(self) -> [u8; 16] {
-        unsafe {
-            [
-                *(&self as *const Self as *const u8).add(0),
-                *(&self as *const Self as *const u8).add(1),
-                *(&self as *const Self as *const u8).add(2),
-                *(&self as *const Self as *const u8).add(3),
-                *(&self as *const Self as *const u8).add(4),
-                *(&self as *const Self as *const u8).add(5),
-                *(&self as *const Self as *const u8).add(6),
-                *(&self as *const Self as *const u8).add(7),
-                *(&self as *const Self as *const u8).add(8),
-                *(&self as *const Self as *const u8).add(9),
-                *(&self as *const Self as *const u8).add(10),
-                *(&self as *const Self as *const u8).add(11),
-                *(&self as *const Self as *const u8).add(12),
-                *(&self as *const Self as *const u8).add(13),
-                *(&self as *const Self as *const u8).add(14),
-                *(&self as *const Self as *const u8).add(15),
-            ]
-        }
+        get_lanes!(self.0, u8, 16)
+    }
+
+    /// Returns the two 64-bit components of this register as [`i64`] values
+    pub fn i64x2(self) -> [i64; 2] {
+        get_lanes!(self.0, i64, 2)
+    }
+
+    /// Returns the two 64-bit components of this register as [`u64`] values
+    pub fn u64x2(self) -> [u64; 2] {
+        get_lanes!(self.0, u64, 2)
+    }
+
+    /// Returns the four 32-bit components of this register as [`i32`] values
+    pub fn i32x4(self) -> [i32; 4] {
+        get_lanes!(self.0, i32, 4)
+    }
+
+    /// Returns the four 32-bit components of this register as [`u32`] values
+    pub fn u32x4(self) -> [u32; 4] {
+        get_lanes!(self.0, u32, 4)
+    }
+
+    /// Returns the eight 16-bit components of this register as [`i16`] values
+    pub fn i16x8(self) -> [i16; 8] {
+        get_lanes!(self.0, i16, 8)
+    }
+
+    /// Returns the sixteen 8-bit components of this register as [`i8`] values
+    pub fn i8x16(self) -> [i8; 16] {
+        get_lanes!(self.0, i8, 16)
+    }
+
+    /// Returns the eight 16-bit components of this register as half precision floats,
+    /// converted to [`f32`] values
+    pub fn f16x8(self) -> [f32; 8] {
+        self.h().map(f16_to_f32)
+    }
+
+    /// Returns the first three 32-bit components of this register as [`f32`] values
+    ///
+    /// This is how a 3-float vector (such as a `Vector3f`) is passed in a register,
+    /// the fourth component is padding
+    pub fn vec3(self) -> [f32; 3] {
+        let [x, y, z, _] = self.s();
+        [x, y, z]
+    }
+
+    /// Returns the four 32-bit components of this register as [`f32`] values
+    ///
+    /// This is equivalent to [`VectorRegister::s`]
+    pub fn vec4(self) -> [f32; 4] {
+        self.s()
     }
 
     /// Sets all 128 bits of the vector register
@@ -252,32 +844,142 @@ impl VectorRegister {
     }
 
     /// Sets the specified 64-bit lane of this register (other 64-bits are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..2`
     pub fn set_d(&mut self, index: usize, d: f64) {
-        unsafe {
-            std::slice::from_raw_parts_mut(self as *mut Self as *mut f64, 2)[index] = d;
-        }
+        set_lane!(self.0, f64, 2, index, d);
     }
 
     /// Sets the specified 32-bit lane of this register (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..4`
     pub fn set_s(&mut self, index: usize, s: f32) {
-        unsafe {
-            std::slice::from_raw_parts_mut(self as *mut Self as *mut f32, 4)[index] = s;
-        }
+        set_lane!(self.0, f32, 4, index, s);
     }
 
     /// Sets the specified 16-bit lane of this register (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..8`
     pub fn set_h(&mut self, index: usize, h: u16) {
-        unsafe {
-            std::slice::from_raw_parts_mut(self as *mut Self as *mut u16, 8)[index] = h;
-        }
+        set_lane!(self.0, u16, 8, index, h);
     }
 
     /// Sets the specified 8-bit lane of this register (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..16`
     pub fn set_b(&mut self, index: usize, b: u8) {
-        unsafe {
-            std::slice::from_raw_parts_mut(self as *mut Self as *mut u8, 16)[index] = b;
+        set_lane!(self.0, u8, 16, index, b);
+    }
+
+    /// Sets the specified 64-bit lane of this register to an [`i64`] (other 64-bits are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..2`
+    pub fn set_i64(&mut self, index: usize, value: i64) {
+        set_lane!(self.0, i64, 2, index, value);
+    }
+
+    /// Sets the specified 64-bit lane of this register to a [`u64`] (other 64-bits are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..2`
+    pub fn set_u64(&mut self, index: usize, value: u64) {
+        set_lane!(self.0, u64, 2, index, value);
+    }
+
+    /// Sets the specified 32-bit lane of this register to an [`i32`] (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..4`
+    pub fn set_i32(&mut self, index: usize, value: i32) {
+        set_lane!(self.0, i32, 4, index, value);
+    }
+
+    /// Sets the specified 32-bit lane of this register to a [`u32`] (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..4`
+    pub fn set_u32(&mut self, index: usize, value: u32) {
+        set_lane!(self.0, u32, 4, index, value);
+    }
+
+    /// Sets the specified 16-bit lane of this register to an [`i16`] (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..8`
+    pub fn set_i16(&mut self, index: usize, value: i16) {
+        set_lane!(self.0, i16, 8, index, value);
+    }
+
+    /// Sets the specified 8-bit lane of this register to an [`i8`] (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..16`
+    pub fn set_i8(&mut self, index: usize, value: i8) {
+        set_lane!(self.0, i8, 16, index, value);
+    }
+
+    /// Sets the specified 16-bit lane of this register to a half precision float, converted from
+    /// an [`f32`] by rounding to the nearest even value (other lanes are unmodified)
+    ///
+    /// # Panics
+    /// This will panic if `index` is not in the range `0..8`
+    pub fn set_f16(&mut self, index: usize, value: f32) {
+        self.set_h(index, f32_to_f16(value));
+    }
+
+    /// Sets the first three 32-bit lanes of this register (the fourth lane is unmodified)
+    pub fn set_vec3(&mut self, vec: [f32; 3]) {
+        for (index, value) in vec.iter().copied().enumerate() {
+            self.set_s(index, value);
         }
     }
+
+    /// Sets all four 32-bit lanes of this register
+    pub fn set_vec4(&mut self, vec: [f32; 4]) {
+        *self = vec.into();
+    }
+}
+
+impl From<[f32; 4]> for VectorRegister {
+    fn from(lanes: [f32; 4]) -> Self {
+        Self(from_lanes!(lanes, f32))
+    }
+}
+
+impl From<[f32; 3]> for VectorRegister {
+    /// Creates a register holding a 3-float vector, with the fourth lane set to zero
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        [x, y, z, 0.0].into()
+    }
+}
+
+impl From<[f64; 2]> for VectorRegister {
+    fn from(lanes: [f64; 2]) -> Self {
+        Self(from_lanes!(lanes, f64))
+    }
+}
+
+impl From<VectorRegister> for [f32; 4] {
+    fn from(reg: VectorRegister) -> Self {
+        reg.s()
+    }
+}
+
+impl From<VectorRegister> for [f32; 3] {
+    fn from(reg: VectorRegister) -> Self {
+        reg.vec3()
+    }
+}
+
+impl From<VectorRegister> for [f64; 2] {
+    fn from(reg: VectorRegister) -> Self {
+        reg.d()
+    }
 }
 
 impl fmt::Debug for VectorRegister {
@@ -376,9 +1078,8 @@ impl FpuRegister {
 
     /// Transforms the view of this register into a [`VectorRegister`] view.
     pub fn as_vec_mut(&mut self) -> &mut VectorRegister {
-        unsafe {
-            std::mem::transmute(self)
-        }
+        // Both registers are a `#[repr(C)]` wrapper around the same u128, so they have the same layout
+        unsafe { &mut *(self as *mut FpuRegister).cast::<VectorRegister>() }
     }
 
     /// Gets the 128-bit representation of this register
@@ -388,30 +1089,58 @@ impl FpuRegister {
 
     /// Gets the 64-bit representation of this register as an [`f64`]
     pub fn d(self) -> f64 {
-        unsafe {
-            *(&self.0 as *const u128 as *const f64)
-        }
+        f64::from_bits(self.0 as u64)
     }
 
     /// Gets the 32-bit representation of this register as an [`f32`]
     pub fn s(self) -> f32 {
-        unsafe {
-            *(&self.0 as *const u128 as *const f32)
-        }
+        f32::from_bits(self.0 as u32)
     }
 
     /// Gets the 16-bit representation of this register
     pub fn h(self) -> u16 {
-        unsafe {
-            *(&self.0 as *const u128 as *const u16)
-        }
+        self.0 as u16
     }
 
     /// Gets the 8-bit representation of this register
     pub fn b(self) -> u8 {
-        unsafe {
-            *(&self.0 as *const u128 as *const u8)
-        }
+        self.0 as u8
+    }
+
+    /// Gets the 64-bit representation of this register as an [`i64`]
+    pub fn i64(self) -> i64 {
+        self.0 as i64
+    }
+
+    /// Gets the 64-bit representation of this register as a [`u64`]
+    pub fn u64(self) -> u64 {
+        self.0 as u64
+    }
+
+    /// Gets the 32-bit representation of this register as an [`i32`]
+    pub fn i32(self) -> i32 {
+        self.0 as i32
+    }
+
+    /// Gets the 32-bit representation of this register as a [`u32`]
+    pub fn u32(self) -> u32 {
+        self.0 as u32
+    }
+
+    /// Gets the 16-bit representation of this register as an [`i16`]
+    pub fn i16(self) -> i16 {
+        self.0 as i16
+    }
+
+    /// Gets the 8-bit representation of this register as an [`i8`]
+    pub fn i8(self) -> i8 {
+        self.0 as i8
+    }
+
+    /// Gets the 16-bit representation of this register as a half precision float,
+    /// converted to an [`f32`]
+    pub fn f16(self) -> f32 {
+        f16_to_f32(self.h())
     }
 
     /// Sets all 128-bits of the register
@@ -421,30 +1150,58 @@ impl FpuRegister {
 
     /// Sets the first 64-bits of the register, zeroing out the remaining bits
     pub fn set_d(&mut self, d: f64) {
-        let vec = self.as_vec_mut();
-        vec.set_v(0);
-        vec.set_d(0, d);
+        self.0 = d.to_bits() as u128;
     }
 
     /// Sets the first 32-bits of the register, zeroing out the remaining bits
     pub fn set_s(&mut self, s: f32) {
-        let vec = self.as_vec_mut();
-        vec.set_v(0);
-        vec.set_s(0, s);
+        self.0 = s.to_bits() as u128;
     }
 
     /// Sets the first 16-bits of the register, zeroing out the remaining bits
     pub fn set_h(&mut self, h: u16) {
-        let vec = self.as_vec_mut();
-        vec.set_v(0);
-        vec.set_h(0, h);
+        self.0 = h as u128;
     }
 
     /// Sets the first 8-bits of the register, zeroing out the remaining bits
     pub fn set_b(&mut self, b: u8) {
-        let vec = self.as_vec_mut();
-        vec.set_v(0);
-        vec.set_b(0, b);
+        self.0 = b as u128;
+    }
+
+    /// Sets the first 64-bits of the register to an [`i64`], zeroing out the remaining bits
+    pub fn set_i64(&mut self, value: i64) {
+        self.0 = value as u64 as u128;
+    }
+
+    /// Sets the first 64-bits of the register to a [`u64`], zeroing out the remaining bits
+    pub fn set_u64(&mut self, value: u64) {
+        self.0 = value as u128;
+    }
+
+    /// Sets the first 32-bits of the register to an [`i32`], zeroing out the remaining bits
+    pub fn set_i32(&mut self, value: i32) {
+        self.0 = value as u32 as u128;
+    }
+
+    /// Sets the first 32-bits of the register to a [`u32`], zeroing out the remaining bits
+    pub fn set_u32(&mut self, value: u32) {
+        self.0 = value as u128;
+    }
+
+    /// Sets the first 16-bits of the register to an [`i16`], zeroing out the remaining bits
+    pub fn set_i16(&mut self, value: i16) {
+        self.0 = value as u16 as u128;
+    }
+
+    /// Sets the first 8-bits of the register to an [`i8`], zeroing out the remaining bits
+    pub fn set_i8(&mut self, value: i8) {
+        self.0 = value as u8 as u128;
+    }
+
+    /// Sets the first 16-bits of the register to a half precision float, converted from
+    /// an [`f32`] by rounding to the nearest even value, zeroing out the remaining bits
+    pub fn set_f16(&mut self, value: f32) {
+        self.set_h(f32_to_f16(value));
     }
 }
 
@@ -500,4 +1257,177 @@ impl fmt::Display for FpuRegister {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fpu_fp_disp_impl!(self, Display, f)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, target_arch = "aarch64"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_lanes() {
+        let mut reg = VectorRegister(0);
+
+        reg.set_i8(0, -1);
+        reg.set_i8(15, -128);
+        assert_eq!(reg.i8x16()[0], -1);
+        assert_eq!(reg.i8x16()[15], -128);
+        assert_eq!(reg.v(), 0x8000_0000_0000_0000_0000_0000_0000_00FF);
+
+        reg.set_v(0);
+        reg.set_i16(1, -2);
+        reg.set_i16(7, i16::MAX);
+        assert_eq!(reg.i16x8(), [0, -2, 0, 0, 0, 0, 0, i16::MAX]);
+        assert_eq!(reg.h()[1], 0xFFFE);
+
+        reg.set_v(0);
+        reg.set_i32(2, i32::MIN);
+        reg.set_u32(3, 0xDEAD_BEEF);
+        assert_eq!(reg.i32x4(), [0, 0, i32::MIN, 0xDEAD_BEEFu32 as i32]);
+        assert_eq!(reg.u32x4(), [0, 0, 0x8000_0000, 0xDEAD_BEEF]);
+
+        reg.set_v(0);
+        reg.set_i64(1, -2);
+        reg.set_u64(0, u64::MAX);
+        assert_eq!(reg.i64x2(), [-1, -2]);
+        assert_eq!(reg.u64x2(), [u64::MAX, 0xFFFF_FFFF_FFFF_FFFE]);
+        assert_eq!(reg.v() >> 64, 0xFFFF_FFFF_FFFF_FFFE);
+    }
+
+    #[test]
+    fn setting_a_lane_keeps_the_others() {
+        let mut reg = VectorRegister(u128::MAX);
+        reg.set_b(3, 0);
+        reg.set_s(2, 1.0);
+        assert_eq!(reg.b()[3], 0);
+        assert_eq!(reg.b()[2], 0xFF);
+        assert_eq!(reg.u32x4(), [0x00FF_FFFF, u32::MAX, 1.0f32.to_bits(), u32::MAX]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn lane_out_of_range() {
+        VectorRegister(0).set_d(2, 1.0);
+    }
+
+    #[test]
+    fn f16_lanes() {
+        let mut reg = VectorRegister(0);
+        reg.set_f16(0, 1.0);
+        reg.set_f16(5, -0.5);
+        assert_eq!(reg.h()[0], 0x3C00);
+        assert_eq!(reg.h()[5], 0xB800);
+        assert_eq!(reg.f16x8(), [1.0, 0.0, 0.0, 0.0, 0.0, -0.5, 0.0, 0.0]);
+
+        let mut fpu = FpuRegister(u128::MAX);
+        fpu.set_f16(65504.0);
+        assert_eq!(fpu.q(), 0x7BFF);
+        assert_eq!(fpu.f16(), 65504.0);
+    }
+
+    #[test]
+    fn vector_round_trips() {
+        let vec4 = [1.0f32, -2.5, 3.25, f32::MAX];
+        assert_eq!(<[f32; 4]>::from(VectorRegister::from(vec4)), vec4);
+
+        let vec3 = [0.5f32, -0.0, 1e-20];
+        let reg = VectorRegister::from(vec3);
+        assert_eq!(<[f32; 3]>::from(reg), vec3);
+        assert_eq!(reg.s()[3], 0.0);
+
+        let mut reg = VectorRegister(u128::MAX);
+        reg.set_vec3(vec3);
+        assert_eq!(reg.vec3(), vec3);
+        assert_eq!(reg.u32x4()[3], u32::MAX);
+
+        let d = [std::f64::consts::PI, f64::MIN_POSITIVE];
+        assert_eq!(<[f64; 2]>::from(VectorRegister::from(d)), d);
+    }
+
+    #[test]
+    fn fpu_as_vec_mut() {
+        let mut fpu = FpuRegister(0);
+        fpu.as_vec_mut().set_s(1, 2.0);
+        assert_eq!(fpu.q(), (2.0f32.to_bits() as u128) << 32);
+        assert_eq!(fpu.as_vec().s(), [0.0, 2.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn f16_to_f32_special_values() {
+        assert_eq!(f16_to_f32(0x0000).to_bits(), 0.0f32.to_bits());
+        assert_eq!(f16_to_f32(0x8000).to_bits(), (-0.0f32).to_bits());
+        assert_eq!(f16_to_f32(0x3C00), 1.0);
+        assert_eq!(f16_to_f32(0x7BFF), 65504.0);
+        assert_eq!(f16_to_f32(0x0400), 2f32.powi(-14));
+
+        // Subnormals
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x03FF), 1023.0 * 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x8001), -(2f32.powi(-24)));
+
+        assert_eq!(f16_to_f32(0x7C00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xFC00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7E00).is_nan());
+        assert!(f16_to_f32(0x7C01).is_nan());
+    }
+
+    #[test]
+    fn f32_to_f16_special_values() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+        assert_eq!(f32_to_f16(1.0), 0x3C00);
+        assert_eq!(f32_to_f16(65504.0), 0x7BFF);
+
+        // Subnormals, and values too small for them
+        assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(1023.0 * 2f32.powi(-24)), 0x03FF);
+        assert_eq!(f32_to_f16(2f32.powi(-14)), 0x0400);
+        assert_eq!(f32_to_f16(2f32.powi(-30)), 0x0000);
+        assert_eq!(f32_to_f16(-(2f32.powi(-30))), 0x8000);
+
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7C00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xFC00);
+        assert_eq!(f32_to_f16(1e10), 0x7C00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+
+        // A NaN whose payload only has low bits must not turn into infinity
+        let nan = f32::from_bits(0x7F80_0001);
+        assert_eq!(f32_to_f16(nan) & 0x7C00, 0x7C00);
+        assert_ne!(f32_to_f16(nan) & 0x3FF, 0);
+    }
+
+    #[test]
+    fn f32_to_f16_rounding() {
+        let ulp = 2f32.powi(-10);
+
+        // Ties round to the even mantissa
+        assert_eq!(f32_to_f16(1.0 + ulp / 2.0), 0x3C00);
+        assert_eq!(f32_to_f16(1.0 + ulp * 1.5), 0x3C02);
+        assert_eq!(f32_to_f16(1.0 + ulp * 0.75), 0x3C01);
+        assert_eq!(f32_to_f16(1.0 + ulp * 0.25), 0x3C00);
+
+        // Rounding up can carry into the exponent, or overflow into infinity
+        assert_eq!(f32_to_f16(2.0 - ulp / 4.0), 0x4000);
+        assert_eq!(f32_to_f16(65519.0), 0x7BFF);
+        assert_eq!(f32_to_f16(65520.0), 0x7C00);
+
+        // Subnormal ties
+        assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_f16(3.0 * 2f32.powi(-25)), 0x0002);
+        assert_eq!(f32_to_f16(1.5 * 2f32.powi(-25)), 0x0001);
+
+        // The largest subnormal can round up into the smallest normal
+        assert_eq!(f32_to_f16(2f32.powi(-14) - 2f32.powi(-26)), 0x0400);
+    }
+
+    #[test]
+    fn f16_round_trips_exactly() {
+        for half in 0..=u16::MAX {
+            let value = f16_to_f32(half);
+            if value.is_nan() {
+                assert!(f16_to_f32(f32_to_f16(value)).is_nan());
+            } else {
+                assert_eq!(f32_to_f16(value), half, "{:#x}", half);
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+
+use super::backtrace::Frame;
+use super::contexts::InlineCtx;
+
+use thiserror::Error;
+
+/// The permission bits of a memory region which can be read and written
+const PERM_RW: u32 = 0b011;
+
+/// The permission bit of a memory region which can be executed
+const PERM_X: u32 = 0b100;
+
+/// The number of bytes shown on each line of a hexdump
+const HEXDUMP_LINE: u64 = 0x10;
+
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StackError {
+    #[error("Failed to query the memory at {0:#x} ({1:#x})")]
+    QueryFailed(u64, u32),
+
+    #[error("The stack pointer {0:#x} is not in readable and writable memory")]
+    NotStackMemory(u64),
+
+    #[error("Reading {size:#x} bytes at {address:#x} goes outside of the stack")]
+    OutOfBounds { address: u64, size: usize },
+}
+
+/// A bounds-checked view of a thread's stack.
+///
+/// The bounds are those that the current thread's `nn::os::ThreadType` keeps for its stack, see
+/// [`crate::nx::get_current_stack_bounds`]. When the stack pointer isn't on that stack, or the
+/// thread has no `ThreadType`, they are those of the memory mapping which contains the stack
+/// pointer instead, which can be larger than the stack. Reads outside of the bounds return
+/// [`StackError::OutOfBounds`] instead of faulting on the guard pages around it.
+///
+/// # Example
+/// ```ignore
+/// #[skyline::hook(offset = 0x1234, inline)]
+/// fn my_hook(ctx: &mut InlineCtx) {
+///     let stack = ctx.stack().unwrap();
+///     let spilled_arg: u64 = stack.read(0x10).unwrap();
+///     println!("{}", stack.hexdump(0x40, 0x100));
+/// }
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct StackView {
+    sp: u64,
+    start: u64,
+    end: u64,
+}
+
+impl StackView {
+    /// Creates a view of the stack containing the provided stack pointer
+    pub fn new(sp: u64) -> Result<Self, StackError> {
+        if let Some((start, end)) = crate::nx::get_current_stack_bounds() {
+            if (start..end).contains(&sp) {
+                return Ok(Self { sp, start, end });
+            }
+        }
+
+        let info = crate::nx::query_memory(sp).map_err(|e| StackError::QueryFailed(sp, e))?;
+        if info.perm & PERM_RW != PERM_RW {
+            return Err(StackError::NotStackMemory(sp));
+        }
+
+        Ok(Self {
+            sp,
+            start: info.addr,
+            end: info.addr + info.size,
+        })
+    }
+
+    /// Creates a view of a stack with known bounds
+    ///
+    /// # Safety
+    /// The whole of `start..end` has to be readable memory
+    pub unsafe fn from_bounds(sp: u64, start: u64, end: u64) -> Self {
+        Self { sp, start, end }
+    }
+
+    /// Creates a view of the current thread's stack, as of the location this is called from
+    #[inline(always)]
+    pub fn current() -> Result<Self, StackError> {
+        let sp: u64;
+        unsafe {
+            std::arch::asm!("mov {}, sp", out(reg) sp);
+        }
+        Self::new(sp)
+    }
+
+    /// Creates a view of the stack that an inline hook was called on
+    pub fn from_inline_ctx(ctx: &InlineCtx) -> Result<Self, StackError> {
+        Self::new(ctx.sp.x())
+    }
+
+    /// Gets the stack pointer that offsets are relative to
+    pub fn sp(&self) -> u64 {
+        self.sp
+    }
+
+    /// Gets the lowest address of the stack
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Gets the address just past the top of the stack
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Gets the number of bytes in use above the stack pointer
+    pub fn used(&self) -> u64 {
+        self.end.saturating_sub(self.sp)
+    }
+
+    /// Gets the number of bytes left below the stack pointer
+    pub fn remaining(&self) -> u64 {
+        self.sp.saturating_sub(self.start)
+    }
+
+    /// Checks whether `size` bytes starting at `address` are inside of the stack
+    pub fn contains(&self, address: u64, size: usize) -> bool {
+        address >= self.start && address.checked_add(size as u64).is_some_and(|end| end <= self.end)
+    }
+
+    /// Reads a value at an offset from the stack pointer
+    pub fn read<T: Copy>(&self, offset: isize) -> Result<T, StackError> {
+        self.read_at(self.sp.wrapping_add(offset as u64))
+    }
+
+    /// Reads a value at an address on the stack
+    pub fn read_at<T: Copy>(&self, address: u64) -> Result<T, StackError> {
+        let size = std::mem::size_of::<T>();
+        if !self.contains(address, size) {
+            return Err(StackError::OutOfBounds { address, size });
+        }
+
+        unsafe { Ok(std::ptr::read_unaligned(address as *const T)) }
+    }
+
+    /// Gets the bytes at an offset from the stack pointer
+    pub fn bytes(&self, offset: isize, size: usize) -> Result<&[u8], StackError> {
+        let address = self.sp.wrapping_add(offset as u64);
+        if !self.contains(address, size) {
+            return Err(StackError::OutOfBounds { address, size });
+        }
+
+        unsafe { Ok(std::slice::from_raw_parts(address as *const u8, size)) }
+    }
+
+    /// Writes a hexdump of the stack around the stack pointer, clamped to the stack's bounds
+    ///
+    /// # Arguments
+    /// * `out` - The sink to write the hexdump to
+    /// * `below` - The number of bytes to include below the stack pointer
+    /// * `above` - The number of bytes to include from the stack pointer upwards
+    pub fn write_hexdump(&self, out: &mut dyn fmt::Write, below: u64, above: u64) -> fmt::Result {
+        let start = self.sp.saturating_sub(below).max(self.start) & !(HEXDUMP_LINE - 1);
+        let end = self.sp.saturating_add(above).min(self.end);
+
+        let mut line = start;
+        while line < end {
+            let bytes = unsafe {
+                std::slice::from_raw_parts(line as *const u8, HEXDUMP_LINE.min(end - line) as usize)
+            };
+
+            write!(out, "{:016x}:", line)?;
+            for idx in 0..HEXDUMP_LINE as usize {
+                match bytes.get(idx) {
+                    Some(byte) => write!(out, " {:02x}", byte)?,
+                    None => write!(out, "   ")?,
+                }
+            }
+
+            write!(out, "  |")?;
+            for &byte in bytes {
+                out.write_char(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })?;
+            }
+            write!(out, "|")?;
+
+            if (line..line + HEXDUMP_LINE).contains(&self.sp) {
+                write!(out, " <- sp + {:#x}", self.sp - line)?;
+            }
+            writeln!(out)?;
+
+            line += HEXDUMP_LINE;
+        }
+
+        Ok(())
+    }
+
+    /// Formats a hexdump of the stack around the stack pointer, see [`StackView::write_hexdump`]
+    pub fn hexdump(&self, below: u64, above: u64) -> String {
+        let mut out = String::new();
+        let _ = self.write_hexdump(&mut out, below, above);
+        out
+    }
+
+    /// Scans the stack upwards from the stack pointer for values which look like return addresses.
+    ///
+    /// A value is accepted if it points into the executable memory of a loaded module and the
+    /// instruction before it is a `bl` or `blr`. This can recover a backtrace when the frame
+    /// pointer chain is broken, but stale return addresses from earlier calls will show up too.
+    pub fn scan_return_addresses(&self) -> ReturnAddressScan {
+        ReturnAddressScan {
+            stack: *self,
+            address: (self.sp + 7) & !7,
+            executable: None,
+        }
+    }
+}
+
+/// A value on the stack which looks like a return address, see [`StackView::scan_return_addresses`]
+#[derive(Debug, Copy, Clone)]
+pub struct StackCandidate {
+    /// The address on the stack that the value was found at
+    pub location: u64,
+
+    /// The frame that the value returns into, which has no frame pointer
+    pub frame: Frame,
+}
+
+/// An iterator over the values on a stack which look like return addresses
+pub struct ReturnAddressScan {
+    stack: StackView,
+    address: u64,
+
+    // The last executable region that was found, which saves a query for most candidates
+    executable: Option<(u64, u64)>,
+}
+
+impl ReturnAddressScan {
+    fn is_executable(&mut self, address: u64) -> bool {
+        if let Some((start, end)) = self.executable {
+            if (start..end).contains(&address) {
+                return true;
+            }
+        }
+
+        match crate::nx::query_memory(address) {
+            Ok(info) if info.perm & PERM_X != 0 => {
+                self.executable = Some((info.addr, info.addr + info.size));
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn is_return_address(&mut self, value: u64) -> bool {
+        if value & 3 != 0 || value < 4 || crate::rtld::find_module_for_address(value).is_none() {
+            return false;
+        }
+
+        if !self.is_executable(value - 4) {
+            return false;
+        }
+
+        let instruction = unsafe { *((value - 4) as *const u32) };
+        let is_bl = instruction & 0xFC00_0000 == 0x9400_0000;
+        let is_blr = instruction & 0xFFFF_FC1F == 0xD63F_0000;
+        is_bl || is_blr
+    }
+}
+
+impl Iterator for ReturnAddressScan {
+    type Item = StackCandidate;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Ok(value) = self.stack.read_at::<u64>(self.address) {
+            let location = self.address;
+            self.address += 8;
+
+            if self.is_return_address(value) {
+                return Some(StackCandidate {
+                    location,
+                    frame: Frame::resolve(value, 0),
+                });
+            }
+        }
+
+        None
+    }
+}
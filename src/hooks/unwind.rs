@@ -0,0 +1,985 @@
+//! A stack unwinder which uses the call frame information (CFI) in each module's `.eh_frame`
+//! section, falling back on walking the frame pointer chain when there is none.
+//!
+//! Every module has a `MOD0` header which points at its `.eh_frame_hdr`, which contains
+//! a sorted table of every function and its frame description entry (FDE). The FDE describes,
+//! for every instruction in the function, where the caller's registers have been saved and how
+//! to find the caller's stack pointer (the canonical frame address, or CFA).
+use thiserror::Error;
+
+use crate::rtld::{ModuleHeader, ModuleObject};
+
+use super::contexts::InlineCtx;
+use super::stack::{StackError, StackView};
+
+/// The DWARF register number of the stack pointer on Aarch64
+const DWARF_SP: u16 = 31;
+
+/// The maximum depth of `DW_CFA_remember_state` that is supported
+const MAX_REMEMBERED_STATES: usize = 8;
+
+/// The permission bit of a memory region which can be read
+const PERM_R: u32 = 0b001;
+
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnwindError {
+    #[error("The address {0:#x} is not inside of any loaded module")]
+    NoModule(u64),
+
+    #[error("The module containing {0:#x} does not have unwind information")]
+    NoUnwindInfo(u64),
+
+    #[error("There is no frame description entry for {0:#x}")]
+    NoFde(u64),
+
+    #[error("The unwind information uses an unsupported pointer encoding ({0:#x})")]
+    UnsupportedEncoding(u8),
+
+    #[error("The unwind information uses an unsupported call frame instruction ({0:#x})")]
+    UnsupportedInstruction(u8),
+
+    #[error("The unwind information is malformed")]
+    Malformed,
+
+    #[error("The frame pointer is null")]
+    NullFramePointer,
+
+    #[error("The stack pointer did not move towards the caller")]
+    StackNotAdvancing,
+
+    #[error("Failed to read a saved register: {0}")]
+    Stack(#[from] StackError),
+
+    #[error("The unwind information points at unreadable memory ({0:#x})")]
+    InvalidPointer(u64),
+
+    #[error("Reached the end of the call stack")]
+    EndOfStack,
+}
+
+/// The registers that are tracked while unwinding the stack
+#[derive(Debug, Copy, Clone)]
+pub struct UnwindRegisters {
+    /// The general purpose registers (x0-x30)
+    pub registers: [u64; 31],
+
+    /// The stack pointer
+    pub sp: u64,
+
+    /// The program counter
+    pub pc: u64,
+
+    /// Whether the program counter is a return address, in which case the call instruction
+    /// is the one before it. This matters for calls to functions which never return,
+    /// where the return address might be outside of the calling function.
+    pc_is_return_address: bool,
+}
+
+impl UnwindRegisters {
+    /// Creates the unwinding state for a specific location
+    ///
+    /// # Arguments
+    /// * `registers` - The general purpose registers (x0-x30)
+    /// * `sp` - The stack pointer
+    /// * `pc` - The address of the instruction that is being executed
+    pub fn new(registers: [u64; 31], sp: u64, pc: u64) -> Self {
+        Self {
+            registers,
+            sp,
+            pc,
+            pc_is_return_address: false,
+        }
+    }
+
+    /// Creates the unwinding state from the context of an inline hook
+    ///
+    /// If the context doesn't know where the hook is, the link register is used
+    /// as the starting point instead.
+    pub fn from_inline_ctx(ctx: &InlineCtx) -> Self {
+        let mut registers = [0u64; 31];
+        for (dst, src) in registers.iter_mut().zip(ctx.registers.iter()) {
+            *dst = src.x();
+        }
+
+        if ctx.pc != 0 {
+            Self::new(registers, ctx.sp.x(), ctx.pc)
+        } else {
+            Self {
+                registers,
+                sp: ctx.sp.x(),
+                pc: ctx.lr().x(),
+                pc_is_return_address: true,
+            }
+        }
+    }
+
+    /// Creates the unwinding state for the location this is called from
+    #[inline(always)]
+    pub fn current() -> Self {
+        let mut registers = [0u64; 31];
+        let sp: u64;
+        let pc: u64;
+
+        // Only the callee-saved registers (and the frame/link registers) mean anything
+        // to the caller, so those are the only ones we need to capture
+        unsafe {
+            std::arch::asm!(r#"
+                stp x19, x20, [{regs}, #0x98]
+                stp x21, x22, [{regs}, #0xA8]
+                stp x23, x24, [{regs}, #0xB8]
+                stp x25, x26, [{regs}, #0xC8]
+                stp x27, x28, [{regs}, #0xD8]
+                stp x29, x30, [{regs}, #0xE8]
+                mov {sp}, sp
+                adr {pc}, .
+            "#,
+                regs = in(reg) registers.as_mut_ptr(),
+                sp = out(reg) sp,
+                pc = out(reg) pc,
+            );
+        }
+
+        Self::new(registers, sp, pc)
+    }
+
+    /// Gets the frame pointer (x29)
+    pub fn fp(&self) -> u64 {
+        self.registers[29]
+    }
+
+    /// Gets the link register (x30)
+    pub fn lr(&self) -> u64 {
+        self.registers[30]
+    }
+
+    /// Unwinds to the caller's registers using the call frame information
+    ///
+    /// Saved registers are only read from inside of the provided stack.
+    pub fn unwind_cfi(&self, stack: &StackView) -> Result<Self, UnwindError> {
+        let lookup_pc = if self.pc_is_return_address {
+            self.pc.checked_sub(1).ok_or(UnwindError::EndOfStack)?
+        } else {
+            self.pc
+        };
+
+        let fde = find_fde(lookup_pc)?;
+        let rules = fde.evaluate(lookup_pc)?;
+        rules.apply(self, stack)
+    }
+
+    /// Unwinds to the caller's registers by following the frame pointer chain
+    ///
+    /// This only recovers the frame pointer, link register, and stack pointer, and it
+    /// gets leaf functions (and functions that don't use a frame pointer) wrong.
+    /// The frame record is only read from inside of the provided stack.
+    pub fn unwind_fp(&self, stack: &StackView) -> Result<Self, UnwindError> {
+        let fp = self.fp();
+        if fp == 0 {
+            return Err(UnwindError::NullFramePointer);
+        }
+
+        let previous_fp = stack.read_at::<u64>(fp)?;
+        let return_address = stack.read_at::<u64>(fp.wrapping_add(8))?;
+
+        let mut caller = *self;
+        caller.registers[29] = previous_fp;
+        caller.registers[30] = return_address;
+        caller.sp = fp + 0x10;
+        caller.pc = return_address;
+        caller.pc_is_return_address = true;
+        Ok(caller)
+    }
+
+    /// Unwinds to the caller's registers, using the call frame information if there is any
+    /// and falling back on the frame pointer chain if there isn't
+    ///
+    /// This is [`UnwindRegisters::unwind_on`] with the stack that the stack pointer is on.
+    pub fn unwind(&self) -> Result<Self, UnwindError> {
+        if self.pc == 0 {
+            return Err(UnwindError::EndOfStack);
+        }
+
+        self.unwind_on(&StackView::new(self.sp)?)
+    }
+
+    /// Unwinds to the caller's registers, using the call frame information if there is any
+    /// and falling back on the frame pointer chain if there isn't
+    ///
+    /// Saved registers are only read from inside of the provided stack, and unwinding stops
+    /// instead of faulting if the unwind information or a frame pointer leads anywhere else.
+    pub fn unwind_on(&self, stack: &StackView) -> Result<Self, UnwindError> {
+        if self.pc == 0 {
+            return Err(UnwindError::EndOfStack);
+        }
+
+        let caller = match self.unwind_cfi(stack) {
+            Ok(caller) => caller,
+            Err(e @ (UnwindError::EndOfStack | UnwindError::Stack(_) | UnwindError::InvalidPointer(_))) => return Err(e),
+            Err(_) => self.unwind_fp(stack)?,
+        };
+
+        if caller.pc == 0 {
+            return Err(UnwindError::EndOfStack);
+        }
+
+        // The stack grows down, so the caller's stack pointer has to be higher than ours,
+        // unless we are a leaf function that hasn't touched the stack
+        if caller.sp < self.sp || (caller.sp == self.sp && caller.pc == self.pc) {
+            return Err(UnwindError::StackNotAdvancing);
+        }
+
+        Ok(caller)
+    }
+}
+
+/// Reads values out of the unwind tables
+struct Reader {
+    address: u64,
+}
+
+impl Reader {
+    fn new(address: u64) -> Self {
+        Self { address }
+    }
+
+    fn read<T: Copy>(&mut self) -> T {
+        let value = unsafe { std::ptr::read_unaligned(self.address as *const T) };
+        self.address += std::mem::size_of::<T>() as u64;
+        value
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.read()
+    }
+
+    fn uleb128(&mut self) -> u64 {
+        let mut result = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8();
+            if shift < 64 {
+                result |= ((byte & 0x7F) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return result;
+            }
+        }
+    }
+
+    fn sleb128(&mut self) -> i64 {
+        let mut result = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8();
+            if shift < 64 {
+                result |= ((byte & 0x7F) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1i64 << shift;
+                }
+                return result;
+            }
+        }
+    }
+
+    /// Reads a pointer encoded with one of the `DW_EH_PE_*` encodings
+    ///
+    /// # Arguments
+    /// * `encoding` - The encoding of the pointer
+    /// * `data_base` - The address that `DW_EH_PE_datarel` pointers are relative to
+    fn encoded(&mut self, encoding: u8, data_base: u64) -> Result<u64, UnwindError> {
+        let start = self.address;
+
+        let value = match encoding & 0x0F {
+            0x00 | 0x04 | 0x0C => self.read::<u64>(),
+            0x01 => self.uleb128(),
+            0x02 => self.read::<u16>() as u64,
+            0x03 => self.read::<u32>() as u64,
+            0x09 => self.sleb128() as u64,
+            0x0A => self.read::<i16>() as u64,
+            0x0B => self.read::<i32>() as u64,
+            _ => return Err(UnwindError::UnsupportedEncoding(encoding)),
+        };
+
+        let value = match encoding & 0x70 {
+            0x00 => value,
+            0x10 => start.wrapping_add(value),
+            0x30 => data_base.wrapping_add(value),
+            _ => return Err(UnwindError::UnsupportedEncoding(encoding)),
+        };
+
+        if encoding & 0x80 != 0 {
+            read_indirect(value)
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+/// Reads a `DW_EH_PE_indirect` pointer, checking that it is in readable memory first
+fn read_indirect(address: u64) -> Result<u64, UnwindError> {
+    let info = crate::nx::query_memory(address).map_err(|_| UnwindError::InvalidPointer(address))?;
+    let readable = info.perm & PERM_R != 0
+        && address.checked_add(8).is_some_and(|end| end <= info.addr.saturating_add(info.size));
+    if !readable {
+        return Err(UnwindError::InvalidPointer(address));
+    }
+
+    Ok(unsafe { std::ptr::read_unaligned(address as *const u64) })
+}
+
+/// The rule to recover a register in the caller's frame
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Rule {
+    /// The register has not been modified
+    SameValue,
+
+    /// The register can't be recovered
+    Undefined,
+
+    /// The register has been saved at `CFA + offset`
+    Offset(i64),
+
+    /// The register's value is `CFA + offset`
+    ValOffset(i64),
+
+    /// The register has been copied into another register
+    Register(u16),
+}
+
+/// The rules to recover the caller's frame at a specific instruction
+#[derive(Debug, Copy, Clone)]
+struct FrameRules {
+    cfa_register: u16,
+    cfa_offset: i64,
+    registers: [Rule; 32],
+    return_address_register: u16,
+}
+
+impl FrameRules {
+    fn get_register(regs: &UnwindRegisters, register: u16) -> Result<u64, UnwindError> {
+        match register {
+            0..=30 => Ok(regs.registers[register as usize]),
+            DWARF_SP => Ok(regs.sp),
+            _ => Err(UnwindError::Malformed),
+        }
+    }
+
+    fn apply(&self, regs: &UnwindRegisters, stack: &StackView) -> Result<UnwindRegisters, UnwindError> {
+        let cfa = Self::get_register(regs, self.cfa_register)?.wrapping_add(self.cfa_offset as u64);
+        if cfa == 0 || cfa % 8 != 0 {
+            return Err(UnwindError::Malformed);
+        }
+
+        let mut caller = *regs;
+        caller.sp = cfa;
+
+        for (idx, rule) in self.registers.iter().enumerate().take(31) {
+            caller.registers[idx] = match *rule {
+                Rule::SameValue => regs.registers[idx],
+                Rule::Undefined if idx == self.return_address_register as usize => return Err(UnwindError::EndOfStack),
+                Rule::Undefined => regs.registers[idx],
+                Rule::Offset(offset) => stack.read_at(cfa.wrapping_add(offset as u64))?,
+                Rule::ValOffset(offset) => cfa.wrapping_add(offset as u64),
+                Rule::Register(register) => Self::get_register(regs, register)?,
+            };
+        }
+
+        caller.pc = Self::get_register(&caller, self.return_address_register)?;
+        caller.pc_is_return_address = true;
+        Ok(caller)
+    }
+}
+
+/// A parsed common information entry, which holds the information shared between FDEs
+struct Cie {
+    code_alignment: u64,
+    data_alignment: i64,
+    return_address_register: u16,
+    fde_encoding: u8,
+    has_augmentation_data: bool,
+    instructions: u64,
+    instructions_end: u64,
+}
+
+impl Cie {
+    fn parse(address: u64, data_base: u64) -> Result<Self, UnwindError> {
+        let mut reader = Reader::new(address);
+        let length = reader.read::<u32>() as u64;
+        if length == 0 || length == 0xFFFF_FFFF {
+            return Err(UnwindError::Malformed);
+        }
+        let end = reader.address + length;
+
+        if reader.read::<u32>() != 0 {
+            return Err(UnwindError::Malformed);
+        }
+
+        let version = reader.u8();
+
+        let augmentation_start = reader.address;
+        while reader.u8() != 0 {}
+        let augmentation = unsafe {
+            std::slice::from_raw_parts(augmentation_start as *const u8, (reader.address - augmentation_start - 1) as usize)
+        };
+
+        let code_alignment = reader.uleb128();
+        let data_alignment = reader.sleb128();
+        let return_address_register = if version == 1 {
+            reader.u8() as u16
+        } else {
+            reader.uleb128() as u16
+        };
+
+        let mut fde_encoding = 0;
+        let has_augmentation_data = augmentation.first() == Some(&b'z');
+        if has_augmentation_data {
+            let data_length = reader.uleb128();
+            let data_end = reader.address + data_length;
+            for c in augmentation.iter().skip(1) {
+                match c {
+                    b'R' => fde_encoding = reader.u8(),
+                    b'P' => {
+                        let encoding = reader.u8();
+                        reader.encoded(encoding, data_base)?;
+                    },
+                    b'L' => {
+                        reader.u8();
+                    },
+                    _ => break,
+                }
+            }
+            reader.address = data_end;
+        }
+
+        Ok(Self {
+            code_alignment,
+            data_alignment,
+            return_address_register,
+            fde_encoding,
+            has_augmentation_data,
+            instructions: reader.address,
+            instructions_end: end,
+        })
+    }
+}
+
+/// A parsed frame description entry, which describes how to unwind a single function
+struct Fde {
+    cie: Cie,
+    pc_begin: u64,
+    pc_end: u64,
+    instructions: u64,
+    instructions_end: u64,
+    data_base: u64,
+}
+
+impl Fde {
+    fn parse(address: u64, data_base: u64) -> Result<Self, UnwindError> {
+        let mut reader = Reader::new(address);
+        let length = reader.read::<u32>() as u64;
+        if length == 0 || length == 0xFFFF_FFFF {
+            return Err(UnwindError::Malformed);
+        }
+        let end = reader.address + length;
+
+        // The CIE pointer is relative to itself
+        let cie_pointer_address = reader.address;
+        let cie_pointer = reader.read::<u32>() as u64;
+        if cie_pointer == 0 {
+            return Err(UnwindError::Malformed);
+        }
+
+        let cie = Cie::parse(cie_pointer_address - cie_pointer, data_base)?;
+        let pc_begin = reader.encoded(cie.fde_encoding, data_base)?;
+        let pc_range = reader.encoded(cie.fde_encoding & 0x0F, data_base)?;
+
+        if cie.has_augmentation_data {
+            let data_length = reader.uleb128();
+            reader.address += data_length;
+        }
+
+        Ok(Self {
+            cie,
+            pc_begin,
+            pc_end: pc_begin + pc_range,
+            instructions: reader.address,
+            instructions_end: end,
+            data_base,
+        })
+    }
+
+    /// Runs the call frame instructions of the CIE and then this FDE up to the provided address
+    fn evaluate(&self, pc: u64) -> Result<FrameRules, UnwindError> {
+        let mut rules = FrameRules {
+            cfa_register: DWARF_SP,
+            cfa_offset: 0,
+            registers: [Rule::SameValue; 32],
+            return_address_register: self.cie.return_address_register,
+        };
+
+        self.execute(&mut rules, None, self.cie.instructions, self.cie.instructions_end, u64::MAX)?;
+        let initial = rules;
+        self.execute(&mut rules, Some(&initial), self.instructions, self.instructions_end, pc)?;
+        Ok(rules)
+    }
+
+    fn execute(
+        &self,
+        rules: &mut FrameRules,
+        initial: Option<&FrameRules>,
+        start: u64,
+        end: u64,
+        pc: u64
+    ) -> Result<(), UnwindError> {
+        let mut reader = Reader::new(start);
+        let mut location = self.pc_begin;
+        let mut remembered = [*rules; MAX_REMEMBERED_STATES];
+        let mut remembered_count = 0;
+
+        let code_alignment = self.cie.code_alignment;
+        let data_alignment = self.cie.data_alignment;
+
+        let set_rule = |rules: &mut FrameRules, register: u64, rule: Rule| {
+            if let Some(slot) = rules.registers.get_mut(register as usize) {
+                *slot = rule;
+            }
+        };
+
+        let restore_rule = |rules: &mut FrameRules, register: u64| {
+            if let (Some(initial), Some(slot)) = (initial, rules.registers.get_mut(register as usize)) {
+                *slot = initial.registers[register as usize];
+            }
+        };
+
+        while reader.address < end {
+            let opcode = reader.u8();
+            let operand = (opcode & 0x3F) as u64;
+
+            match opcode >> 6 {
+                // DW_CFA_advance_loc
+                0x1 => location += operand * code_alignment,
+                // DW_CFA_offset
+                0x2 => {
+                    let offset = reader.uleb128() as i64 * data_alignment;
+                    set_rule(rules, operand, Rule::Offset(offset));
+                },
+                // DW_CFA_restore
+                0x3 => restore_rule(rules, operand),
+                _ => match opcode {
+                    // DW_CFA_nop
+                    0x00 => {},
+                    // DW_CFA_set_loc
+                    0x01 => location = reader.encoded(self.cie.fde_encoding, self.data_base)?,
+                    // DW_CFA_advance_loc1, DW_CFA_advance_loc2, DW_CFA_advance_loc4
+                    0x02 => location += reader.u8() as u64 * code_alignment,
+                    0x03 => location += reader.read::<u16>() as u64 * code_alignment,
+                    0x04 => location += reader.read::<u32>() as u64 * code_alignment,
+                    // DW_CFA_offset_extended
+                    0x05 => {
+                        let register = reader.uleb128();
+                        let offset = reader.uleb128() as i64 * data_alignment;
+                        set_rule(rules, register, Rule::Offset(offset));
+                    },
+                    // DW_CFA_restore_extended
+                    0x06 => {
+                        let register = reader.uleb128();
+                        restore_rule(rules, register);
+                    },
+                    // DW_CFA_undefined
+                    0x07 => {
+                        let register = reader.uleb128();
+                        set_rule(rules, register, Rule::Undefined);
+                    },
+                    // DW_CFA_same_value
+                    0x08 => {
+                        let register = reader.uleb128();
+                        set_rule(rules, register, Rule::SameValue);
+                    },
+                    // DW_CFA_register
+                    0x09 => {
+                        let register = reader.uleb128();
+                        let other = reader.uleb128() as u16;
+                        set_rule(rules, register, Rule::Register(other));
+                    },
+                    // DW_CFA_remember_state
+                    0x0A => {
+                        let slot = remembered.get_mut(remembered_count).ok_or(UnwindError::Malformed)?;
+                        *slot = *rules;
+                        remembered_count += 1;
+                    },
+                    // DW_CFA_restore_state
+                    0x0B => {
+                        remembered_count = remembered_count.checked_sub(1).ok_or(UnwindError::Malformed)?;
+                        *rules = remembered[remembered_count];
+                    },
+                    // DW_CFA_def_cfa
+                    0x0C => {
+                        rules.cfa_register = reader.uleb128() as u16;
+                        rules.cfa_offset = reader.uleb128() as i64;
+                    },
+                    // DW_CFA_def_cfa_register
+                    0x0D => rules.cfa_register = reader.uleb128() as u16,
+                    // DW_CFA_def_cfa_offset
+                    0x0E => rules.cfa_offset = reader.uleb128() as i64,
+                    // DW_CFA_expression and DW_CFA_val_expression, the register can't be
+                    // recovered without a DWARF expression evaluator
+                    0x10 | 0x16 => {
+                        let register = reader.uleb128();
+                        let length = reader.uleb128();
+                        reader.address += length;
+                        set_rule(rules, register, Rule::Undefined);
+                    },
+                    // DW_CFA_offset_extended_sf
+                    0x11 => {
+                        let register = reader.uleb128();
+                        let offset = reader.sleb128() * data_alignment;
+                        set_rule(rules, register, Rule::Offset(offset));
+                    },
+                    // DW_CFA_def_cfa_sf
+                    0x12 => {
+                        rules.cfa_register = reader.uleb128() as u16;
+                        rules.cfa_offset = reader.sleb128() * data_alignment;
+                    },
+                    // DW_CFA_def_cfa_offset_sf
+                    0x13 => rules.cfa_offset = reader.sleb128() * data_alignment,
+                    // DW_CFA_val_offset
+                    0x14 => {
+                        let register = reader.uleb128();
+                        let offset = reader.uleb128() as i64 * data_alignment;
+                        set_rule(rules, register, Rule::ValOffset(offset));
+                    },
+                    // DW_CFA_val_offset_sf
+                    0x15 => {
+                        let register = reader.uleb128();
+                        let offset = reader.sleb128() * data_alignment;
+                        set_rule(rules, register, Rule::ValOffset(offset));
+                    },
+                    // DW_CFA_AARCH64_negate_ra_state, pointer authentication is not used on Horizon
+                    0x2D => {},
+                    // DW_CFA_GNU_args_size
+                    0x2E => {
+                        reader.uleb128();
+                    },
+                    // DW_CFA_GNU_negative_offset_extended
+                    0x2F => {
+                        let register = reader.uleb128();
+                        let offset = -(reader.uleb128() as i64) * data_alignment;
+                        set_rule(rules, register, Rule::Offset(offset));
+                    },
+                    // DW_CFA_def_cfa_expression, and anything else we don't know about
+                    _ => return Err(UnwindError::UnsupportedInstruction(opcode)),
+                }
+            }
+
+            // The rules only apply up to the instruction we are unwinding from
+            if location > pc {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets the `.eh_frame_hdr` section of a module, if it has one
+fn get_eh_frame_hdr(module: &ModuleObject) -> Option<u64> {
+    unsafe {
+        let base = module.module_base as u64;
+        let header = (base + *(base as *const u32).add(1) as u64) as *const ModuleHeader;
+        if (*header).magic != ModuleHeader::MOD0_MAGIC {
+            return None;
+        }
+
+        if (*header).unwind_start_offset == (*header).unwind_end_offset {
+            return None;
+        }
+
+        Some(header as u64 + (*header).unwind_start_offset as u64)
+    }
+}
+
+/// Finds the frame description entry for an address by binary searching the `.eh_frame_hdr` table
+fn find_fde(pc: u64) -> Result<Fde, UnwindError> {
+    let module = crate::rtld::find_module_for_address(pc).ok_or(UnwindError::NoModule(pc))?;
+    let eh_frame_hdr = get_eh_frame_hdr(module).ok_or(UnwindError::NoUnwindInfo(pc))?;
+
+    let mut reader = Reader::new(eh_frame_hdr);
+    let version = reader.u8();
+    let eh_frame_ptr_encoding = reader.u8();
+    let fde_count_encoding = reader.u8();
+    let table_encoding = reader.u8();
+
+    if version != 1 {
+        return Err(UnwindError::Malformed);
+    }
+
+    reader.encoded(eh_frame_ptr_encoding, eh_frame_hdr)?;
+    let fde_count = reader.encoded(fde_count_encoding, eh_frame_hdr)?;
+
+    // The table is only searchable if every entry is the same size, which in practice
+    // is always a pair of 4-byte offsets from the start of the header
+    if table_encoding != 0x3B {
+        return Err(UnwindError::UnsupportedEncoding(table_encoding));
+    }
+
+    let table = reader.address;
+    let entry = |index: u64| -> (u64, u64) {
+        let mut reader = Reader::new(table + index * 8);
+        let location = eh_frame_hdr.wrapping_add(reader.read::<i32>() as u64);
+        let fde = eh_frame_hdr.wrapping_add(reader.read::<i32>() as u64);
+        (location, fde)
+    };
+
+    // Find the last entry which starts at or before the address
+    let (mut low, mut high) = (0, fde_count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if entry(mid).0 <= pc {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    if low == 0 {
+        return Err(UnwindError::NoFde(pc));
+    }
+
+    let fde = Fde::parse(entry(low - 1).1, eh_frame_hdr)?;
+    if fde.pc_begin <= pc && pc < fde.pc_end {
+        Ok(fde)
+    } else {
+        Err(UnwindError::NoFde(pc))
+    }
+}
+
+#[cfg(all(test, target_arch = "aarch64"))]
+mod tests {
+    use super::*;
+
+    const FUNCTION: u64 = 0x7100_0000;
+    const LR: u64 = 0x7100_1234;
+
+    /// Builds an `.eh_frame` with a single CIE and an FDE for `FUNCTION`, returning the FDE's offset
+    ///
+    /// The CIE uses absolute 8-byte pointers, a code alignment of 4 and a data alignment of -8,
+    /// like what LLVM emits for Aarch64 apart from the pointer encoding.
+    fn build_eh_frame(fde_instructions: &[u8]) -> (Vec<u8>, usize) {
+        let mut cie = vec![
+            0, 0, 0, 0,         // CIE id
+            1,                  // version
+            b'z', b'R', 0,      // augmentation
+            4,                  // code alignment
+            0x78,               // data alignment (-8)
+            30,                 // return address register
+            1, 0x00,            // augmentation data, DW_EH_PE_absptr
+            0x0C, 31, 0,        // DW_CFA_def_cfa sp, 0
+        ];
+        while (cie.len() + 4) % 8 != 0 {
+            cie.push(0); // DW_CFA_nop
+        }
+
+        let mut eh_frame = Vec::new();
+        eh_frame.extend_from_slice(&(cie.len() as u32).to_le_bytes());
+        eh_frame.extend_from_slice(&cie);
+
+        let fde_offset = eh_frame.len();
+        let mut fde = Vec::new();
+        fde.extend_from_slice(&(fde_offset as u32 + 4).to_le_bytes()); // CIE pointer
+        fde.extend_from_slice(&FUNCTION.to_le_bytes());
+        fde.extend_from_slice(&0x100u64.to_le_bytes());
+        fde.push(0); // augmentation data
+        fde.extend_from_slice(fde_instructions);
+        while (fde.len() + 4) % 8 != 0 {
+            fde.push(0);
+        }
+
+        eh_frame.extend_from_slice(&(fde.len() as u32).to_le_bytes());
+        eh_frame.extend_from_slice(&fde);
+        (eh_frame, fde_offset)
+    }
+
+    /// The CFI of a function with a frame record:
+    /// ```text
+    /// sub sp, sp, #0x20
+    /// stp x29, x30, [sp, #0x10]
+    /// add x29, sp, #0x10
+    /// ```
+    const PROLOGUE: &[u8] = &[
+        0x41,               // DW_CFA_advance_loc 1
+        0x0E, 0x20,         // DW_CFA_def_cfa_offset 0x20
+        0x41,               // DW_CFA_advance_loc 1
+        0x9E, 1,            // DW_CFA_offset x30, -8
+        0x9D, 2,            // DW_CFA_offset x29, -16
+        0x41,               // DW_CFA_advance_loc 1
+        0x0C, 29, 0x10,     // DW_CFA_def_cfa x29, 0x10
+    ];
+
+    /// A fake stack of 0x80 bytes, with the stack pointer in the middle of it
+    struct FakeStack {
+        words: Box<[u64; 16]>,
+    }
+
+    impl FakeStack {
+        fn new() -> Self {
+            Self { words: Box::new([0; 16]) }
+        }
+
+        fn base(&self) -> u64 {
+            self.words.as_ptr() as u64
+        }
+
+        fn sp(&self) -> u64 {
+            self.base() + 0x40
+        }
+
+        fn set(&mut self, address: u64, value: u64) {
+            self.words[((address - self.base()) / 8) as usize] = value;
+        }
+
+        fn view(&self) -> StackView {
+            unsafe { StackView::from_bounds(self.sp(), self.base(), self.base() + 0x80) }
+        }
+
+        fn registers(&self, pc: u64) -> UnwindRegisters {
+            let mut registers = [0u64; 31];
+            registers[29] = self.sp() + 0x10;
+            registers[30] = LR;
+            UnwindRegisters::new(registers, self.sp(), pc)
+        }
+    }
+
+    fn unwind_at(eh_frame: &(Vec<u8>, usize), stack: &FakeStack, pc: u64) -> Result<UnwindRegisters, UnwindError> {
+        let fde = Fde::parse(eh_frame.0.as_ptr() as u64 + eh_frame.1 as u64, 0)?;
+        assert_eq!((fde.pc_begin, fde.pc_end), (FUNCTION, FUNCTION + 0x100));
+
+        fde.evaluate(pc)?.apply(&stack.registers(pc), &stack.view())
+    }
+
+    #[test]
+    fn function_entry() {
+        let eh_frame = build_eh_frame(PROLOGUE);
+        let stack = FakeStack::new();
+
+        let caller = unwind_at(&eh_frame, &stack, FUNCTION).unwrap();
+        assert_eq!(caller.sp, stack.sp());
+        assert_eq!(caller.pc, LR);
+        assert!(caller.pc_is_return_address);
+    }
+
+    #[test]
+    fn stack_allocated() {
+        let eh_frame = build_eh_frame(PROLOGUE);
+        let stack = FakeStack::new();
+
+        let caller = unwind_at(&eh_frame, &stack, FUNCTION + 4).unwrap();
+        assert_eq!(caller.sp, stack.sp() + 0x20);
+        assert_eq!(caller.pc, LR);
+    }
+
+    #[test]
+    fn registers_saved() {
+        let eh_frame = build_eh_frame(PROLOGUE);
+        let mut stack = FakeStack::new();
+        stack.set(stack.sp() + 0x10, 0xAAAA_0000);
+        stack.set(stack.sp() + 0x18, 0x7100_5678);
+
+        for pc in [FUNCTION + 8, FUNCTION + 0xC, FUNCTION + 0x80] {
+            let caller = unwind_at(&eh_frame, &stack, pc).unwrap();
+            assert_eq!(caller.sp, stack.sp() + 0x20, "{:#x}", pc);
+            assert_eq!(caller.pc, 0x7100_5678, "{:#x}", pc);
+            assert_eq!(caller.fp(), 0xAAAA_0000, "{:#x}", pc);
+            assert_eq!(caller.lr(), 0x7100_5678, "{:#x}", pc);
+        }
+    }
+
+    #[test]
+    fn remember_and_restore_state() {
+        let mut instructions = PROLOGUE.to_vec();
+        instructions.extend_from_slice(&[
+            0x0A,               // DW_CFA_remember_state
+            0x41,               // DW_CFA_advance_loc 1
+            0x0C, 31, 0,        // DW_CFA_def_cfa sp, 0
+            0xDE,               // DW_CFA_restore x30
+            0x41,               // DW_CFA_advance_loc 1
+            0x0B,               // DW_CFA_restore_state
+        ]);
+        let eh_frame = build_eh_frame(&instructions);
+        let mut stack = FakeStack::new();
+        stack.set(stack.sp() + 0x18, 0x7100_5678);
+
+        // The epilogue has returned the stack and the link register
+        let caller = unwind_at(&eh_frame, &stack, FUNCTION + 0x10).unwrap();
+        assert_eq!(caller.sp, stack.sp());
+        assert_eq!(caller.pc, LR);
+
+        // And the body after it is back to using the frame record
+        let caller = unwind_at(&eh_frame, &stack, FUNCTION + 0x14).unwrap();
+        assert_eq!(caller.sp, stack.sp() + 0x20);
+        assert_eq!(caller.pc, 0x7100_5678);
+    }
+
+    #[test]
+    fn undefined_return_address() {
+        let eh_frame = build_eh_frame(&[0x07, 30]); // DW_CFA_undefined x30
+        let stack = FakeStack::new();
+
+        assert_eq!(unwind_at(&eh_frame, &stack, FUNCTION).unwrap_err(), UnwindError::EndOfStack);
+    }
+
+    #[test]
+    fn saved_register_outside_of_stack() {
+        let eh_frame = build_eh_frame(&[
+            0x0E, 0x80, 0x02,   // DW_CFA_def_cfa_offset 0x100
+            0x9E, 1,            // DW_CFA_offset x30, -8
+        ]);
+        let stack = FakeStack::new();
+
+        let address = stack.sp() + 0xF8;
+        assert_eq!(
+            unwind_at(&eh_frame, &stack, FUNCTION).unwrap_err(),
+            UnwindError::Stack(StackError::OutOfBounds { address, size: 8 })
+        );
+    }
+
+    #[test]
+    fn unsupported_instruction() {
+        let eh_frame = build_eh_frame(&[0x0F, 0]); // DW_CFA_def_cfa_expression
+        let stack = FakeStack::new();
+
+        assert_eq!(unwind_at(&eh_frame, &stack, FUNCTION).unwrap_err(), UnwindError::UnsupportedInstruction(0x0F));
+    }
+
+    #[test]
+    fn frame_pointer_chain() {
+        let mut stack = FakeStack::new();
+        stack.set(stack.sp() + 0x10, stack.sp() + 0x38);
+        stack.set(stack.sp() + 0x18, 0x7100_5678);
+
+        let caller = stack.registers(FUNCTION).unwind_fp(&stack.view()).unwrap();
+        assert_eq!(caller.sp, stack.sp() + 0x20);
+        assert_eq!(caller.pc, 0x7100_5678);
+        assert_eq!(caller.fp(), stack.sp() + 0x38);
+
+        // The return address in the caller's frame record would be past the end of the stack
+        let address = stack.sp() + 0x40;
+        assert_eq!(
+            caller.unwind_fp(&stack.view()).unwrap_err(),
+            UnwindError::Stack(StackError::OutOfBounds { address, size: 8 })
+        );
+    }
+
+    #[test]
+    fn null_pc_ends_the_walk() {
+        let stack = FakeStack::new();
+        let mut registers = stack.registers(0);
+        registers.pc_is_return_address = true;
+
+        assert_eq!(registers.unwind_on(&stack.view()).unwrap_err(), UnwindError::EndOfStack);
+        assert_eq!(registers.unwind_cfi(&stack.view()).unwrap_err(), UnwindError::EndOfStack);
+    }
+}
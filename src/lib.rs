@@ -1,9 +1,13 @@
+pub mod crash;
+pub mod exception;
 pub mod hooks;
 pub mod memory;
 
 pub mod rtld;
 pub mod nx;
 
-pub use skyline_macro::{main, hook, inline_hook, legacy_inline_hook, callback, shim};
+pub use skyline_macro::{main, hook, inline_hook, legacy_inline_hook, callback, post_hook, shim};
+
+pub use crash::install_panic_hook;
 
 pub use once_cell;
\ No newline at end of file
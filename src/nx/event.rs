@@ -0,0 +1,190 @@
+//! Kernel events, and waiting on several kernel objects at once.
+//!
+//! # Example
+//! ```ignore
+//! use skyline::nx::event::{self, Waitable};
+//!
+//! let (writable, readable) = event::create().unwrap();
+//! let worker = skyline::nx::thread::spawn(move || writable.signal().unwrap()).unwrap();
+//!
+//! match event::wait_any(&[&readable, &worker], None) {
+//!     Ok(0) => println!("The event was signaled"),
+//!     Ok(_) => println!("The thread exited"),
+//!     Err(e) => println!("{}", e),
+//! }
+//! ```
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// The most handles the kernel can wait on at once
+pub const MAX_WAIT_HANDLES: usize = 0x40;
+
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WaitError {
+    #[error("The wait timed out")]
+    TimedOut,
+
+    #[error("The wait was cancelled")]
+    Cancelled,
+
+    #[error("Cannot wait on {0} handles, the limit is {MAX_WAIT_HANDLES}")]
+    TooManyHandles(usize),
+
+    #[error("Failed to wait ({0:#x})")]
+    Failed(u32),
+}
+
+impl From<u32> for WaitError {
+    fn from(result: u32) -> Self {
+        match result {
+            super::RESULT_TIMED_OUT => WaitError::TimedOut,
+            super::RESULT_CANCELLED => WaitError::Cancelled,
+            result => WaitError::Failed(result),
+        }
+    }
+}
+
+/// A kernel object which can be waited on until it is signaled
+pub trait Waitable {
+    /// Gets the kernel handle which is waited on
+    fn wait_handle(&self) -> u32;
+
+    /// Waits for the object to be signaled, for at most the timeout if there is one
+    fn wait(&self, timeout: Option<Duration>) -> Result<(), WaitError> {
+        super::wait_synchronization(&[self.wait_handle()], super::sync::timeout_nanoseconds(timeout))
+            .map(|_| ())
+            .map_err(WaitError::from)
+    }
+}
+
+impl Waitable for u32 {
+    fn wait_handle(&self) -> u32 {
+        *self
+    }
+}
+
+/// Waits until one of the objects is signaled, for at most the timeout if there is one.
+///
+/// A wait can be cancelled from another thread with [`super::cancel_synchronization`].
+///
+/// # Returns
+/// The index of the object which was signaled
+pub fn wait_any(objects: &[&dyn Waitable], timeout: Option<Duration>) -> Result<usize, WaitError> {
+    if objects.len() > MAX_WAIT_HANDLES {
+        return Err(WaitError::TooManyHandles(objects.len()));
+    }
+
+    let mut handles = [0; MAX_WAIT_HANDLES];
+    for (handle, object) in handles.iter_mut().zip(objects) {
+        *handle = object.wait_handle();
+    }
+
+    super::wait_synchronization(&handles[..objects.len()], super::sync::timeout_nanoseconds(timeout)).map_err(WaitError::from)
+}
+
+/// Creates a kernel event, which is cleared until it is signaled
+///
+/// # Returns
+/// The writable end which signals the event, and the readable end which is waited on
+pub fn create() -> Result<(WritableEvent, ReadableEvent), u32> {
+    let (writable, readable) = super::create_event()?;
+    Ok((WritableEvent { handle: writable }, ReadableEvent { handle: readable }))
+}
+
+/// The end of a kernel event which can be waited on, which closes its handle when dropped
+#[derive(Debug)]
+pub struct ReadableEvent {
+    handle: u32,
+}
+
+impl ReadableEvent {
+    /// Takes ownership of a readable event handle, such as one received through IPC
+    ///
+    /// # Safety
+    /// The handle has to be a readable event which isn't closed by anything else
+    pub unsafe fn from_raw(handle: u32) -> Self {
+        Self { handle }
+    }
+
+    /// Gives up ownership of the handle without closing it
+    pub fn into_raw(self) -> u32 {
+        let handle = self.handle;
+        std::mem::forget(self);
+        handle
+    }
+
+    /// Gets the kernel handle of the event
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Checks whether the event is signaled, without waiting for it
+    pub fn is_signaled(&self) -> bool {
+        self.wait(Some(Duration::ZERO)).is_ok()
+    }
+
+    /// Clears the event if it was signaled
+    ///
+    /// # Returns
+    /// Whether the event was signaled
+    pub fn reset(&self) -> bool {
+        super::reset_signal(self.handle).is_ok()
+    }
+}
+
+impl Waitable for ReadableEvent {
+    fn wait_handle(&self) -> u32 {
+        self.handle
+    }
+}
+
+impl Drop for ReadableEvent {
+    fn drop(&mut self) {
+        let _ = super::close_handle(self.handle);
+    }
+}
+
+/// The end of a kernel event which signals it, which closes its handle when dropped
+#[derive(Debug)]
+pub struct WritableEvent {
+    handle: u32,
+}
+
+impl WritableEvent {
+    /// Takes ownership of a writable event handle
+    ///
+    /// # Safety
+    /// The handle has to be a writable event which isn't closed by anything else
+    pub unsafe fn from_raw(handle: u32) -> Self {
+        Self { handle }
+    }
+
+    /// Gives up ownership of the handle without closing it
+    pub fn into_raw(self) -> u32 {
+        let handle = self.handle;
+        std::mem::forget(self);
+        handle
+    }
+
+    /// Gets the kernel handle of the event
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Signals the event, waking up every thread waiting on it until it is cleared
+    pub fn signal(&self) -> Result<(), u32> {
+        super::signal_event(self.handle)
+    }
+
+    /// Clears the event
+    pub fn clear(&self) -> Result<(), u32> {
+        super::clear_event(self.handle)
+    }
+}
+
+impl Drop for WritableEvent {
+    fn drop(&mut self) {
+        let _ = super::close_handle(self.handle);
+    }
+}
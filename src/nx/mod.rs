@@ -1,6 +1,26 @@
 #[cfg(not(feature = "static-module"))]
 std::arch::global_asm!(include_str!("./svc.s"));
 
+mod resource_limit;
+mod tls;
+
+pub mod event;
+pub mod sync;
+pub mod thread;
+pub mod time;
+
+pub use resource_limit::*;
+pub use tls::*;
+
+/// The pseudo-handle which refers to the current thread in SVCs
+pub const CURRENT_THREAD_HANDLE: u32 = 0xFFFF8000;
+
+/// The result of a wait which ran out of time
+pub const RESULT_TIMED_OUT: u32 = 0xEA01;
+
+/// The result of a wait which was cancelled by another thread
+pub const RESULT_CANCELLED: u32 = 0xEC01;
+
 #[repr(C)]
 pub struct MemoryInfo {
     pub addr: u64,
@@ -16,7 +36,29 @@ pub struct MemoryInfo {
 extern "C" {
     fn svcQueryMemory(memory_info: *mut MemoryInfo, page_info: &mut u32, addr: u64) -> u32;
     fn svcOutputDebugString(str: *const u8, len: usize) -> u32;
-    fn svcGetInfo(out: *mut u64, id0: u32, handle: u32, id1: u64);
+    fn svcReturnFromException(result: u32) -> !;
+    fn svcGetInfo(out: *mut u64, id0: u32, handle: u32, id1: u64) -> u32;
+    fn svcGetThreadId(out: *mut u64, handle: u32) -> u32;
+    fn svcGetThreadPriority(out: *mut u32, handle: u32) -> u32;
+    fn svcGetThreadCoreMask(out_core: *mut i32, out_mask: *mut u64, handle: u32) -> u32;
+    fn svcGetCurrentProcessorNumber() -> u32;
+    fn svcGetSystemTick() -> u64;
+    fn svcCreateThread(out: *mut u32, entry: extern "C" fn(*mut u8) -> !, arg: *mut u8, stack_top: *mut u8, priority: i32, core: i32) -> u32;
+    fn svcStartThread(handle: u32) -> u32;
+    fn svcExitThread() -> !;
+    fn svcSleepThread(nanoseconds: i64);
+    fn svcSetThreadPriority(handle: u32, priority: u32) -> u32;
+    fn svcSetThreadCoreMask(handle: u32, ideal_core: i32, affinity_mask: u64) -> u32;
+    fn svcWaitSynchronization(out_index: *mut i32, handles: *const u32, count: i32, timeout: i64) -> u32;
+    fn svcCancelSynchronization(thread_handle: u32) -> u32;
+    fn svcCreateEvent(out_writable: *mut u32, out_readable: *mut u32) -> u32;
+    fn svcSignalEvent(handle: u32) -> u32;
+    fn svcClearEvent(handle: u32) -> u32;
+    fn svcResetSignal(handle: u32) -> u32;
+    fn svcArbitrateLock(owner: u32, tag_address: *mut u32, tag: u32) -> u32;
+    fn svcArbitrateUnlock(tag_address: *mut u32) -> u32;
+    fn svcWaitProcessWideKeyAtomic(tag_address: *mut u32, key: *mut u32, tag: u32, timeout: i64) -> u32;
+    fn svcSignalProcessWideKey(key: *mut u32, count: i32);
     fn svcQueryIoMapping(out: *mut u64, out_size: *mut usize, io: u64, size: usize) -> u32;
     fn svcConnectToNamedPort(out: *mut u32, name: *const u8) -> u32;
     fn svcSendSyncRequest(handle: u32) -> u32;
@@ -63,6 +105,74 @@ pub fn output_debug_string(string: &str) -> Result<(), u32> {
     }
 }
 
+/// A [`std::fmt::Write`] which sends what is written to `svcOutputDebugString` a line at a time,
+/// through a fixed buffer so that it never allocates.
+///
+/// Lines longer than the buffer are split. Anything left in the buffer is sent when the writer
+/// is dropped.
+pub struct DebugLogWriter {
+    buffer: [u8; Self::CAPACITY],
+    len: usize,
+}
+
+impl DebugLogWriter {
+    /// The most bytes sent in one call to `svcOutputDebugString`
+    pub const CAPACITY: usize = 0x200;
+
+    /// Creates a writer with an empty buffer
+    pub const fn new() -> Self {
+        Self { buffer: [0; Self::CAPACITY], len: 0 }
+    }
+
+    /// Sends whatever is in the buffer
+    pub fn flush(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                svcOutputDebugString(self.buffer.as_ptr(), self.len);
+            }
+            self.len = 0;
+        }
+    }
+}
+
+impl Default for DebugLogWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Write for DebugLogWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        for &byte in s.as_bytes() {
+            if self.len == Self::CAPACITY {
+                self.flush();
+            }
+
+            if byte == b'\n' {
+                self.flush();
+            } else {
+                self.buffer[self.len] = byte;
+                self.len += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DebugLogWriter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Returns from a user exception handler without handling the exception, which lets the
+/// kernel terminate the process as if there had been no handler
+pub fn return_from_exception(result: u32) -> ! {
+    unsafe {
+        svcReturnFromException(result)
+    }
+}
+
 pub fn get_program_id() -> u64 {
     unsafe {
         let mut id = 0u64;
@@ -102,6 +212,248 @@ pub fn send_sync_request(handle: u32) -> Result<(), u32> {
     }
 }
 
+pub fn get_thread_id(handle: u32) -> Result<u64, u32> {
+    unsafe {
+        let mut out = 0;
+        let result = svcGetThreadId(&mut out, handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(out)
+        }
+    }
+}
+
+pub fn get_thread_priority(handle: u32) -> Result<u32, u32> {
+    unsafe {
+        let mut out = 0;
+        let result = svcGetThreadPriority(&mut out, handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(out)
+        }
+    }
+}
+
+/// Gets the ideal core and the affinity mask of a thread
+pub fn get_thread_core_mask(handle: u32) -> Result<(i32, u64), u32> {
+    unsafe {
+        let mut core = 0;
+        let mut mask = 0;
+        let result = svcGetThreadCoreMask(&mut core, &mut mask, handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok((core, mask))
+        }
+    }
+}
+
+/// Gets the number of the core that the current thread is running on
+pub fn get_current_processor_number() -> u32 {
+    unsafe {
+        svcGetCurrentProcessorNumber()
+    }
+}
+
+/// Creates a suspended thread, which starts running `entry(arg)` once [`start_thread`] is called
+///
+/// # Safety
+/// `stack_top` has to be the 16-byte aligned end of memory that stays valid until the thread exits
+pub unsafe fn create_thread(entry: extern "C" fn(*mut u8) -> !, arg: *mut u8, stack_top: *mut u8, priority: i32, core: i32) -> Result<u32, u32> {
+    let mut out = 0;
+    let result = svcCreateThread(&mut out, entry, arg, stack_top, priority, core);
+    if result != 0 {
+        Err(result)
+    } else {
+        Ok(out)
+    }
+}
+
+pub fn start_thread(handle: u32) -> Result<(), u32> {
+    unsafe {
+        let result = svcStartThread(handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Exits the current thread without running any destructors
+pub fn exit_thread() -> ! {
+    unsafe {
+        svcExitThread()
+    }
+}
+
+/// Puts the current thread to sleep, where 0, -1, and -2 instead yield to threads on the same
+/// core, yield allowing this thread to migrate, and yield to threads on any core
+pub fn sleep_thread(nanoseconds: i64) {
+    unsafe {
+        svcSleepThread(nanoseconds)
+    }
+}
+
+pub fn set_thread_priority(handle: u32, priority: u32) -> Result<(), u32> {
+    unsafe {
+        let result = svcSetThreadPriority(handle, priority);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub fn set_thread_core_mask(handle: u32, ideal_core: i32, affinity_mask: u64) -> Result<(), u32> {
+    unsafe {
+        let result = svcSetThreadCoreMask(handle, ideal_core, affinity_mask);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Waits until one of the handles is signaled, or until the timeout in nanoseconds runs out
+/// if it isn't negative
+///
+/// # Returns
+/// The index of the handle which was signaled
+pub fn wait_synchronization(handles: &[u32], timeout: i64) -> Result<usize, u32> {
+    unsafe {
+        let mut index = 0;
+        let result = svcWaitSynchronization(&mut index, handles.as_ptr(), handles.len() as i32, timeout);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(index as usize)
+        }
+    }
+}
+
+/// Makes a thread's current or next wait return early with [`RESULT_CANCELLED`]
+pub fn cancel_synchronization(thread_handle: u32) -> Result<(), u32> {
+    unsafe {
+        let result = svcCancelSynchronization(thread_handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Creates a kernel event
+///
+/// # Returns
+/// The handles of the writable and readable ends of the event
+pub fn create_event() -> Result<(u32, u32), u32> {
+    unsafe {
+        let mut writable = 0;
+        let mut readable = 0;
+        let result = svcCreateEvent(&mut writable, &mut readable);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok((writable, readable))
+        }
+    }
+}
+
+/// Signals an event through its writable handle
+pub fn signal_event(handle: u32) -> Result<(), u32> {
+    unsafe {
+        let result = svcSignalEvent(handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Clears an event through its writable handle
+pub fn clear_event(handle: u32) -> Result<(), u32> {
+    unsafe {
+        let result = svcClearEvent(handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Clears the signal of a readable event or process handle, which fails if it wasn't signaled
+pub fn reset_signal(handle: u32) -> Result<(), u32> {
+    unsafe {
+        let result = svcResetSignal(handle);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Waits until the thread owning a lock releases it to the current thread
+///
+/// # Safety
+/// `tag_address` has to point to a lock word in memory which stays valid during the wait
+pub unsafe fn arbitrate_lock(owner: u32, tag_address: *mut u32, tag: u32) -> Result<(), u32> {
+    let result = svcArbitrateLock(owner, tag_address, tag);
+    if result != 0 {
+        Err(result)
+    } else {
+        Ok(())
+    }
+}
+
+/// Releases a lock owned by the current thread to the highest priority thread waiting for it
+///
+/// # Safety
+/// `tag_address` has to point to a lock word in memory which the current thread owns
+pub unsafe fn arbitrate_unlock(tag_address: *mut u32) -> Result<(), u32> {
+    let result = svcArbitrateUnlock(tag_address);
+    if result != 0 {
+        Err(result)
+    } else {
+        Ok(())
+    }
+}
+
+/// Releases a lock owned by the current thread and waits for the key to be signaled, after
+/// which the kernel hands the lock back to the current thread
+///
+/// # Safety
+/// `tag_address` and `key` have to point to memory which stays valid during the wait
+pub unsafe fn wait_process_wide_key_atomic(tag_address: *mut u32, key: *mut u32, tag: u32, timeout: i64) -> Result<(), u32> {
+    let result = svcWaitProcessWideKeyAtomic(tag_address, key, tag, timeout);
+    if result != 0 {
+        Err(result)
+    } else {
+        Ok(())
+    }
+}
+
+/// Wakes up to `count` threads waiting for the key, or every thread if `count` is negative
+///
+/// # Safety
+/// `key` has to point to valid memory
+pub unsafe fn signal_process_wide_key(key: *mut u32, count: i32) {
+    svcSignalProcessWideKey(key, count)
+}
+
+/// Gets the system tick counter, which counts up at [`time::Tick::frequency`] ticks per second
+pub fn get_system_tick() -> u64 {
+    unsafe { svcGetSystemTick() }
+}
+
 pub fn get_tls() -> *mut u8 {
     unsafe {
         let tls_ptr: *mut u8;
@@ -122,6 +474,19 @@ pub fn create_resource_limit() -> Result<u32, u32> {
     }
 }
 
+/// Gets a new handle to the current process's resource limit, which is 0 if it has none
+pub fn get_process_resource_limit() -> Result<u32, u32> {
+    unsafe {
+        let mut out = 0u64;
+        let result = svcGetInfo(&mut out, 9, 0, 0);
+        if result != 0 {
+            Err(result)
+        } else {
+            Ok(out as u32)
+        }
+    }
+}
+
 pub fn close_handle(handle: u32) -> Result<(), u32> {
     unsafe {
         let result = svcCloseHandle(handle);
@@ -0,0 +1,201 @@
+use std::fmt;
+
+/// A kind of kernel resource which a resource limit restricts
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LimitableResource {
+    /// Bytes of physical memory
+    PhysicalMemory = 0,
+
+    /// Threads
+    Threads = 1,
+
+    /// Kernel events
+    Events = 2,
+
+    /// Transfer memory objects
+    TransferMemory = 3,
+
+    /// IPC sessions
+    Sessions = 4,
+}
+
+impl LimitableResource {
+    /// Every resource, in the order of their raw values
+    pub const ALL: [LimitableResource; 5] = [
+        Self::PhysicalMemory,
+        Self::Threads,
+        Self::Events,
+        Self::TransferMemory,
+        Self::Sessions,
+    ];
+
+    /// Converts the raw value the kernel uses into a resource
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        Self::ALL.get(raw as usize).copied()
+    }
+
+    /// Gets the raw value the kernel uses for the resource
+    pub fn as_raw(self) -> u32 {
+        self as u32
+    }
+
+    /// Gets a readable name for the resource
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PhysicalMemory => "Physical memory",
+            Self::Threads => "Threads",
+            Self::Events => "Events",
+            Self::TransferMemory => "Transfer memory",
+            Self::Sessions => "Sessions",
+        }
+    }
+}
+
+impl fmt::Display for LimitableResource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An owned handle to a kernel resource limit, which closes the handle when dropped
+#[derive(Debug)]
+pub struct ResourceLimit {
+    handle: u32,
+}
+
+impl ResourceLimit {
+    /// Creates a new resource limit, which doesn't restrict anything until its limits are set
+    pub fn new() -> Result<Self, u32> {
+        super::create_resource_limit().map(|handle| Self { handle })
+    }
+
+    /// Gets the resource limit of the current process
+    ///
+    /// # Returns
+    /// `None` if the process isn't restricted by a resource limit
+    pub fn current_process() -> Result<Option<Self>, u32> {
+        super::get_process_resource_limit().map(|handle| if handle == 0 { None } else { Some(Self { handle }) })
+    }
+
+    /// Takes ownership of a resource limit handle
+    ///
+    /// # Safety
+    /// The handle has to be a resource limit which isn't closed by anything else
+    pub unsafe fn from_raw(handle: u32) -> Self {
+        Self { handle }
+    }
+
+    /// Gives up ownership of the handle without closing it
+    pub fn into_raw(self) -> u32 {
+        let handle = self.handle;
+        std::mem::forget(self);
+        handle
+    }
+
+    /// Gets the kernel handle of the resource limit
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Gets the most of a resource which can be in use
+    pub fn limit(&self, resource: LimitableResource) -> Result<i64, u32> {
+        super::get_resource_limit_limit(self.handle, resource.as_raw())
+    }
+
+    /// Gets how much of a resource is in use
+    pub fn current(&self, resource: LimitableResource) -> Result<i64, u32> {
+        super::get_resource_limit_current(self.handle, resource.as_raw())
+    }
+
+    /// Gets how much of a resource can still be used before reaching the limit
+    pub fn available(&self, resource: LimitableResource) -> Result<i64, u32> {
+        Ok(self.limit(resource)? - self.current(resource)?)
+    }
+
+    /// Sets the most of a resource which can be in use, which can't be lower than the current usage
+    pub fn set_limit(&self, resource: LimitableResource, value: i64) -> Result<(), u32> {
+        super::set_resource_limit_limit(self.handle, resource.as_raw(), value)
+    }
+
+    /// Gets the limit and usage of every resource at once, see [`ResourceLimitSnapshot`]
+    pub fn snapshot(&self) -> Result<ResourceLimitSnapshot, u32> {
+        let mut snapshot = ResourceLimitSnapshot::default();
+        for resource in LimitableResource::ALL {
+            snapshot.usages[resource as usize] = ResourceUsage {
+                limit: self.limit(resource)?,
+                current: self.current(resource)?,
+            };
+        }
+        Ok(snapshot)
+    }
+}
+
+impl Drop for ResourceLimit {
+    fn drop(&mut self) {
+        let _ = super::close_handle(self.handle);
+    }
+}
+
+/// The limit and usage of a resource at one point in time
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub limit: i64,
+    pub current: i64,
+}
+
+impl ResourceUsage {
+    /// Gets how much of the resource could still be used
+    pub fn available(&self) -> i64 {
+        self.limit - self.current
+    }
+}
+
+/// The limits and usage of every resource, for diagnostics such as crash reports.
+///
+/// Its [`Display`](fmt::Display) implementation writes one resource per line:
+/// ```text
+/// Physical memory: 0x1a2b000 / 0x1c800000 bytes
+/// Threads: 12 / 96
+/// ```
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimitSnapshot {
+    usages: [ResourceUsage; 5],
+}
+
+impl ResourceLimitSnapshot {
+    /// Takes a snapshot of the current process's resource limit
+    ///
+    /// # Returns
+    /// `None` if the process isn't restricted by a resource limit
+    pub fn current_process() -> Result<Option<Self>, u32> {
+        ResourceLimit::current_process()?.map(|limit| limit.snapshot()).transpose()
+    }
+
+    /// Gets the limit and usage of a resource
+    pub fn get(&self, resource: LimitableResource) -> ResourceUsage {
+        self.usages[resource as usize]
+    }
+
+    /// Iterates over every resource along with its limit and usage
+    pub fn iter(&self) -> impl Iterator<Item = (LimitableResource, ResourceUsage)> + '_ {
+        LimitableResource::ALL.iter().map(move |&resource| (resource, self.get(resource)))
+    }
+}
+
+impl fmt::Display for ResourceLimitSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (resource, usage)) in self.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            match resource {
+                LimitableResource::PhysicalMemory => {
+                    write!(f, "{}: {:#x} / {:#x} bytes", resource, usage.current, usage.limit)?
+                }
+                _ => write!(f, "{}: {} / {}", resource, usage.current, usage.limit)?,
+            }
+        }
+        Ok(())
+    }
+}
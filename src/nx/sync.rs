@@ -0,0 +1,53 @@
+//! Synchronization primitives built directly on the kernel's arbitration SVCs.
+//!
+//! These don't rely on nn::os or on std being initialized, so they can be used from hooks which
+//! run early, and from threads created with [`super::thread`]. None of them are poisoned by panics.
+//!
+//! The lock word of a [`RawMutex`] uses the same format as the SDK's internal critical sections and
+//! libnx's `Mutex`: zero when unlocked, otherwise the owner's thread handle with bit 30 set when
+//! there are waiters. Threads whose handle can't be found, see [`super::get_current_thread_handle`],
+//! still work but yield instead of sleeping while they wait for a lock.
+use std::time::Duration;
+
+use super::time::Instant;
+
+mod condvar;
+mod event;
+mod mutex;
+mod rwlock;
+mod semaphore;
+
+pub use condvar::*;
+pub use event::*;
+pub use mutex::*;
+pub use rwlock::*;
+pub use semaphore::*;
+
+/// Tracks how much of a timeout is left across several waits
+struct Deadline {
+    end: Option<Instant>,
+}
+
+impl Deadline {
+    fn new(timeout: Option<Duration>) -> Self {
+        let now = Instant::now();
+        Self { end: timeout.map(|timeout| now + timeout) }
+    }
+
+    /// Gets the time left, which is `None` when waiting forever
+    fn remaining(&self) -> Option<Duration> {
+        self.end.map(|end| end.duration_since(Instant::now()))
+    }
+
+    fn has_passed(&self) -> bool {
+        self.end.is_some_and(|end| Instant::now() >= end)
+    }
+}
+
+/// Converts an optional timeout into the nanoseconds the kernel expects, where -1 waits forever
+pub(super) fn timeout_nanoseconds(timeout: Option<Duration>) -> i64 {
+    match timeout {
+        Some(timeout) => timeout.as_nanos().min(i64::MAX as u128) as i64,
+        None => -1,
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::sync::atomic::AtomicU32;
+use std::time::Duration;
+
+use super::mutex::{current_thread_tag, MutexGuard, RawMutex};
+use super::{timeout_nanoseconds, Deadline};
+
+/// A condition variable, like [`std::sync::Condvar`] but used with [`super::Mutex`]
+pub struct Condvar {
+    key: AtomicU32,
+}
+
+impl Condvar {
+    /// Creates a condition variable with no waiters
+    pub const fn new() -> Self {
+        Self { key: AtomicU32::new(0) }
+    }
+
+    /// Unlocks the mutex and waits for the condition variable to be notified, then locks the
+    /// mutex again. Like any condition variable, this can wake up spuriously.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.wait_as(guard.raw(), guard.tag(), None);
+        guard
+    }
+
+    /// Waits until the condition returns false, see [`Condvar::wait`]
+    pub fn wait_while<'a, T, F>(&self, mut guard: MutexGuard<'a, T>, mut condition: F) -> MutexGuard<'a, T>
+    where
+        T: ?Sized,
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// Waits for the condition variable to be notified for at most the provided duration
+    ///
+    /// # Returns
+    /// The guard of the relocked mutex, and whether the wait timed out
+    pub fn wait_timeout<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>, timeout: Duration) -> (MutexGuard<'a, T>, bool) {
+        let timed_out = self.wait_as(guard.raw(), guard.tag(), Some(timeout));
+        (guard, timed_out)
+    }
+
+    /// Waits until the condition returns false, or until the timeout runs out
+    ///
+    /// # Returns
+    /// The guard of the relocked mutex, and whether the wait timed out with the condition still true
+    pub fn wait_timeout_while<'a, T, F>(&self, mut guard: MutexGuard<'a, T>, timeout: Duration, mut condition: F) -> (MutexGuard<'a, T>, bool)
+    where
+        T: ?Sized,
+        F: FnMut(&mut T) -> bool,
+    {
+        let deadline = Deadline::new(Some(timeout));
+        while condition(&mut *guard) {
+            if deadline.has_passed() {
+                return (guard, true);
+            }
+            guard = self.wait_timeout(guard, deadline.remaining().unwrap_or_default()).0;
+        }
+        (guard, false)
+    }
+
+    /// Waits for the condition variable with a raw mutex, see [`Condvar::wait_timeout`]
+    ///
+    /// # Safety
+    /// The mutex has to be locked by the current thread
+    ///
+    /// # Returns
+    /// Whether the wait timed out
+    pub unsafe fn wait_raw(&self, mutex: &RawMutex, timeout: Option<Duration>) -> bool {
+        self.wait_as(mutex, current_thread_tag(), timeout)
+    }
+
+    pub(super) fn wait_as(&self, mutex: &RawMutex, tag: u32, timeout: Option<Duration>) -> bool {
+        let result = unsafe {
+            crate::nx::wait_process_wide_key_atomic(mutex.as_ptr(), self.key.as_ptr(), tag, timeout_nanoseconds(timeout))
+        };
+
+        // The kernel only hands the mutex back after a notification, and only if the owner's tag is
+        // a handle, so otherwise it has to be locked again here
+        if !mutex.is_owned_by(tag) {
+            mutex.lock_as(tag);
+        }
+
+        result == Err(crate::nx::RESULT_TIMED_OUT)
+    }
+
+    /// Wakes up one thread waiting on the condition variable
+    pub fn notify_one(&self) {
+        unsafe { crate::nx::signal_process_wide_key(self.key.as_ptr(), 1) }
+    }
+
+    /// Wakes up every thread waiting on the condition variable
+    pub fn notify_all(&self) {
+        unsafe { crate::nx::signal_process_wide_key(self.key.as_ptr(), -1) }
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
+
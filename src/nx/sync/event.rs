@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use super::{Condvar, Deadline, Mutex};
+
+/// Whether an [`Event`] clears itself when a waiting thread is woken up
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClearMode {
+    /// The event stays signaled until [`Event::clear`] is called, waking every waiting thread
+    Manual,
+
+    /// The event is cleared as soon as one waiting thread is woken up
+    Auto,
+}
+
+/// An event which threads can wait to be signaled, like `nn::os::EventType` but without a
+/// kernel object. Use [`crate::nx::event::WritableEvent`] for events that are shared through handles.
+#[derive(Debug)]
+pub struct Event {
+    signaled: Mutex<bool>,
+    condvar: Condvar,
+    clear_mode: ClearMode,
+}
+
+impl Event {
+    /// Creates an event
+    ///
+    /// # Arguments
+    /// * `signaled` - Whether the event starts out signaled
+    /// * `clear_mode` - Whether the event clears itself when a thread is woken up
+    pub const fn new(signaled: bool, clear_mode: ClearMode) -> Self {
+        Self {
+            signaled: Mutex::new(signaled),
+            condvar: Condvar::new(),
+            clear_mode,
+        }
+    }
+
+    /// Signals the event, waking up one waiting thread if it clears automatically or every
+    /// waiting thread otherwise
+    pub fn signal(&self) {
+        let mut signaled = self.signaled.lock();
+        *signaled = true;
+        match self.clear_mode {
+            ClearMode::Auto => self.condvar.notify_one(),
+            ClearMode::Manual => self.condvar.notify_all(),
+        }
+    }
+
+    /// Clears the event
+    pub fn clear(&self) {
+        *self.signaled.lock() = false;
+    }
+
+    /// Checks whether the event is signaled, without clearing it
+    pub fn is_signaled(&self) -> bool {
+        *self.signaled.lock()
+    }
+
+    /// Waits for the event to be signaled
+    pub fn wait(&self) {
+        self.wait_until(Deadline::new(None));
+    }
+
+    /// Checks whether the event is signaled without waiting, clearing it if it clears automatically
+    pub fn try_wait(&self) -> bool {
+        self.wait_until(Deadline::new(Some(Duration::ZERO)))
+    }
+
+    /// Waits for the event to be signaled for at most the provided duration
+    ///
+    /// # Returns
+    /// Whether the event was signaled
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        self.wait_until(Deadline::new(Some(timeout)))
+    }
+
+    fn wait_until(&self, deadline: Deadline) -> bool {
+        let mut signaled = self.signaled.lock();
+        while !*signaled {
+            if deadline.has_passed() {
+                return false;
+            }
+            signaled = match deadline.remaining() {
+                Some(remaining) => self.condvar.wait_timeout(signaled, remaining).0,
+                None => self.condvar.wait(signaled),
+            };
+        }
+
+        if self.clear_mode == ClearMode::Auto {
+            *signaled = false;
+        }
+        true
+    }
+}
@@ -0,0 +1,239 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{fence, AtomicU32, Ordering};
+
+/// The bit of a lock word which is set when there are threads waiting for the lock
+const WAIT_MASK: u32 = 0x4000_0000;
+
+/// The bit of a lock word which marks a tag that isn't a handle, which the kernel never accepts
+const NOT_A_HANDLE: u32 = 0x8000_0000;
+
+/// Yields to threads of any priority on any core, so a lock owner can always make progress
+const YIELD_TO_ANY_THREAD: i64 = -2;
+
+/// Gets the value that the current thread writes to a lock word when it owns it
+pub(super) fn current_thread_tag() -> u32 {
+    match crate::nx::get_current_thread_handle() {
+        Some(handle) => handle,
+        None => {
+            let id = crate::nx::get_thread_id(crate::nx::CURRENT_THREAD_HANDLE).unwrap_or(0) as u32;
+            NOT_A_HANDLE | (id & !(NOT_A_HANDLE | WAIT_MASK)) | 1
+        }
+    }
+}
+
+/// A mutex which isn't tied to any data, with the same layout as an SDK internal critical section
+#[repr(transparent)]
+pub struct RawMutex {
+    tag: AtomicU32,
+}
+
+impl RawMutex {
+    /// Creates an unlocked mutex
+    pub const fn new() -> Self {
+        Self { tag: AtomicU32::new(0) }
+    }
+
+    /// Reinterprets a lock word, such as the one inside of an `nn::os::MutexType`, as a mutex
+    ///
+    /// # Safety
+    /// `tag` has to point to a lock word which lives for `'a` and is only used as a lock
+    pub unsafe fn from_ptr<'a>(tag: *mut u32) -> &'a Self {
+        &*(tag as *const Self)
+    }
+
+    /// Gets the address of the lock word
+    pub fn as_ptr(&self) -> *mut u32 {
+        self.tag.as_ptr()
+    }
+
+    /// Locks the mutex, waiting for it to be unlocked if another thread owns it
+    pub fn lock(&self) {
+        self.lock_as(current_thread_tag())
+    }
+
+    pub(super) fn lock_as(&self, tag: u32) {
+        loop {
+            let value = self.tag.load(Ordering::Relaxed);
+            if value == 0 {
+                if self.tag.compare_exchange_weak(0, tag, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+                    return;
+                }
+                continue;
+            }
+
+            if value & WAIT_MASK == 0
+                && self.tag.compare_exchange_weak(value, value | WAIT_MASK, Ordering::Relaxed, Ordering::Relaxed).is_err()
+            {
+                continue;
+            }
+
+            // The kernel hands the lock over when the owner unlocks it, but refuses to wait
+            // straight away if the owner's tag isn't a handle
+            if unsafe { crate::nx::arbitrate_lock(value & !WAIT_MASK, self.as_ptr(), tag) }.is_err() {
+                crate::nx::sleep_thread(YIELD_TO_ANY_THREAD);
+            }
+
+            if self.tag.load(Ordering::Acquire) & !WAIT_MASK == tag {
+                return;
+            }
+        }
+    }
+
+    /// Locks the mutex if no other thread owns it
+    pub fn try_lock(&self) -> bool {
+        self.tag
+            .compare_exchange(0, current_thread_tag(), Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Unlocks the mutex
+    ///
+    /// # Safety
+    /// The mutex has to be locked by the current thread
+    pub unsafe fn unlock(&self) {
+        self.unlock_as(current_thread_tag())
+    }
+
+    pub(super) unsafe fn unlock_as(&self, tag: u32) {
+        if self.tag.compare_exchange(tag, 0, Ordering::Release, Ordering::Relaxed).is_err() {
+            // There are waiters, so the kernel picks which one gets the lock next
+            fence(Ordering::Release);
+            let _ = crate::nx::arbitrate_unlock(self.as_ptr());
+        }
+    }
+
+    /// Checks whether the current thread owns the mutex
+    pub fn is_locked_by_current_thread(&self) -> bool {
+        self.is_owned_by(current_thread_tag())
+    }
+
+    pub(super) fn is_owned_by(&self, tag: u32) -> bool {
+        self.tag.load(Ordering::Acquire) & !WAIT_MASK == tag
+    }
+}
+
+impl Default for RawMutex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RawMutex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawMutex")
+            .field("tag", &format_args!("{:#x}", self.tag.load(Ordering::Relaxed)))
+            .finish()
+    }
+}
+
+/// A mutual exclusion lock protecting some data, like [`std::sync::Mutex`] but without poisoning
+pub struct Mutex<T: ?Sized> {
+    raw: RawMutex,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Creates an unlocked mutex protecting the value
+    pub const fn new(value: T) -> Self {
+        Self {
+            raw: RawMutex::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the mutex, returning the value it protected
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Locks the mutex, waiting for it to be unlocked if another thread owns it
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        let tag = current_thread_tag();
+        self.raw.lock_as(tag);
+        MutexGuard { mutex: self, tag, _not_send: PhantomData }
+    }
+
+    /// Locks the mutex if no other thread owns it
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        let tag = current_thread_tag();
+        self.raw
+            .tag
+            .compare_exchange(0, tag, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard { mutex: self, tag, _not_send: PhantomData })
+    }
+
+    /// Gets the value without locking, which is safe as the mutex is borrowed mutably
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Gets the raw mutex which protects the value
+    pub fn raw(&self) -> &RawMutex {
+        &self.raw
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_struct("Mutex").field("data", &&*guard).finish(),
+            None => f.debug_struct("Mutex").field("data", &format_args!("<locked>")).finish(),
+        }
+    }
+}
+
+/// Keeps a [`Mutex`] locked until it is dropped
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+    tag: u32,
+
+    // The lock is owned by a thread, so it has to be unlocked on the same thread
+    _not_send: PhantomData<*const ()>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    pub(super) fn raw(&self) -> &'a RawMutex {
+        &self.mutex.raw
+    }
+
+    pub(super) fn tag(&self) -> u32 {
+        self.tag
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        unsafe { self.mutex.raw.unlock_as(self.tag) }
+    }
+}
@@ -0,0 +1,166 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use super::{Condvar, Mutex};
+
+#[derive(Default)]
+struct State {
+    readers: usize,
+    writer: bool,
+    waiting_writers: usize,
+}
+
+/// A reader-writer lock, like [`std::sync::RwLock`] but without poisoning. Writers are preferred,
+/// so new readers wait while a writer is waiting for the lock.
+pub struct RwLock<T: ?Sized> {
+    state: Mutex<State>,
+    readers: Condvar,
+    writers: Condvar,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    /// Creates an unlocked lock protecting the value
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: Mutex::new(State { readers: 0, writer: false, waiting_writers: 0 }),
+            readers: Condvar::new(),
+            writers: Condvar::new(),
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the lock, returning the value it protected
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Locks for reading, waiting while a writer owns the lock or is waiting for it
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let mut state = self.readers.wait_while(self.state.lock(), |state| state.writer || state.waiting_writers != 0);
+        state.readers += 1;
+        RwLockReadGuard { lock: self }
+    }
+
+    /// Locks for reading if no writer owns the lock or is waiting for it
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let mut state = self.state.lock();
+        if state.writer || state.waiting_writers != 0 {
+            return None;
+        }
+        state.readers += 1;
+        Some(RwLockReadGuard { lock: self })
+    }
+
+    /// Locks for writing, waiting for every reader and writer to unlock it
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let mut state = self.state.lock();
+        state.waiting_writers += 1;
+        let mut state = self.writers.wait_while(state, |state| state.writer || state.readers != 0);
+        state.waiting_writers -= 1;
+        state.writer = true;
+        RwLockWriteGuard { lock: self }
+    }
+
+    /// Locks for writing if no reader or writer owns the lock
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let mut state = self.state.lock();
+        if state.writer || state.readers != 0 {
+            return None;
+        }
+        state.writer = true;
+        Some(RwLockWriteGuard { lock: self })
+    }
+
+    /// Gets the value without locking, which is safe as the lock is borrowed mutably
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    fn unlock_read(&self) {
+        let mut state = self.state.lock();
+        state.readers -= 1;
+        if state.readers == 0 && state.waiting_writers != 0 {
+            self.writers.notify_one();
+        }
+    }
+
+    fn unlock_write(&self) {
+        let mut state = self.state.lock();
+        state.writer = false;
+        if state.waiting_writers != 0 {
+            self.writers.notify_one();
+        } else {
+            self.readers.notify_all();
+        }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.try_read() {
+            Some(guard) => f.debug_struct("RwLock").field("data", &&*guard).finish(),
+            None => f.debug_struct("RwLock").field("data", &format_args!("<locked>")).finish(),
+        }
+    }
+}
+
+/// Keeps a [`RwLock`] locked for reading until it is dropped
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockReadGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_read()
+    }
+}
+
+/// Keeps a [`RwLock`] locked for writing until it is dropped
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Sync for RwLockWriteGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.unlock_write()
+    }
+}
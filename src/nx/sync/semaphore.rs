@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use super::{Condvar, Deadline, Mutex};
+
+/// A counting semaphore, which threads can acquire while its count is above zero
+#[derive(Debug)]
+pub struct Semaphore {
+    count: Mutex<usize>,
+    condvar: Condvar,
+}
+
+impl Semaphore {
+    /// Creates a semaphore with the provided count
+    pub const fn new(count: usize) -> Self {
+        Self {
+            count: Mutex::new(count),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Waits for the count to be above zero, then decrements it
+    pub fn acquire(&self) {
+        self.acquire_until(Deadline::new(None));
+    }
+
+    /// Decrements the count if it is above zero, without waiting
+    pub fn try_acquire(&self) -> bool {
+        self.acquire_until(Deadline::new(Some(Duration::ZERO)))
+    }
+
+    /// Waits for at most the provided duration for the count to be above zero, then decrements it
+    ///
+    /// # Returns
+    /// Whether the semaphore was acquired
+    pub fn acquire_timeout(&self, timeout: Duration) -> bool {
+        self.acquire_until(Deadline::new(Some(timeout)))
+    }
+
+    fn acquire_until(&self, deadline: Deadline) -> bool {
+        let mut count = self.count.lock();
+        while *count == 0 {
+            if deadline.has_passed() {
+                return false;
+            }
+            count = match deadline.remaining() {
+                Some(remaining) => self.condvar.wait_timeout(count, remaining).0,
+                None => self.condvar.wait(count),
+            };
+        }
+
+        *count -= 1;
+        true
+    }
+
+    /// Increments the count, waking up a waiting thread
+    pub fn release(&self) {
+        *self.count.lock() += 1;
+        self.condvar.notify_one();
+    }
+
+    /// Gets the current count
+    pub fn available(&self) -> usize {
+        *self.count.lock()
+    }
+}
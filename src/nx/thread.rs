@@ -0,0 +1,341 @@
+//! Kernel threads which don't depend on nn::os.
+//!
+//! Threads are created directly with `svcCreateThread`, so they have no `nn::os::ThreadType`.
+//! While the closure runs, the thread is given a zeroed stand-in for one, which is where the SDK
+//! keeps the values of thread local keys, and where [`super::get_current_stack_bounds`] finds
+//! the thread's stack. That is enough for `thread_local!` and for catching
+//! panics, which both go through those keys, but std APIs which rely on a real `ThreadType`,
+//! such as [`std::thread::current`], shouldn't be used from these threads.
+//!
+//! Thread local destructors are not run when the thread exits, so anything stored in a
+//! `thread_local!` by one of these threads is leaked.
+//!
+//! # Example
+//! ```ignore
+//! let worker = skyline::nx::thread::Builder::new()
+//!     .stack_size(0x10000)
+//!     .core(3)
+//!     .spawn(|| expensive_calculation())
+//!     .unwrap();
+//!
+//! let result = worker.join().unwrap();
+//! ```
+use std::alloc::{self, Layout};
+use std::any::Any;
+use std::cell::UnsafeCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{fence, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// The size of a thread's stack when none is provided
+pub const DEFAULT_STACK_SIZE: usize = 0x40000;
+
+/// The priority of a thread when none is provided, which is the same as the main thread's
+pub const DEFAULT_PRIORITY: i32 = 0x2C;
+
+/// The core which makes a thread run on the process's default core
+pub const DEFAULT_CORE: i32 = -2;
+
+/// Thread stacks are allocated in whole pages
+const STACK_ALIGNMENT: usize = 0x1000;
+
+/// The size of the stand-in `nn::os::ThreadType`, which is larger than the real one in every SDK version
+const THREAD_TYPE_SIZE: usize = 0x200;
+
+#[derive(Error, Debug)]
+pub enum ThreadError {
+    #[error("Failed to allocate a {0:#x} byte stack")]
+    StackAllocation(usize),
+
+    #[error("Failed to create the thread ({0:#x})")]
+    Create(u32),
+
+    #[error("Failed to start the thread ({0:#x})")]
+    Start(u32),
+
+    #[error("Failed to wait for the thread ({0:#x})")]
+    Wait(u32),
+
+    #[error("The thread exited without returning")]
+    NoResult,
+
+    #[error("The thread panicked")]
+    Panicked(Box<dyn Any + Send + 'static>),
+}
+
+/// The heap allocation that a thread runs on
+struct ThreadStack {
+    memory: *mut u8,
+    layout: Layout,
+}
+
+impl ThreadStack {
+    fn new(size: usize) -> Result<Self, ThreadError> {
+        let layout = Layout::from_size_align(size, STACK_ALIGNMENT).map_err(|_| ThreadError::StackAllocation(size))?;
+        let memory = unsafe { alloc::alloc(layout) };
+        if memory.is_null() {
+            return Err(ThreadError::StackAllocation(size));
+        }
+
+        Ok(Self { memory, layout })
+    }
+
+    fn top(&self) -> *mut u8 {
+        unsafe { self.memory.add(self.layout.size()) }
+    }
+}
+
+// The stack is only accessed by its thread, and freed by whoever owns the handle
+unsafe impl Send for ThreadStack {}
+
+impl Drop for ThreadStack {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.memory, self.layout) }
+    }
+}
+
+/// The zeroed stand-in for the `nn::os::ThreadType` that a thread runs its closure with,
+/// whose thread local key values start out empty and whose stack is filled in by the thread
+#[repr(C, align(16))]
+struct ThreadTypeStorage([u8; THREAD_TYPE_SIZE]);
+
+/// The result of a thread, which is written by the thread before it exits
+struct Packet<T> {
+    result: UnsafeCell<Option<std::thread::Result<T>>>,
+}
+
+// The result is only written by the thread, and only read after waiting for the thread to exit
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+struct ThreadStart<F, T> {
+    function: F,
+    packet: Arc<Packet<T>>,
+    handle: u32,
+    stack: *mut u8,
+    stack_size: usize,
+}
+
+extern "C" fn thread_entry<F, T>(arg: *mut u8) -> !
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let ThreadStart { function, packet, handle, stack, stack_size } = *unsafe { Box::from_raw(arg as *mut ThreadStart<F, T>) };
+
+    // Locks in nx::sync use the handle to find the thread which owns them
+    super::set_current_thread_handle(handle);
+
+    // std keeps thread locals, including the panic count that catching a panic relies on,
+    // in the thread local keys of the current ThreadType
+    let mut thread_type = Box::new(ThreadTypeStorage([0; THREAD_TYPE_SIZE]));
+    super::set_current_thread_type(thread_type.0.as_mut_ptr() as *mut super::ThreadType);
+    super::set_current_thread_stack(stack, stack_size);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(function));
+    unsafe {
+        *packet.result.get() = Some(result);
+    }
+    fence(Ordering::Release);
+
+    // Exiting doesn't unwind, so everything this thread owns has to be dropped first
+    super::set_current_thread_type(std::ptr::null_mut());
+    drop(thread_type);
+    drop(packet);
+    super::exit_thread()
+}
+
+/// Configuration for a new thread
+#[derive(Debug, Clone)]
+pub struct Builder {
+    stack_size: usize,
+    priority: i32,
+    core: i32,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self {
+            stack_size: DEFAULT_STACK_SIZE,
+            priority: DEFAULT_PRIORITY,
+            core: DEFAULT_CORE,
+        }
+    }
+}
+
+impl Builder {
+    /// Creates the default thread configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size of the thread's stack, which is rounded up to a whole page
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = size;
+        self
+    }
+
+    /// Sets the kernel priority of the thread, from 0 (highest) to 63 (lowest)
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the core the thread runs on, or [`DEFAULT_CORE`] for the process's default core
+    pub fn core(mut self, core: i32) -> Self {
+        self.core = core;
+        self
+    }
+
+    /// Spawns a new thread which runs the provided closure
+    pub fn spawn<F, T>(self, function: F) -> Result<JoinHandle<T>, ThreadError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let size = (self.stack_size.max(STACK_ALIGNMENT) + STACK_ALIGNMENT - 1) & !(STACK_ALIGNMENT - 1);
+        let stack = ThreadStack::new(size)?;
+
+        let packet = Arc::new(Packet { result: UnsafeCell::new(None) });
+        let start = Box::into_raw(Box::new(ThreadStart {
+            function,
+            packet: packet.clone(),
+            handle: 0,
+            stack: stack.memory,
+            stack_size: size,
+        }));
+
+        let created = unsafe {
+            super::create_thread(thread_entry::<F, T>, start as *mut u8, stack.top(), self.priority, self.core)
+        };
+
+        let handle = match created {
+            Ok(handle) => handle,
+            Err(e) => {
+                drop(unsafe { Box::from_raw(start) });
+                return Err(ThreadError::Create(e));
+            }
+        };
+
+        // The thread can't read its start info until it is started, so it is safe to write here
+        unsafe {
+            (*start).handle = handle;
+        }
+
+        if let Err(e) = super::start_thread(handle) {
+            let _ = super::close_handle(handle);
+            drop(unsafe { Box::from_raw(start) });
+            return Err(ThreadError::Start(e));
+        }
+
+        Ok(JoinHandle {
+            handle,
+            stack: Some(stack),
+            packet,
+            finished: false,
+        })
+    }
+}
+
+/// An owned handle to a thread, which detaches the thread when it is dropped.
+///
+/// A detached thread's stack can't be freed safely, so it is leaked if the thread is still running.
+pub struct JoinHandle<T> {
+    handle: u32,
+    stack: Option<ThreadStack>,
+    packet: Arc<Packet<T>>,
+    finished: bool,
+}
+
+impl<T> JoinHandle<T> {
+    /// Gets the kernel handle of the thread
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Gets the kernel id of the thread
+    pub fn id(&self) -> Result<u64, u32> {
+        super::get_thread_id(self.handle)
+    }
+
+    /// Sets the kernel priority of the thread, from 0 (highest) to 63 (lowest)
+    pub fn set_priority(&self, priority: u32) -> Result<(), u32> {
+        super::set_thread_priority(self.handle, priority)
+    }
+
+    /// Makes the thread's current or next wait return early, see [`super::event::WaitError::Cancelled`]
+    pub fn cancel_wait(&self) -> Result<(), u32> {
+        super::cancel_synchronization(self.handle)
+    }
+
+    /// Checks whether the thread has exited, without waiting for it
+    pub fn is_finished(&mut self) -> bool {
+        if !self.finished {
+            self.finished = super::wait_synchronization(&[self.handle], 0).is_ok();
+        }
+        self.finished
+    }
+
+    /// Waits for the thread to exit
+    ///
+    /// # Returns
+    /// The value returned by the thread's closure, or [`ThreadError::Panicked`] with the panic's payload
+    pub fn join(mut self) -> Result<T, ThreadError> {
+        super::wait_synchronization(&[self.handle], -1).map_err(ThreadError::Wait)?;
+        self.finished = true;
+        fence(Ordering::Acquire);
+
+        match unsafe { (*self.packet.result.get()).take() } {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(payload)) => Err(ThreadError::Panicked(payload)),
+            None => Err(ThreadError::NoResult),
+        }
+    }
+}
+
+/// Waiting on a thread waits for it to exit, but [`JoinHandle::join`] is needed to get its result
+impl<T> super::event::Waitable for JoinHandle<T> {
+    fn wait_handle(&self) -> u32 {
+        self.handle
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if !self.finished {
+            std::mem::forget(self.stack.take());
+        }
+        let _ = super::close_handle(self.handle);
+    }
+}
+
+/// Spawns a new thread with the default configuration, see [`Builder`]
+pub fn spawn<F, T>(function: F) -> Result<JoinHandle<T>, ThreadError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    Builder::new().spawn(function)
+}
+
+/// Puts the current thread to sleep for at least the provided duration
+pub fn sleep(duration: Duration) {
+    super::sleep_thread(duration.as_nanos().min(i64::MAX as u128) as i64)
+}
+
+/// Lets other threads on the same core run before the current thread continues
+pub fn yield_now() {
+    super::sleep_thread(0)
+}
+
+/// Gets the number of the core the current thread is running on
+pub fn current_core() -> u32 {
+    super::get_current_processor_number()
+}
+
+/// Gets the kernel id of the current thread
+pub fn current_id() -> u64 {
+    super::get_thread_id(super::CURRENT_THREAD_HANDLE).unwrap_or(0)
+}
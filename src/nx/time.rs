@@ -0,0 +1,186 @@
+//! Time measurement with the system tick counter.
+//!
+//! The counter is read directly from `CNTPCT_EL0`, which is what `svcGetSystemTick` returns, so
+//! reading it is cheap enough to time hook code. See [`profile`] for aggregating timings.
+//!
+//! # Example
+//! ```ignore
+//! use skyline::nx::time::Instant;
+//!
+//! let start = Instant::now();
+//! expensive_calculation();
+//! println!("Took {:?}", start.elapsed());
+//! ```
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+pub use std::time::Duration;
+
+mod profiler;
+
+pub use profiler::*;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A number of system ticks, which count up at [`Tick::frequency`] ticks per second
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tick(pub u64);
+
+impl Tick {
+    /// Reads the current value of the system tick counter
+    #[inline(always)]
+    pub fn now() -> Self {
+        let tick: u64;
+        unsafe {
+            std::arch::asm!("mrs {}, cntpct_el0", out(reg) tick, options(nomem, nostack));
+        }
+        Tick(tick)
+    }
+
+    /// Gets the number of ticks per second, which is 19.2MHz on the Switch
+    pub fn frequency() -> u64 {
+        let frequency: u64;
+        unsafe {
+            std::arch::asm!("mrs {}, cntfrq_el0", out(reg) frequency, options(nomem, nostack));
+        }
+        frequency.max(1)
+    }
+
+    /// Converts a duration into ticks, saturating if it doesn't fit
+    pub fn from_duration(duration: Duration) -> Self {
+        let ticks = duration.as_nanos() * Self::frequency() as u128 / NANOS_PER_SEC;
+        Tick(ticks.min(u64::MAX as u128) as u64)
+    }
+
+    /// Converts the ticks into a duration
+    pub fn to_duration(self) -> Duration {
+        let nanos = self.0 as u128 * NANOS_PER_SEC / Self::frequency() as u128;
+        Duration::new((nanos / NANOS_PER_SEC) as u64, (nanos % NANOS_PER_SEC) as u32)
+    }
+
+    /// Gets the number of ticks
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<Duration> for Tick {
+    fn from(duration: Duration) -> Self {
+        Self::from_duration(duration)
+    }
+}
+
+impl From<Tick> for Duration {
+    fn from(tick: Tick) -> Self {
+        tick.to_duration()
+    }
+}
+
+impl Add for Tick {
+    type Output = Tick;
+
+    fn add(self, other: Tick) -> Tick {
+        Tick(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Tick {
+    type Output = Tick;
+
+    fn sub(self, other: Tick) -> Tick {
+        Tick(self.0.saturating_sub(other.0))
+    }
+}
+
+impl fmt::Display for Tick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ticks", self.0)
+    }
+}
+
+/// A point in time measured with the system tick counter, like [`std::time::Instant`]. It is
+/// monotonic, and usable from any thread without depending on nn::os.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(Tick);
+
+impl Instant {
+    /// Gets the current point in time
+    #[inline(always)]
+    pub fn now() -> Self {
+        Instant(Tick::now())
+    }
+
+    /// Gets the point in time at which the system tick counter had the provided value
+    pub fn from_tick(tick: Tick) -> Self {
+        Instant(tick)
+    }
+
+    /// Gets the value of the system tick counter at this point in time
+    pub fn tick(self) -> Tick {
+        self.0
+    }
+
+    /// Gets the time passed since this point in time
+    pub fn elapsed(self) -> Duration {
+        Instant::now().duration_since(self)
+    }
+
+    /// Gets the time passed since an earlier point in time, or zero if it was later
+    pub fn duration_since(self, earlier: Instant) -> Duration {
+        (self.0 - earlier.0).to_duration()
+    }
+
+    /// Gets the time passed since an earlier point in time, or `None` if it was later
+    pub fn checked_duration_since(self, earlier: Instant) -> Option<Duration> {
+        self.0 .0.checked_sub(earlier.0 .0).map(|ticks| Tick(ticks).to_duration())
+    }
+
+    /// Gets the point in time a duration after this one, or `None` if it overflows
+    pub fn checked_add(self, duration: Duration) -> Option<Instant> {
+        let ticks = duration.as_nanos() * Tick::frequency() as u128 / NANOS_PER_SEC;
+        u64::try_from(ticks).ok().and_then(|ticks| self.0 .0.checked_add(ticks)).map(|tick| Instant(Tick(tick)))
+    }
+
+    /// Gets the point in time a duration before this one, or `None` if it is before boot
+    pub fn checked_sub(self, duration: Duration) -> Option<Instant> {
+        let ticks = duration.as_nanos() * Tick::frequency() as u128 / NANOS_PER_SEC;
+        u64::try_from(ticks).ok().and_then(|ticks| self.0 .0.checked_sub(ticks)).map(|tick| Instant(Tick(tick)))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + Tick::from_duration(duration))
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        Instant(self.0 - Tick::from_duration(duration))
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
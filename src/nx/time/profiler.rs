@@ -0,0 +1,139 @@
+use std::fmt::{self, Write};
+
+use super::{Duration, Instant, Tick};
+use crate::nx::sync::Mutex;
+
+static TIMINGS: Mutex<Vec<(&'static str, Timing)>> = Mutex::new(Vec::new());
+
+/// The timings recorded under one label by the profiler
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// How many times the label was timed
+    pub count: u64,
+
+    /// The time spent under the label in total
+    pub total: Tick,
+
+    /// The shortest time recorded
+    pub min: Tick,
+
+    /// The longest time recorded
+    pub max: Tick,
+}
+
+impl Timing {
+    /// Adds one recorded time to the timing
+    pub fn add(&mut self, ticks: Tick) {
+        self.min = if self.count == 0 { ticks } else { self.min.min(ticks) };
+        self.max = self.max.max(ticks);
+        self.total = self.total + ticks;
+        self.count += 1;
+    }
+
+    /// Gets the average time recorded
+    pub fn average(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => Tick(self.total.0 / count).to_duration(),
+        }
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} calls, {:?} total, {:?} average, {:?} min, {:?} max",
+            self.count,
+            self.total.to_duration(),
+            self.average(),
+            self.min.to_duration(),
+            self.max.to_duration()
+        )
+    }
+}
+
+/// Times the code from now until the returned scope is dropped, adding the time to the label's
+/// timing in the profiler.
+///
+/// # Example
+/// ```ignore
+/// #[skyline::hook(offset = 0x1234)]
+/// fn update_hook(ctx: u64) {
+///     let _scope = skyline::nx::time::profile("update");
+///     call_original!(ctx)
+/// }
+///
+/// // Later, such as once every few seconds
+/// skyline::nx::time::dump_profile();
+/// ```
+pub fn profile(label: &'static str) -> ProfileScope {
+    ProfileScope { label, start: Instant::now() }
+}
+
+/// Adds a time to the label's timing in the profiler
+pub fn record(label: &'static str, ticks: Tick) {
+    let mut timings = TIMINGS.lock();
+    match timings.iter_mut().find(|(other, _)| *other == label) {
+        Some((_, timing)) => timing.add(ticks),
+        None => {
+            let mut timing = Timing::default();
+            timing.add(ticks);
+            timings.push((label, timing));
+        }
+    }
+}
+
+/// Gets the timings recorded by the profiler, in the order their labels were first recorded
+pub fn profile_timings() -> Vec<(&'static str, Timing)> {
+    TIMINGS.lock().clone()
+}
+
+/// Clears every timing recorded by the profiler
+pub fn reset_profile() {
+    TIMINGS.lock().clear();
+}
+
+/// Writes one line per label recorded by the profiler
+pub fn write_profile(out: &mut dyn Write) -> fmt::Result {
+    for (label, timing) in profile_timings() {
+        writeln!(out, "{}: {}", label, timing)?;
+    }
+    Ok(())
+}
+
+/// Outputs the timings recorded by the profiler with `svcOutputDebugString`, one label at a time
+pub fn dump_profile() -> Result<(), u32> {
+    let mut line = String::new();
+    for (label, timing) in profile_timings() {
+        line.clear();
+        let _ = write!(line, "{}: {}", label, timing);
+        crate::nx::output_debug_string(&line)?;
+    }
+    Ok(())
+}
+
+/// Times the code until it is dropped, see [`profile`]
+#[must_use = "the scope records its time when it is dropped"]
+pub struct ProfileScope {
+    label: &'static str,
+    start: Instant,
+}
+
+impl ProfileScope {
+    /// Gets the label the time is recorded under
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// Gets the time passed since the scope started
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        record(self.label, Tick::now() - self.start.tick());
+    }
+}
@@ -0,0 +1,292 @@
+use std::ffi::CStr;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The size of the IPC message buffer at the start of every thread's local region
+pub const IPC_BUFFER_SIZE: usize = 0x100;
+
+/// The magic of the thread variables that libnx, and [`super::thread`], keep in the thread local region
+const THREAD_VARS_MAGIC: u32 = 0x21545624;
+
+/// The offset of the kernel handle in an `nn::os::ThreadType`, which is checked before it is trusted
+const THREAD_TYPE_HANDLE_OFFSET: usize = 0x1B0;
+
+/// The offset of the stack in an `nn::os::ThreadType`, which is followed by its size
+const THREAD_TYPE_STACK_OFFSET: usize = 0x50;
+
+const HANDLE_OFFSET_UNCHECKED: u8 = 0;
+const HANDLE_OFFSET_VALID: u8 = 1;
+const HANDLE_OFFSET_INVALID: u8 = 2;
+
+static THREAD_TYPE_HANDLE_STATE: AtomicU8 = AtomicU8::new(HANDLE_OFFSET_UNCHECKED);
+
+/// An opaque `nn::os::ThreadType`, which is only ever handled through a pointer
+#[repr(C)]
+pub struct ThreadType {
+    _private: [u8; 0],
+}
+
+/// The thread local region of a thread, which `tpidrro_el0` points to.
+///
+/// The kernel owns the fields up to `interrupt_flag`, and the SDK keeps a pointer to the
+/// `nn::os::ThreadType` of the thread at the end. Threads which weren't created by nn::os
+/// keep their handle in the same place that libnx does.
+#[repr(C)]
+pub struct ThreadLocalRegion {
+    /// The buffer that IPC requests and responses are written to
+    pub message_buffer: [u8; IPC_BUFFER_SIZE],
+
+    /// The number of nested requests to disable preemption of the thread
+    pub disable_counter: u16,
+
+    /// Set by the kernel when the thread would have been preempted while preemption was disabled
+    pub interrupt_flag: u16,
+
+    reserved: [u8; 0xDC],
+
+    thread_vars_magic: u32,
+    thread_vars_handle: u32,
+
+    reserved2: [u8; 0x10],
+
+    /// The `nn::os::ThreadType` of the thread, which is null for threads not created by nn::os
+    pub thread_type: *mut ThreadType,
+}
+
+extern "C" {
+    #[link_name = "_ZN2nn2os20GetThreadNamePointerEPKNS0_10ThreadTypeE"]
+    fn nn_os_get_thread_name_pointer(thread: *const ThreadType) -> *const u8;
+}
+
+/// Gets the thread local region of the current thread
+pub fn get_thread_local_region() -> *mut ThreadLocalRegion {
+    super::get_tls() as *mut ThreadLocalRegion
+}
+
+/// Gets the IPC message buffer of the current thread
+pub fn get_ipc_buffer() -> *mut u8 {
+    super::get_tls()
+}
+
+/// Gets the `nn::os::ThreadType` of the current thread, which is null for threads not created by nn::os
+pub fn get_current_thread_type() -> *mut ThreadType {
+    unsafe {
+        let region = get_thread_local_region();
+
+        // Threads created by libnx or nx::thread don't have a real ThreadType, even if
+        // nx::thread gave them a stand-in
+        if (*region).thread_vars_magic == THREAD_VARS_MAGIC {
+            std::ptr::null_mut()
+        } else {
+            (*region).thread_type
+        }
+    }
+}
+
+/// Gets a real handle to the current thread, as opposed to [`super::CURRENT_THREAD_HANDLE`].
+///
+/// The handle is only known for threads created by nn::os, [`super::thread`], or libnx.
+pub fn get_current_thread_handle() -> Option<u32> {
+    unsafe {
+        let region = get_thread_local_region();
+        if (*region).thread_vars_magic == THREAD_VARS_MAGIC {
+            return Some((*region).thread_vars_handle);
+        }
+
+        let thread = (*region).thread_type;
+        if thread.is_null() {
+            return None;
+        }
+
+        let handle = *((thread as *const u8).add(THREAD_TYPE_HANDLE_OFFSET) as *const u32);
+        match THREAD_TYPE_HANDLE_STATE.load(Ordering::Relaxed) {
+            HANDLE_OFFSET_VALID => Some(handle),
+            HANDLE_OFFSET_INVALID => None,
+            _ => {
+                // The layout of ThreadType is the same for every thread, so checking it once is enough
+                let id = super::get_thread_id(handle).ok();
+                let valid = id.is_some() && id == super::get_thread_id(super::CURRENT_THREAD_HANDLE).ok();
+                let state = if valid { HANDLE_OFFSET_VALID } else { HANDLE_OFFSET_INVALID };
+                THREAD_TYPE_HANDLE_STATE.store(state, Ordering::Relaxed);
+                valid.then_some(handle)
+            }
+        }
+    }
+}
+
+/// Stores the handle of a thread which wasn't created by nn::os in its thread local region
+pub(crate) fn set_current_thread_handle(handle: u32) {
+    unsafe {
+        let region = get_thread_local_region();
+        (*region).thread_vars_handle = handle;
+        (*region).thread_vars_magic = THREAD_VARS_MAGIC;
+    }
+}
+
+/// Sets the `nn::os::ThreadType` of a thread which wasn't created by nn::os, see [`super::thread`]
+pub(crate) fn set_current_thread_type(thread: *mut ThreadType) {
+    unsafe {
+        (*get_thread_local_region()).thread_type = thread;
+    }
+}
+
+/// Sets the stack in the stand-in `nn::os::ThreadType` that [`super::thread`] gives its threads
+pub(crate) fn set_current_thread_stack(stack: *mut u8, size: usize) {
+    unsafe {
+        let thread = (*get_thread_local_region()).thread_type as *mut u8;
+        *(thread.add(THREAD_TYPE_STACK_OFFSET) as *mut [usize; 2]) = [stack as usize, size];
+    }
+}
+
+/// Gets the bounds of the current thread's stack, as kept in its `nn::os::ThreadType`
+///
+/// # Returns
+/// The lowest address of the stack and the address just past its top, or `None` for threads
+/// which have no `ThreadType` to read them from
+pub fn get_current_stack_bounds() -> Option<(u64, u64)> {
+    unsafe {
+        // Threads created by nx::thread have their stack in the stand-in ThreadType as well
+        let thread = (*get_thread_local_region()).thread_type as *const u8;
+        if thread.is_null() {
+            return None;
+        }
+
+        let [stack, size] = *(thread.add(THREAD_TYPE_STACK_OFFSET) as *const [u64; 2]);
+        if stack == 0 || size == 0 {
+            return None;
+        }
+
+        Some((stack, stack.checked_add(size)?))
+    }
+}
+
+/// The size of the name buffer in an `nn::os::ThreadType`, including the null terminator
+pub const THREAD_NAME_SIZE: usize = 0x20;
+
+/// Gets the name that a thread was given when it was created by nn::os
+pub fn get_thread_name(thread: &ThreadType) -> Option<&str> {
+    let name = unsafe { nn_os_get_thread_name_pointer(thread) };
+    if name.is_null() {
+        return None;
+    }
+
+    unsafe { CStr::from_ptr(name as _) }.to_str().ok()
+}
+
+/// The name of a thread, copied out of its `nn::os::ThreadType` so that it outlives the thread
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct ThreadName {
+    bytes: [u8; THREAD_NAME_SIZE],
+    len: u8,
+}
+
+impl ThreadName {
+    /// Copies a name, cutting it at the last character which fits in [`THREAD_NAME_SIZE`] bytes
+    pub fn new(name: &str) -> Self {
+        let mut len = name.len().min(THREAD_NAME_SIZE);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        let mut bytes = [0; THREAD_NAME_SIZE];
+        bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Self { bytes, len: len as u8 }
+    }
+
+    /// Gets the name as a string slice
+    pub fn as_str(&self) -> &str {
+        // Only whole characters of a string are ever copied in
+        unsafe { std::str::from_utf8_unchecked(&self.bytes[..self.len as usize]) }
+    }
+}
+
+impl fmt::Debug for ThreadName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for ThreadName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A snapshot of the current thread's identity and scheduling state, for tagging logs and crash reports
+#[derive(Debug, Copy, Clone)]
+pub struct ThreadInfo {
+    /// The kernel id of the thread, which is unique within the system
+    pub id: u64,
+
+    /// A handle to the thread, which is [`super::CURRENT_THREAD_HANDLE`] if the real handle isn't known
+    pub handle: u32,
+
+    /// The name of the thread, if it was created by nn::os and given one
+    pub name: Option<ThreadName>,
+
+    /// The kernel priority of the thread, where lower values are scheduled first
+    pub priority: u32,
+
+    /// The core that the thread was running on when the snapshot was taken
+    pub core: u32,
+
+    /// The core that the thread prefers to run on
+    pub ideal_core: i32,
+
+    /// The cores that the thread is allowed to run on
+    pub affinity_mask: u64,
+
+    /// The `nn::os::ThreadType` of the thread, which is null for threads not created by nn::os
+    pub thread_type: *mut ThreadType,
+}
+
+impl ThreadInfo {
+    /// Takes a snapshot of the current thread
+    pub fn current() -> Self {
+        let handle = super::CURRENT_THREAD_HANDLE;
+        let thread_type = get_current_thread_type();
+        let (ideal_core, affinity_mask) = super::get_thread_core_mask(handle).unwrap_or((-1, 0));
+
+        Self {
+            id: super::get_thread_id(handle).unwrap_or(0),
+            handle: get_current_thread_handle().unwrap_or(handle),
+            name: unsafe { thread_type.as_ref() }
+                .and_then(get_thread_name)
+                .filter(|name| !name.is_empty())
+                .map(ThreadName::new),
+            priority: super::get_thread_priority(handle).unwrap_or(0),
+            core: super::get_current_processor_number(),
+            ideal_core,
+            affinity_mask,
+            thread_type,
+        }
+    }
+}
+
+impl fmt::Display for ThreadInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' (id: {:#x}, priority: {}, core: {})",
+            self.name.as_ref().map_or("<unnamed>", ThreadName::as_str),
+            self.id,
+            self.priority,
+            self.core
+        )
+    }
+}
+
+#[cfg(all(test, target_arch = "aarch64"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_name() {
+        assert_eq!(ThreadName::new("MainThread").as_str(), "MainThread");
+        assert_eq!(ThreadName::new(&"a".repeat(0x30)).as_str(), "a".repeat(THREAD_NAME_SIZE));
+
+        // The last character doesn't fit, so it's left out instead of split
+        let name = format!("{}é", "a".repeat(THREAD_NAME_SIZE - 1));
+        assert_eq!(ThreadName::new(&name).as_str(), "a".repeat(THREAD_NAME_SIZE - 1));
+    }
+}
@@ -84,6 +84,96 @@ impl ModuleObject {
         }
     }
 
+    /// Gets the combined size of the text, read-only, and read-write segments of the module
+    pub fn get_module_size(&self) -> Option<u64> {
+        let text = crate::nx::query_memory(self.module_base as u64).ok()?;
+        let rodata = crate::nx::query_memory(text.addr + text.size).ok()?;
+        let data = crate::nx::query_memory(rodata.addr + rodata.size).ok()?;
+
+        Some(text.size + rodata.size + data.size)
+    }
+
+    /// Gets the build id of the module, which uniquely identifies the ELF it was built from
+    ///
+    /// NROs keep their header mapped, so the id comes from the NRO header, zero-padded to 0x20 bytes
+    /// like NSO module ids. Modules which map their ELF header have the GNU build id note found
+    /// through their program headers. NSOs map neither, and neither their MOD0 header nor their
+    /// dynamic section point at the notes, so the end of the read-only segment, where the SDK's
+    /// linker script places the note, is scanned for it instead (the same way Atmosphère's creport
+    /// finds it).
+    pub fn get_build_id(&self) -> Option<&'static [u8]> {
+        const NRO_MAGIC: u32 = u32::from_le_bytes(*b"NRO0");
+        const NRO_BUILD_ID_OFFSET: usize = 0x40;
+        const NRO_BUILD_ID_SIZE: usize = 0x20;
+
+        let text = crate::nx::query_memory(self.module_base as u64).ok()?;
+        if (text.size as usize) < NRO_BUILD_ID_OFFSET + NRO_BUILD_ID_SIZE {
+            return None;
+        }
+
+        unsafe {
+            if *(self.module_base.add(0x10) as *const u32) == NRO_MAGIC {
+                return Some(std::slice::from_raw_parts(self.module_base.add(NRO_BUILD_ID_OFFSET), NRO_BUILD_ID_SIZE));
+            }
+
+            if *(self.module_base as *const [u8; 4]) == elf::ELFMAG {
+                return self.get_build_id_from_program_headers(text.size as usize);
+            }
+        }
+
+        // Only the last couple of pages are scanned, and the match closest to the end wins, since
+        // the note is the final section of the segment
+        const NSO_NOTE_SEARCH_SIZE: u64 = 0x2000;
+
+        let rodata = crate::nx::query_memory(text.addr + text.size).ok()?;
+        let search_size = rodata.size.min(NSO_NOTE_SEARCH_SIZE);
+        let tail = unsafe {
+            std::slice::from_raw_parts((rodata.addr + rodata.size - search_size) as *const u8, search_size as usize)
+        };
+
+        (0..tail.len().saturating_sub(0x10))
+            .step_by(4)
+            .rev()
+            .find_map(|offset| parse_build_id_note(&tail[offset..]).filter(|(_, size)| *size == 0).map(|(id, _)| id))
+    }
+
+    /// Finds the GNU build id note through the `PT_NOTE` program headers of a module whose ELF
+    /// header is mapped
+    unsafe fn get_build_id_from_program_headers(&self, text_size: usize) -> Option<&'static [u8]> {
+        let header = &*(self.module_base as *const elf::FileHeader64<LittleEndian>);
+        let phoff = header.e_phoff.get(LittleEndian) as usize;
+        let phnum = header.e_phnum.get(LittleEndian) as usize;
+        if phoff + phnum * std::mem::size_of::<elf::ProgramHeader64<LittleEndian>>() > text_size {
+            return None;
+        }
+
+        let program_headers = std::slice::from_raw_parts(
+            self.module_base.add(phoff) as *const elf::ProgramHeader64<LittleEndian>,
+            phnum,
+        );
+
+        program_headers
+            .iter()
+            .filter(|header| header.p_type.get(LittleEndian) == elf::PT_NOTE)
+            .find_map(|header| {
+                let mut notes = std::slice::from_raw_parts(
+                    self.module_base.add(header.p_vaddr.get(LittleEndian) as usize),
+                    header.p_filesz.get(LittleEndian) as usize,
+                );
+
+                // Walk the notes in order, as a segment can hold several of them
+                while !notes.is_empty() {
+                    let (build_id, size) = parse_build_id_note(notes)?;
+                    if size == 0 {
+                        return Some(build_id);
+                    }
+                    notes = notes.get(size..)?;
+                }
+
+                None
+            })
+    }
+
     pub fn contains_address(&self, address: u64) -> bool {
         let info = match crate::nx::query_memory(self.module_base as u64) {
             Ok(info) => info,
@@ -134,6 +224,53 @@ impl ModuleObject {
         
         None
     }
+
+    /// Finds the address of a symbol which is defined by this module
+    pub fn find_symbol_by_name(&self, name: &str) -> Option<u64> {
+        let symbols = unsafe {
+            std::slice::from_raw_parts(self.dynsym, self.hash_nchain_value as usize)
+        };
+
+        symbols.iter().find_map(|symbol| {
+            let shndx = symbol.st_shndx.get(LittleEndian);
+            if shndx == 0 || (shndx & 0xFF00) == 0xFF00 {
+                return None;
+            }
+
+            let symbol_name = unsafe {
+                std::ffi::CStr::from_ptr(self.dynstr.add(symbol.st_name.get(LittleEndian) as usize) as *const _)
+            };
+
+            if symbol_name.to_bytes() == name.as_bytes() {
+                Some(self.module_base as u64 + symbol.st_value.get(LittleEndian))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Parses the ELF note at the start of `notes`
+///
+/// # Returns
+/// The descriptor and a size of 0 if the note is a GNU build id, otherwise an empty descriptor and
+/// the size of the note, so that the next one can be parsed. `None` if the note is malformed.
+fn parse_build_id_note(notes: &'static [u8]) -> Option<(&'static [u8], usize)> {
+    let word = |offset: usize| notes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    let (name_size, desc_size, ty) = (word(0)? as usize, word(4)? as usize, word(8)?);
+    let desc_offset = 0xC + ((name_size + 3) & !3);
+    let size = desc_offset + ((desc_size + 3) & !3);
+
+    if name_size == 4 && ty == elf::NT_GNU_BUILD_ID && notes.get(0xC..0x10)? == b"GNU\0" {
+        if desc_size == 0 || desc_size > 0x20 {
+            return None;
+        }
+        return Some((notes.get(desc_offset..desc_offset + desc_size)?, 0));
+    }
+
+    notes.get(..size)?;
+    Some((&[], size))
 }
 
 #[repr(C)]
@@ -186,6 +323,19 @@ pub fn find_module_for_address(address: u64) -> Option<&'static crate::rtld::Mod
     unsafe { crate::rtld::MANUAL_LOAD_LIST.iter().find(|object| object.contains_address(address)) }
 }
 
+/// Gets an iterator over every module which has been loaded by the dynamic loader
+pub fn loaded_modules() -> impl Iterator<Item = &'static ModuleObject> {
+    unsafe {
+        AUTO_LOAD_LIST.iter().chain(MANUAL_LOAD_LIST.iter())
+    }
+}
+
+/// Finds the address of a symbol in the first loaded module which defines it, which is the
+/// same order the dynamic loader resolves symbols in
+pub fn find_symbol_by_name(name: &str) -> Option<u64> {
+    loaded_modules().find_map(|module| module.find_symbol_by_name(name))
+}
+
 pub fn find_module_by_name(name: &str) -> Option<&'static crate::rtld::ModuleObject> {
     let mut objects = unsafe {
         AUTO_LOAD_LIST.iter().chain(MANUAL_LOAD_LIST.iter())